@@ -0,0 +1,1208 @@
+use {
+    charm::state::{MintWindow, PassTerms},
+    charm_metadata::MetadataDocument,
+    charm_sdk::{
+        charm_instruction,
+        content::{hash_content, HttpFetcher},
+        pda::{
+            charm_pda, content_hash_address, edition_address, listing_address,
+            listing_treasury_address, listing_vault_address, membership_address, metadata_address,
+            platform_treasury_address, royalty_treasury_address, royalty_vault_address,
+        },
+        proceeds::{
+            create_platform_treasury, fetch_listing_treasury, fetch_platform_treasury,
+            withdraw_platform_fees, withdraw_proceeds,
+        },
+        resolver::resolve_buy,
+    },
+    clap::{
+        crate_description, crate_name, crate_version, App, AppSettings, Arg, ArgMatches, SubCommand,
+    },
+    serde::{Deserialize, Serialize},
+    solana_account_decoder::UiAccountEncoding,
+    solana_clap_utils::{
+        input_parsers::pubkey_of,
+        input_validators::{is_url, is_valid_pubkey, is_valid_signer},
+    },
+    solana_client::{
+        rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+    },
+    solana_program::{borsh::try_from_slice_unchecked, program_pack::Pack},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signature::{read_keypair_file, Keypair, Signature, Signer},
+        system_instruction::create_account,
+        system_program, sysvar,
+        transaction::Transaction,
+    },
+    spl_associated_token_account::{create_associated_token_account, get_associated_token_address},
+    spl_token::{
+        instruction::{initialize_mint, mint_to},
+        state::Mint,
+    },
+    spl_token_metadata::state::{Edition, Key, MasterEditionV2, Metadata, MAX_EDITION_LEN},
+    std::{fmt, fs, fs::File, process::exit},
+};
+
+/// Byte widths of the fixed-width fields packed into the `metadata` instruction data.
+const NAME_WIDTH: usize = 20;
+const SYMBOL_WIDTH: usize = 4;
+const URI_WIDTH: usize = 116;
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Display,
+    Json,
+}
+
+/// Description of a charm to be created, read from the `create-charm` spec file.
+#[derive(Deserialize)]
+struct CharmSpec {
+    name: String,
+    symbol: String,
+    uri: String,
+    /// Hand the master edition token over to the charm PDA so anyone can buy editions.
+    #[serde(default = "default_escrow")]
+    escrow: bool,
+    /// Collection to register the master edition in. The payer must be its authority.
+    #[serde(default)]
+    collection: Option<String>,
+    /// Hold every bought edition in charm custody for the buyer until you revoke it.
+    #[serde(default)]
+    soulbound: bool,
+    /// Sell editions as passes that expire and can be renewed.
+    #[serde(default)]
+    pass: Option<PassSpec>,
+    /// Create an open edition without max supply, sold only inside this window.
+    #[serde(default)]
+    open_edition: Option<WindowSpec>,
+    /// Currencies editions are sold in, each at its own price. Free when empty.
+    #[serde(default)]
+    prices: Vec<PriceSpec>,
+    /// Record the SHA-256 of the metadata JSON at `uri`, and of its image, on creation.
+    #[serde(default)]
+    hash_content: bool,
+}
+
+#[derive(Deserialize)]
+struct PriceSpec {
+    /// Mint of the currency, native SOL when not set.
+    #[serde(default)]
+    currency: Option<String>,
+    /// Price in the smallest unit of the currency, lamports for SOL.
+    amount: u64,
+}
+
+#[derive(Deserialize)]
+struct WindowSpec {
+    /// Unix timestamp sales start at.
+    start: i64,
+    /// Unix timestamp sales end at.
+    end: i64,
+}
+
+#[derive(Deserialize)]
+struct PassSpec {
+    /// Seconds a pass is valid for after each purchase or renewal.
+    duration: i64,
+    /// Lamports paid to the creator for each renewal.
+    renewal_price: u64,
+}
+
+fn default_escrow() -> bool {
+    true
+}
+
+#[derive(Serialize)]
+struct CreatedCharm {
+    mint: String,
+    metadata: String,
+    master_edition: String,
+    token_account: String,
+    escrowed: bool,
+    soulbound: bool,
+    signature: String,
+}
+
+impl fmt::Display for CreatedCharm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Created charm with mint {}", self.mint)?;
+        writeln!(f, "Metadata: {}", self.metadata)?;
+        writeln!(f, "Master edition: {}", self.master_edition)?;
+        writeln!(f, "Token account: {}", self.token_account)?;
+        writeln!(f, "Escrowed to charm PDA: {}", self.escrowed)?;
+        writeln!(f, "Soulbound editions: {}", self.soulbound)?;
+        write!(f, "Signature: {}", self.signature)
+    }
+}
+
+#[derive(Serialize)]
+struct BoughtEdition {
+    master_mint: String,
+    edition: u64,
+    mint: String,
+    metadata: String,
+    edition_account: String,
+    token_account: String,
+    soulbound: bool,
+    signature: String,
+}
+
+impl fmt::Display for BoughtEdition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Bought edition {} of {}", self.edition, self.master_mint)?;
+        writeln!(f, "Mint: {}", self.mint)?;
+        writeln!(f, "Metadata: {}", self.metadata)?;
+        writeln!(f, "Edition: {}", self.edition_account)?;
+        writeln!(f, "Token account: {}", self.token_account)?;
+        if self.soulbound {
+            writeln!(f, "Soulbound: held in custody for the buyer")?;
+        }
+        write!(f, "Signature: {}", self.signature)
+    }
+}
+
+#[derive(Serialize)]
+struct TokenMovement {
+    mint: String,
+    destination: String,
+    amount: u64,
+    signature: String,
+}
+
+impl fmt::Display for TokenMovement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Moved {} of {} to {}",
+            self.amount, self.mint, self.destination
+        )?;
+        write!(f, "Signature: {}", self.signature)
+    }
+}
+
+#[derive(Serialize)]
+struct CreatorView {
+    address: String,
+    verified: bool,
+    share: u8,
+}
+
+#[derive(Serialize)]
+struct CharmView {
+    mint: String,
+    metadata: String,
+    name: String,
+    symbol: String,
+    uri: String,
+    update_authority: String,
+    seller_fee_basis_points: u16,
+    primary_sale_happened: bool,
+    is_mutable: bool,
+    creators: Vec<CreatorView>,
+    edition_account: Option<String>,
+    /// `master` or `edition`, absent when the mint has no edition account.
+    edition_kind: Option<String>,
+    supply: Option<u64>,
+    max_supply: Option<u64>,
+    parent: Option<String>,
+    edition: Option<u64>,
+}
+
+impl fmt::Display for CharmView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Mint: {}", self.mint)?;
+        writeln!(f, "Metadata: {}", self.metadata)?;
+        writeln!(f, "Name: {}", self.name)?;
+        writeln!(f, "Symbol: {}", self.symbol)?;
+        writeln!(f, "Uri: {}", self.uri)?;
+        writeln!(f, "Update authority: {}", self.update_authority)?;
+        writeln!(
+            f,
+            "Seller fee basis points: {}",
+            self.seller_fee_basis_points
+        )?;
+        writeln!(f, "Primary sale happened: {}", self.primary_sale_happened)?;
+        writeln!(f, "Mutable: {}", self.is_mutable)?;
+        for creator in &self.creators {
+            writeln!(
+                f,
+                "Creator: {} (share {}, verified {})",
+                creator.address, creator.share, creator.verified
+            )?;
+        }
+        match self.edition_kind.as_deref() {
+            Some("master") => write!(
+                f,
+                "Master edition {}: supply {} of {:?}",
+                self.edition_account.as_deref().unwrap_or_default(),
+                self.supply.unwrap_or_default(),
+                self.max_supply
+            ),
+            Some(_) => write!(
+                f,
+                "Edition {} number {} of master edition {}",
+                self.edition_account.as_deref().unwrap_or_default(),
+                self.edition.unwrap_or_default(),
+                self.parent.as_deref().unwrap_or_default()
+            ),
+            None => write!(f, "No master edition or edition detected"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EditionView {
+    edition_account: String,
+    edition: u64,
+}
+
+#[derive(Serialize)]
+struct EditionList {
+    master_mint: String,
+    master_edition: String,
+    editions: Vec<EditionView>,
+}
+
+impl fmt::Display for EditionList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Found {} editions of {} (master edition {})",
+            self.editions.len(),
+            self.master_mint,
+            self.master_edition
+        )?;
+        for edition in &self.editions {
+            write!(f, "\n  #{}: {}", edition.edition, edition.edition_account)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct ValidatedMetadata {
+    name: String,
+    sha256: String,
+    /// Mint whose on-chain data the document was checked against.
+    mint: Option<String>,
+    /// File the canonical JSON was written to.
+    output_file: Option<String>,
+}
+
+impl fmt::Display for ValidatedMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Metadata of {} is valid", self.name)?;
+        if let Some(mint) = &self.mint {
+            write!(f, "\nMatches the on-chain data of {}", mint)?;
+        }
+        write!(f, "\nSHA-256 of the canonical JSON: {}", self.sha256)?;
+        if let Some(output_file) = &self.output_file {
+            write!(f, "\nCanonical JSON written to {}", output_file)?;
+        }
+        Ok(())
+    }
+}
+
+/// Packs name, symbol and uri into the fixed-width string the `metadata`
+/// instruction slices apart, padding each field with nulls like puffed metadata.
+fn pack_charm_data(spec: &CharmSpec) -> CliResult<String> {
+    let mut data = String::new();
+    for (field, value, width) in [
+        ("name", &spec.name, NAME_WIDTH),
+        ("symbol", &spec.symbol, SYMBOL_WIDTH),
+        ("uri", &spec.uri, URI_WIDTH),
+    ]
+    .iter()
+    {
+        if value.len() > *width {
+            return Err(format!("{} must be at most {} bytes", field, width).into());
+        }
+        data.push_str(value);
+        data.push_str(&"\0".repeat(width - value.len()));
+    }
+    Ok(data)
+}
+
+fn send(
+    client: &RpcClient,
+    payer: &Keypair,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> CliResult<Signature> {
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    let recent_blockhash = client.get_recent_blockhash()?.0;
+    transaction.sign(&signers.to_vec(), recent_blockhash);
+    Ok(client.send_and_confirm_transaction(&transaction)?)
+}
+
+/// Instructions creating a zero decimal mint owned by `authority` with one token in
+/// `owner`'s associated token account.
+fn single_token_mint_instructions(
+    client: &RpcClient,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    owner: &Pubkey,
+) -> CliResult<Vec<Instruction>> {
+    Ok(vec![
+        create_account(
+            payer,
+            mint,
+            client.get_minimum_balance_for_rent_exemption(Mint::LEN)?,
+            Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        initialize_mint(&spl_token::id(), mint, authority, Some(authority), 0)?,
+        create_associated_token_account(payer, owner, mint),
+        mint_to(
+            &spl_token::id(),
+            mint,
+            &get_associated_token_address(owner, mint),
+            authority,
+            &[authority],
+            1,
+        )?,
+    ])
+}
+
+/// Returns the associated token account of `owner`, plus the instruction creating it
+/// when it does not exist yet.
+fn associated_account(
+    client: &RpcClient,
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> (Pubkey, Option<Instruction>) {
+    let address = get_associated_token_address(owner, mint);
+    match client.get_account(&address) {
+        Ok(_) => (address, None),
+        Err(_) => (
+            address,
+            Some(create_associated_token_account(payer, owner, mint)),
+        ),
+    }
+}
+
+fn create_charm(
+    app_matches: &ArgMatches,
+    payer: Keypair,
+    client: RpcClient,
+) -> CliResult<CreatedCharm> {
+    let spec_path = app_matches.value_of("spec").unwrap();
+    let spec: CharmSpec = serde_json::from_reader(File::open(spec_path)?)?;
+    let data = pack_charm_data(&spec)?;
+
+    let mint = Keypair::new();
+    let metadata_key = metadata_address(&mint.pubkey());
+    let master_edition_key = edition_address(&mint.pubkey());
+    let token_account = get_associated_token_address(&payer.pubkey(), &mint.pubkey());
+
+    let collection = match &spec.collection {
+        Some(collection) => Some(collection.parse::<Pubkey>()?),
+        None => None,
+    };
+    let (metadata_hash, media_hash) = if spec.hash_content {
+        let (metadata_hash, media_hash) = hash_content(&HttpFetcher::new(), &spec.uri)?;
+        (Some(metadata_hash), media_hash)
+    } else {
+        (None, None)
+    };
+
+    let mut instructions = single_token_mint_instructions(
+        &client,
+        &payer.pubkey(),
+        &mint.pubkey(),
+        &payer.pubkey(),
+        &payer.pubkey(),
+    )?;
+    send(&client, &payer, &instructions, &[&payer, &mint])?;
+
+    instructions = vec![
+        charm_instruction(
+            charm::accounts::CreateMetadata {
+                payer: payer.pubkey(),
+                mint: mint.pubkey(),
+                mint_authority: payer.pubkey(),
+                update_authority: payer.pubkey(),
+                metadata_account: metadata_key,
+                master_edition_account: master_edition_key,
+                metadata_program: spl_token_metadata::id(),
+                token_program: spl_token::id(),
+                system_program: system_program::id(),
+                rent_program: sysvar::rent::id(),
+            },
+            charm::instruction::Metadata { data },
+        ),
+        charm_instruction(
+            charm::accounts::Edition {
+                payer: payer.pubkey(),
+                mint: mint.pubkey(),
+                mint_authority: payer.pubkey(),
+                update_authority: payer.pubkey(),
+                metadata_account: metadata_key,
+                master_edition_account: master_edition_key,
+                metadata_program: spl_token_metadata::id(),
+                token_program: spl_token::id(),
+                system_program: system_program::id(),
+                rent_program: sysvar::rent::id(),
+                content_hash: content_hash_address(&master_edition_key).0,
+            },
+            charm::instruction::Edition {
+                open: spec.open_edition.is_some(),
+                metadata_hash,
+                media_hash,
+            },
+        ),
+    ];
+    if let Some(collection) = collection {
+        let membership = membership_address(&collection, &mint.pubkey()).0;
+        instructions[1].accounts.extend(vec![
+            AccountMeta::new(collection, false),
+            AccountMeta::new(membership, false),
+        ]);
+    }
+    let (listing, listing_bump) = listing_address(&mint.pubkey());
+    instructions.push(charm_instruction(
+        charm::accounts::CreateListing {
+            metadata: metadata_key,
+            master_edition: master_edition_key,
+            listing,
+            authority: payer.pubkey(),
+            system_program: system_program::id(),
+        },
+        charm::instruction::CreateListing {
+            bump: listing_bump,
+            soulbound: spec.soulbound,
+            pass: spec.pass.as_ref().map(|pass| PassTerms {
+                duration: pass.duration,
+                renewal_price: pass.renewal_price,
+            }),
+            window: spec.open_edition.as_ref().map(|window| MintWindow {
+                start: window.start,
+                end: window.end,
+            }),
+        },
+    ));
+    let signature = send(&client, &payer, &instructions, &[&payer])?;
+
+    // Each currency is set up in its own transaction, all of them would not fit in one
+    // packet. Editions can not be bought before the master edition is escrowed last.
+    for price in &spec.prices {
+        let mut instructions = vec![];
+        let currency_mint = match &price.currency {
+            Some(currency) => currency.parse::<Pubkey>()?,
+            None => spl_token::native_mint::id(),
+        };
+        // Proceeds of every sale are held for the payer in the listing treasury.
+        let (listing_treasury, bump) = listing_treasury_address(&listing, &currency_mint);
+        let (vault, vault_bump) = listing_vault_address(&listing_treasury);
+        instructions.push(charm_instruction(
+            charm::accounts::CreateListingTreasury {
+                listing,
+                authority: payer.pubkey(),
+                currency_mint,
+                treasury: listing_treasury,
+                vault,
+                token_program: spl_token::id(),
+                system_program: system_program::id(),
+                rent: sysvar::rent::id(),
+            },
+            charm::instruction::CreateListingTreasury {
+                bump,
+                vault_bump,
+                beneficiary: payer.pubkey(),
+            },
+        ));
+        // Platform fees of the currency are held in a treasury shared by every listing.
+        if client
+            .get_account(&platform_treasury_address(&currency_mint).0)
+            .is_err()
+        {
+            instructions.push(create_platform_treasury(&payer.pubkey(), &currency_mint));
+        }
+        // The currency is only accepted once its treasury exists.
+        instructions.push(charm_instruction(
+            charm::accounts::SetListingPrice {
+                listing,
+                authority: payer.pubkey(),
+                treasury: listing_treasury,
+            },
+            charm::instruction::SetListingPrice {
+                currency_mint,
+                price: Some(price.amount),
+            },
+        ));
+        // Royalties of every sale are paid into the treasury of the master edition.
+        let (treasury, bump) = royalty_treasury_address(&master_edition_key, &currency_mint);
+        let (royalty_vault, vault_bump) = royalty_vault_address(&treasury);
+        instructions.push(charm_instruction(
+            charm::accounts::CreateRoyaltyTreasury {
+                payer: payer.pubkey(),
+                master_edition: master_edition_key,
+                master_metadata: metadata_key,
+                currency_mint,
+                treasury,
+                royalty_vault,
+                token_program: spl_token::id(),
+                system_program: system_program::id(),
+                rent: sysvar::rent::id(),
+            },
+            charm::instruction::CreateRoyaltyTreasury {
+                bump,
+                _vault_bump: vault_bump,
+            },
+        ));
+        send(&client, &payer, &instructions, &[&payer])?;
+    }
+    if spec.escrow {
+        let escrow = charm_instruction(
+            charm::accounts::ChangeOwnership {
+                signer: payer.pubkey(),
+                master_edition_account: token_account,
+                pda: charm_pda().0,
+                token_program: spl_token::id(),
+            },
+            charm::instruction::ChangeOwnership {},
+        );
+        send(&client, &payer, &[escrow], &[&payer])?;
+    }
+
+    Ok(CreatedCharm {
+        mint: mint.pubkey().to_string(),
+        metadata: metadata_key.to_string(),
+        master_edition: master_edition_key.to_string(),
+        token_account: token_account.to_string(),
+        escrowed: spec.escrow,
+        soulbound: spec.soulbound,
+        signature: signature.to_string(),
+    })
+}
+
+fn buy(app_matches: &ArgMatches, payer: Keypair, client: RpcClient) -> CliResult<BoughtEdition> {
+    let master_mint = pubkey_of(app_matches, "master").unwrap();
+    let edition = match app_matches.value_of("edition") {
+        Some(val) => Some(val.parse::<u64>()?),
+        None => None,
+    };
+
+    let currency = pubkey_of(app_matches, "currency");
+
+    let mut resolved = resolve_buy(
+        &client,
+        &master_mint,
+        &payer.pubkey(),
+        edition,
+        currency.as_ref(),
+    )?;
+    if let Some(referrer) = pubkey_of(app_matches, "referrer") {
+        resolved = resolved.with_referrer(&referrer);
+    }
+    let signature = send(
+        &client,
+        &payer,
+        &resolved.instructions(),
+        &[&payer, &resolved.new_mint],
+    )?;
+
+    Ok(BoughtEdition {
+        master_mint: master_mint.to_string(),
+        edition: resolved.edition,
+        mint: resolved.new_mint.pubkey().to_string(),
+        metadata: resolved.new_metadata.to_string(),
+        edition_account: resolved.new_edition.to_string(),
+        token_account: resolved.new_token_account.to_string(),
+        soulbound: resolved.soulbound,
+        signature: signature.to_string(),
+    })
+}
+
+fn faucet(app_matches: &ArgMatches, payer: Keypair, client: RpcClient) -> CliResult<TokenMovement> {
+    let mint = pubkey_of(app_matches, "mint").unwrap();
+    let recipient = pubkey_of(app_matches, "recipient").unwrap_or_else(|| payer.pubkey());
+    let (pda, bump) = charm_pda();
+
+    let (user_account, create_ix) = associated_account(&client, &payer.pubkey(), &recipient, &mint);
+    let mut instructions: Vec<Instruction> = create_ix.into_iter().collect();
+    instructions.push(charm_instruction(
+        charm::accounts::Faucet {
+            signer: payer.pubkey(),
+            mint,
+            user_account,
+            pda,
+            token_program: spl_token::id(),
+        },
+        charm::instruction::Faucet { bump },
+    ));
+    let signature = send(&client, &payer, &instructions, &[&payer])?;
+
+    let balance = client.get_token_account_balance(&user_account)?;
+    Ok(TokenMovement {
+        mint: mint.to_string(),
+        destination: user_account.to_string(),
+        amount: balance.amount.parse()?,
+        signature: signature.to_string(),
+    })
+}
+
+fn withdraw(
+    app_matches: &ArgMatches,
+    payer: Keypair,
+    client: RpcClient,
+) -> CliResult<TokenMovement> {
+    let master_mint = pubkey_of(app_matches, "master").unwrap();
+    let currency = pubkey_of(app_matches, "currency").unwrap_or_else(spl_token::native_mint::id);
+    let treasury = fetch_listing_treasury(&client, &master_mint, &currency)?;
+    let amount = match app_matches.value_of("amount") {
+        Some(val) => val.parse::<u64>()?,
+        None => treasury.available(),
+    };
+
+    let mut instructions = Vec::new();
+    let destination = if currency == spl_token::native_mint::id() {
+        treasury.beneficiary
+    } else {
+        let (destination, create_ix) =
+            associated_account(&client, &payer.pubkey(), &treasury.beneficiary, &currency);
+        instructions.extend(create_ix);
+        destination
+    };
+    instructions.push(withdraw_proceeds(&treasury, amount));
+    let signature = send(&client, &payer, &instructions, &[&payer])?;
+
+    Ok(TokenMovement {
+        mint: currency.to_string(),
+        destination: destination.to_string(),
+        amount,
+        signature: signature.to_string(),
+    })
+}
+
+fn withdraw_fees(
+    app_matches: &ArgMatches,
+    payer: Keypair,
+    client: RpcClient,
+) -> CliResult<TokenMovement> {
+    let currency = pubkey_of(app_matches, "currency").unwrap_or_else(spl_token::native_mint::id);
+    let treasury = fetch_platform_treasury(&client, &currency)?;
+    let amount = match app_matches.value_of("amount") {
+        Some(val) => val.parse::<u64>()?,
+        None => treasury.available(),
+    };
+
+    // Only the config fee wallet can withdraw, so the keypair is the fee wallet.
+    let fee_wallet = payer.pubkey();
+    let mut instructions = Vec::new();
+    let destination = if currency == spl_token::native_mint::id() {
+        fee_wallet
+    } else {
+        let (destination, create_ix) =
+            associated_account(&client, &fee_wallet, &fee_wallet, &currency);
+        instructions.extend(create_ix);
+        destination
+    };
+    instructions.push(withdraw_platform_fees(&treasury, &fee_wallet, amount));
+    let signature = send(&client, &payer, &instructions, &[&payer])?;
+
+    Ok(TokenMovement {
+        mint: currency.to_string(),
+        destination: destination.to_string(),
+        amount,
+        signature: signature.to_string(),
+    })
+}
+
+fn transfer(
+    app_matches: &ArgMatches,
+    payer: Keypair,
+    client: RpcClient,
+) -> CliResult<TokenMovement> {
+    let mint = pubkey_of(app_matches, "mint").unwrap();
+    let recipient = pubkey_of(app_matches, "to").unwrap();
+    let amount = app_matches.value_of("amount").unwrap().parse::<u64>()?;
+
+    let from = get_associated_token_address(&payer.pubkey(), &mint);
+    let (to, create_ix) = associated_account(&client, &payer.pubkey(), &recipient, &mint);
+    let mut instructions: Vec<Instruction> = create_ix.into_iter().collect();
+    instructions.push(charm_instruction(
+        charm::accounts::ProxyTransfer {
+            authority: payer.pubkey(),
+            from,
+            to,
+            token_program: spl_token::id(),
+        },
+        charm::instruction::ProxyTransfer { amount },
+    ));
+    let signature = send(&client, &payer, &instructions, &[&payer])?;
+
+    Ok(TokenMovement {
+        mint: mint.to_string(),
+        destination: to.to_string(),
+        amount,
+        signature: signature.to_string(),
+    })
+}
+
+fn show_charm(
+    app_matches: &ArgMatches,
+    _payer: Keypair,
+    client: RpcClient,
+) -> CliResult<CharmView> {
+    let mint = pubkey_of(app_matches, "mint").unwrap();
+    let metadata_key = metadata_address(&mint);
+    let metadata: Metadata = try_from_slice_unchecked(&client.get_account(&metadata_key)?.data)?;
+
+    let mut view = CharmView {
+        mint: mint.to_string(),
+        metadata: metadata_key.to_string(),
+        name: metadata.data.name.trim_end_matches('\0').to_owned(),
+        symbol: metadata.data.symbol.trim_end_matches('\0').to_owned(),
+        uri: metadata.data.uri.trim_end_matches('\0').to_owned(),
+        update_authority: metadata.update_authority.to_string(),
+        seller_fee_basis_points: metadata.data.seller_fee_basis_points,
+        primary_sale_happened: metadata.primary_sale_happened,
+        is_mutable: metadata.is_mutable,
+        creators: metadata
+            .data
+            .creators
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| CreatorView {
+                address: c.address.to_string(),
+                verified: c.verified,
+                share: c.share,
+            })
+            .collect(),
+        edition_account: None,
+        edition_kind: None,
+        supply: None,
+        max_supply: None,
+        parent: None,
+        edition: None,
+    };
+
+    let edition_key = edition_address(&mint);
+    if let Ok(account) = client.get_account(&edition_key) {
+        view.edition_account = Some(edition_key.to_string());
+        if account.data[0] == Key::MasterEditionV2 as u8 {
+            let master_edition: MasterEditionV2 = try_from_slice_unchecked(&account.data)?;
+            view.edition_kind = Some("master".to_owned());
+            view.supply = Some(master_edition.supply);
+            view.max_supply = master_edition.max_supply;
+        } else if account.data[0] == Key::EditionV1 as u8 {
+            let edition: Edition = try_from_slice_unchecked(&account.data)?;
+            view.edition_kind = Some("edition".to_owned());
+            view.parent = Some(edition.parent.to_string());
+            view.edition = Some(edition.edition);
+        }
+    }
+    Ok(view)
+}
+
+fn validate(
+    app_matches: &ArgMatches,
+    _payer: Keypair,
+    client: RpcClient,
+) -> CliResult<ValidatedMetadata> {
+    let file = app_matches.value_of("file").unwrap();
+    let document = MetadataDocument::from_json(&fs::read(file)?)?;
+    let mint = pubkey_of(app_matches, "mint");
+
+    let result = match mint {
+        Some(mint) => {
+            let metadata_key = metadata_address(&mint);
+            let metadata: Metadata =
+                try_from_slice_unchecked(&client.get_account(&metadata_key)?.data)?;
+            document.validate(&metadata.data)
+        }
+        None => document.check(),
+    };
+    if let Err(errors) = result {
+        let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        return Err(format!("invalid metadata: {}", errors.join("; ")).into());
+    }
+
+    let output_file = app_matches.value_of("out");
+    if let Some(path) = output_file {
+        fs::write(path, document.to_canonical_json())?;
+    }
+
+    Ok(ValidatedMetadata {
+        name: document.name.clone(),
+        sha256: document
+            .content_hash()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+        mint: mint.map(|mint| mint.to_string()),
+        output_file: output_file.map(str::to_owned),
+    })
+}
+
+fn list_editions(
+    app_matches: &ArgMatches,
+    _payer: Keypair,
+    client: RpcClient,
+) -> CliResult<EditionList> {
+    let master_mint = pubkey_of(app_matches, "master").unwrap();
+    let master_edition_key = edition_address(&master_mint);
+
+    let accounts = client.get_program_accounts_with_config(
+        &spl_token_metadata::id(),
+        RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::DataSize(MAX_EDITION_LEN as u64),
+                RpcFilterType::Memcmp(Memcmp {
+                    offset: 1,
+                    bytes: MemcmpEncodedBytes::Binary(master_edition_key.to_string()),
+                    encoding: None,
+                }),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        },
+    )?;
+    let mut editions: Vec<EditionView> = accounts
+        .into_iter()
+        .filter(|(_, account)| account.data[0] == Key::EditionV1 as u8)
+        .filter_map(|(key, account)| {
+            try_from_slice_unchecked::<Edition>(&account.data)
+                .ok()
+                .map(|edition| EditionView {
+                    edition_account: key.to_string(),
+                    edition: edition.edition,
+                })
+        })
+        .collect();
+    editions.sort_by_key(|e| e.edition);
+
+    Ok(EditionList {
+        master_mint: master_mint.to_string(),
+        master_edition: master_edition_key.to_string(),
+        editions,
+    })
+}
+
+fn print_output<T: Serialize + fmt::Display>(format: OutputFormat, output: CliResult<T>) {
+    match output {
+        Ok(value) => match format {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&value).unwrap()),
+            OutputFormat::Display => println!("{}", value),
+        },
+        Err(err) => {
+            match format {
+                OutputFormat::Json => {
+                    println!("{}", serde_json::json!({ "error": err.to_string() }))
+                }
+                OutputFormat::Display => eprintln!("Error: {}", err),
+            }
+            exit(1);
+        }
+    }
+}
+
+fn main() {
+    let app_matches = App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("config_file")
+                .long("config")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Solana CLI configuration file supplying the default keypair and url"),
+        )
+        .arg(
+            Arg::with_name("keypair")
+                .long("keypair")
+                .value_name("KEYPAIR")
+                .validator(is_valid_signer)
+                .takes_value(true)
+                .global(true)
+                .help("Filepath or URL to a keypair [default: from the Solana CLI config]"),
+        )
+        .arg(
+            Arg::with_name("json_rpc_url")
+                .long("url")
+                .value_name("URL")
+                .takes_value(true)
+                .global(true)
+                .validator(is_url)
+                .help("JSON RPC URL for the cluster [default: from the Solana CLI config]"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .global(true)
+                .possible_values(&["display", "json"])
+                .default_value("display")
+                .help("Output format"),
+        )
+        .subcommand(
+            SubCommand::with_name("create-charm")
+                .about("Create a charm mint with metadata and a master edition from a JSON spec")
+                .arg(
+                    Arg::with_name("spec")
+                        .long("spec")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .required(true)
+                        .help("JSON file with `name`, `symbol`, `uri` and optional `escrow` (default true)"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("buy")
+                .about("Buy a new edition of a charm")
+                .arg(
+                    Arg::with_name("master")
+                        .long("master")
+                        .value_name("MINT")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Mint of the master edition"),
+                )
+                .arg(
+                    Arg::with_name("edition")
+                        .long("edition")
+                        .value_name("NUMBER")
+                        .takes_value(true)
+                        .help("Edition number to mint [default: next after the current supply]"),
+                )
+                .arg(
+                    Arg::with_name("referrer")
+                        .long("referrer")
+                        .value_name("WALLET")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .help("Wallet of the affiliate that referred the purchase"),
+                )
+                .arg(
+                    Arg::with_name("currency")
+                        .long("currency")
+                        .value_name("MINT")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .help("Mint of the currency to pay in, the native mint for SOL"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("faucet")
+                .about("Mint faucet tokens from the charm PDA controlled mint")
+                .arg(
+                    Arg::with_name("mint")
+                        .long("mint")
+                        .value_name("MINT")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Faucet mint whose mint authority is the charm PDA"),
+                )
+                .arg(
+                    Arg::with_name("recipient")
+                        .long("recipient")
+                        .value_name("WALLET")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .help("Wallet receiving the tokens [default: the keypair]"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("withdraw")
+                .about("Withdraw the sale proceeds of a listing to its beneficiary")
+                .arg(
+                    Arg::with_name("master")
+                        .long("master")
+                        .value_name("MINT")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Mint of the master edition of the listing"),
+                )
+                .arg(
+                    Arg::with_name("currency")
+                        .long("currency")
+                        .value_name("MINT")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .help("Mint of the currency to withdraw [default: the native mint]"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .long("amount")
+                        .value_name("AMOUNT")
+                        .takes_value(true)
+                        .help("Amount to withdraw [default: all available proceeds]"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("withdraw-fees")
+                .about("Withdraw the platform fees to the fee wallet, which the keypair must be")
+                .arg(
+                    Arg::with_name("currency")
+                        .long("currency")
+                        .value_name("MINT")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .help("Mint of the currency to withdraw [default: the native mint]"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .long("amount")
+                        .value_name("AMOUNT")
+                        .takes_value(true)
+                        .help("Amount to withdraw [default: all available fees]"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("transfer")
+                .about("Transfer tokens through the charm proxy")
+                .arg(
+                    Arg::with_name("mint")
+                        .long("mint")
+                        .value_name("MINT")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Mint of the tokens to transfer"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("WALLET")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Wallet receiving the tokens"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .long("amount")
+                        .value_name("AMOUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Amount in base units"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("show-charm")
+                .about("Show the metadata and edition of a charm")
+                .arg(
+                    Arg::with_name("mint")
+                        .long("mint")
+                        .value_name("MINT")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Mint of the charm"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list-editions")
+                .about("List the editions printed from a master edition")
+                .arg(
+                    Arg::with_name("master")
+                        .long("master")
+                        .value_name("MINT")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Mint of the master edition"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Validate an off-chain metadata JSON file and emit its canonical form")
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Metadata JSON file"),
+                )
+                .arg(
+                    Arg::with_name("mint")
+                        .long("mint")
+                        .value_name("MINT")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .help("Also check the file against the on-chain metadata of this mint"),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .long("out")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .help("Write the canonical JSON, ready for upload, to this file"),
+                ),
+        )
+        .get_matches();
+
+    let cli_config = match app_matches.value_of("config_file") {
+        Some(path) => solana_cli_config::Config::load(path).unwrap_or_default(),
+        None => solana_cli_config::CONFIG_FILE
+            .as_ref()
+            .and_then(|path| solana_cli_config::Config::load(path).ok())
+            .unwrap_or_default(),
+    };
+
+    let client = RpcClient::new(
+        app_matches
+            .value_of("json_rpc_url")
+            .unwrap_or(&cli_config.json_rpc_url)
+            .to_owned(),
+    );
+
+    let keypair_path = app_matches
+        .value_of("keypair")
+        .unwrap_or(&cli_config.keypair_path);
+    let payer = read_keypair_file(keypair_path).unwrap_or_else(|err| {
+        eprintln!("Unable to read keypair {}: {}", keypair_path, err);
+        exit(1);
+    });
+
+    let format = match app_matches.value_of("output") {
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Display,
+    };
+
+    match app_matches.subcommand() {
+        ("create-charm", Some(arg_matches)) => {
+            print_output(format, create_charm(arg_matches, payer, client))
+        }
+        ("buy", Some(arg_matches)) => print_output(format, buy(arg_matches, payer, client)),
+        ("faucet", Some(arg_matches)) => print_output(format, faucet(arg_matches, payer, client)),
+        ("withdraw", Some(arg_matches)) => {
+            print_output(format, withdraw(arg_matches, payer, client))
+        }
+        ("withdraw-fees", Some(arg_matches)) => {
+            print_output(format, withdraw_fees(arg_matches, payer, client))
+        }
+        ("transfer", Some(arg_matches)) => {
+            print_output(format, transfer(arg_matches, payer, client))
+        }
+        ("show-charm", Some(arg_matches)) => {
+            print_output(format, show_charm(arg_matches, payer, client))
+        }
+        ("list-editions", Some(arg_matches)) => {
+            print_output(format, list_editions(arg_matches, payer, client))
+        }
+        ("validate", Some(arg_matches)) => {
+            print_output(format, validate(arg_matches, payer, client))
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,263 @@
+//! Off-chain metadata JSON that `Data.uri` points at.
+//!
+//! A [`MetadataDocument`] is checked on its own and against the on-chain
+//! [`Data`], and serialized to canonical JSON so its SHA-256 is stable.
+
+use {
+    serde::{Deserialize, Serialize},
+    solana_program::{hash::hash, pubkey::Pubkey},
+    spl_token_metadata::state::{Data, MAX_CREATOR_LIMIT, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH},
+    thiserror::Error,
+};
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ValidationError {
+    #[error("name is empty or longer than {} bytes", MAX_NAME_LENGTH)]
+    InvalidName,
+
+    #[error("symbol is longer than {} bytes", MAX_SYMBOL_LENGTH)]
+    InvalidSymbol,
+
+    #[error("image is empty")]
+    MissingImage,
+
+    #[error("file {0} has an empty uri")]
+    EmptyFileUri(usize),
+
+    #[error("attribute {0} has an empty trait_type")]
+    EmptyTraitType(usize),
+
+    #[error("creator {0} is not a valid address")]
+    InvalidCreatorAddress(String),
+
+    #[error("more than {} creators", MAX_CREATOR_LIMIT)]
+    TooManyCreators,
+
+    #[error("creator shares add up to {0} instead of 100")]
+    InvalidShareTotal(u32),
+
+    #[error("name {document:?} does not match the on-chain name {on_chain:?}")]
+    NameMismatch { document: String, on_chain: String },
+
+    #[error("symbol {document:?} does not match the on-chain symbol {on_chain:?}")]
+    SymbolMismatch { document: String, on_chain: String },
+
+    #[error("seller fee of {document} does not match the on-chain {on_chain} basis points")]
+    SellerFeeMismatch { document: u16, on_chain: u16 },
+
+    #[error("creators do not match the on-chain creators")]
+    CreatorsMismatch,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct MetadataDocument {
+    pub name: String,
+    pub symbol: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seller_fee_basis_points: Option<u16>,
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<Attribute>,
+    #[serde(default)]
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Attribute {
+    pub trait_type: String,
+    /// A string or a number.
+    pub value: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Properties {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub files: Vec<File>,
+    #[serde(default)]
+    pub creators: Vec<Creator>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct File {
+    pub uri: String,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Creator {
+    pub address: String,
+    pub share: u8,
+}
+
+/// Rebuilds every object of `value` with its keys in sorted order.
+fn sort_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(sort_keys).collect())
+        }
+        value => value,
+    }
+}
+
+/// On-chain strings are padded with `\0` up to a fixed width.
+fn unpad(value: &str) -> &str {
+    value.trim_end_matches('\0')
+}
+
+impl MetadataDocument {
+    /// Starts a document matching `data`, showing `image`.
+    pub fn from_data(data: &Data, image: &str) -> Self {
+        let creators = data
+            .creators
+            .iter()
+            .flatten()
+            .map(|creator| Creator {
+                address: creator.address.to_string(),
+                share: creator.share,
+            })
+            .collect();
+        MetadataDocument {
+            name: unpad(&data.name).to_owned(),
+            symbol: unpad(&data.symbol).to_owned(),
+            seller_fee_basis_points: Some(data.seller_fee_basis_points),
+            image: image.to_owned(),
+            properties: Properties {
+                files: vec![File {
+                    uri: image.to_owned(),
+                    mime_type: None,
+                }],
+                creators,
+                ..Properties::default()
+            },
+            ..MetadataDocument::default()
+        }
+    }
+
+    pub fn from_json(json: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(json)
+    }
+
+    /// Checks the document on its own, returning every problem found.
+    pub fn check(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        if self.name.is_empty() || self.name.len() > MAX_NAME_LENGTH {
+            errors.push(ValidationError::InvalidName);
+        }
+        if self.symbol.len() > MAX_SYMBOL_LENGTH {
+            errors.push(ValidationError::InvalidSymbol);
+        }
+        if self.image.is_empty() {
+            errors.push(ValidationError::MissingImage);
+        }
+        for (index, file) in self.properties.files.iter().enumerate() {
+            if file.uri.is_empty() {
+                errors.push(ValidationError::EmptyFileUri(index));
+            }
+        }
+        for (index, attribute) in self.attributes.iter().enumerate() {
+            if attribute.trait_type.is_empty() {
+                errors.push(ValidationError::EmptyTraitType(index));
+            }
+        }
+
+        let creators = &self.properties.creators;
+        for creator in creators {
+            if creator.address.parse::<Pubkey>().is_err() {
+                errors.push(ValidationError::InvalidCreatorAddress(
+                    creator.address.clone(),
+                ));
+            }
+        }
+        if creators.len() > MAX_CREATOR_LIMIT {
+            errors.push(ValidationError::TooManyCreators);
+        }
+        let total: u32 = creators.iter().map(|creator| creator.share as u32).sum();
+        if !creators.is_empty() && total != 100 {
+            errors.push(ValidationError::InvalidShareTotal(total));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks the document on its own and against the on-chain `data` it belongs to.
+    pub fn validate(&self, data: &Data) -> Result<(), Vec<ValidationError>> {
+        let mut errors = self.check().err().unwrap_or_default();
+
+        if self.name != unpad(&data.name) {
+            errors.push(ValidationError::NameMismatch {
+                document: self.name.clone(),
+                on_chain: unpad(&data.name).to_owned(),
+            });
+        }
+        if self.symbol != unpad(&data.symbol) {
+            errors.push(ValidationError::SymbolMismatch {
+                document: self.symbol.clone(),
+                on_chain: unpad(&data.symbol).to_owned(),
+            });
+        }
+        if let Some(fee) = self.seller_fee_basis_points {
+            if fee != data.seller_fee_basis_points {
+                errors.push(ValidationError::SellerFeeMismatch {
+                    document: fee,
+                    on_chain: data.seller_fee_basis_points,
+                });
+            }
+        }
+
+        let on_chain: Vec<(String, u8)> = data
+            .creators
+            .iter()
+            .flatten()
+            .map(|creator| (creator.address.to_string(), creator.share))
+            .collect();
+        let document: Vec<(String, u8)> = self
+            .properties
+            .creators
+            .iter()
+            .map(|creator| (creator.address.clone(), creator.share))
+            .collect();
+        if on_chain != document {
+            errors.push(ValidationError::CreatorsMismatch);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Compact JSON with object keys sorted, ready for upload.
+    pub fn to_canonical_json(&self) -> String {
+        let value = serde_json::to_value(self).unwrap();
+        serde_json::to_string(&sort_keys(value)).unwrap()
+    }
+
+    /// SHA-256 of the canonical JSON, as recorded in a charm content hash.
+    pub fn content_hash(&self) -> [u8; 32] {
+        hash(self.to_canonical_json().as_bytes()).to_bytes()
+    }
+}
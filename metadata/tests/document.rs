@@ -0,0 +1,114 @@
+use {
+    charm_metadata::{Attribute, Creator, MetadataDocument, ValidationError},
+    solana_program::{hash::hash, pubkey::Pubkey},
+    spl_token_metadata::state::{Creator as OnChainCreator, Data},
+};
+
+/// On-chain data as the `metadata` instruction stores it, padded with `\0`.
+fn data(creators: &[(Pubkey, u8)]) -> Data {
+    Data {
+        name: format!("{:\0<20}", "Silver Heart"),
+        symbol: "CHRM".to_owned(),
+        uri: format!("{:\0<116}", "https://arweave.net/heart"),
+        seller_fee_basis_points: 20,
+        creators: Some(
+            creators
+                .iter()
+                .map(|(address, share)| OnChainCreator {
+                    address: *address,
+                    verified: true,
+                    share: *share,
+                })
+                .collect(),
+        ),
+    }
+}
+
+#[test]
+fn document_from_data_is_valid() {
+    let creator = Pubkey::new_unique();
+    let data = data(&[(creator, 100)]);
+
+    let document = MetadataDocument::from_data(&data, "https://arweave.net/heart.png");
+
+    assert_eq!(document.name, "Silver Heart");
+    assert_eq!(document.properties.creators[0].address, creator.to_string());
+    assert_eq!(document.validate(&data), Ok(()));
+}
+
+#[test]
+fn reports_every_mismatch() {
+    let creator = Pubkey::new_unique();
+    let data = data(&[(creator, 100)]);
+    let mut document = MetadataDocument::from_data(&data, "https://arweave.net/heart.png");
+    document.name = "Gold Heart".to_owned();
+    document.seller_fee_basis_points = Some(500);
+    document.properties.creators = vec![
+        Creator {
+            address: creator.to_string(),
+            share: 60,
+        },
+        Creator {
+            address: "not an address".to_owned(),
+            share: 30,
+        },
+    ];
+
+    let errors = document.validate(&data).unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![
+            ValidationError::InvalidCreatorAddress("not an address".to_owned()),
+            ValidationError::InvalidShareTotal(90),
+            ValidationError::NameMismatch {
+                document: "Gold Heart".to_owned(),
+                on_chain: "Silver Heart".to_owned(),
+            },
+            ValidationError::SellerFeeMismatch {
+                document: 500,
+                on_chain: 20,
+            },
+            ValidationError::CreatorsMismatch,
+        ]
+    );
+}
+
+#[test]
+fn canonical_json_sorts_keys_and_omits_empty_fields() {
+    let json = br#"{
+        "symbol": "CHRM",
+        "name": "Silver Heart",
+        "image": "https://arweave.net/heart.png",
+        "attributes": [{"value": 12, "trait_type": "weight"}],
+        "properties": {"files": [{"type": "image/png", "uri": "https://arweave.net/heart.png"}]}
+    }"#;
+
+    let document = MetadataDocument::from_json(json).unwrap();
+
+    assert_eq!(
+        document.attributes,
+        vec![Attribute {
+            trait_type: "weight".to_owned(),
+            value: 12.into(),
+        }]
+    );
+    let canonical = document.to_canonical_json();
+    assert_eq!(
+        canonical,
+        concat!(
+            r#"{"attributes":[{"trait_type":"weight","value":12}],"#,
+            r#""image":"https://arweave.net/heart.png","name":"Silver Heart","#,
+            r#""properties":{"creators":[],"files":[{"type":"image/png","#,
+            r#""uri":"https://arweave.net/heart.png"}]},"symbol":"CHRM"}"#
+        )
+    );
+    assert_eq!(
+        document.content_hash(),
+        hash(canonical.as_bytes()).to_bytes()
+    );
+    assert_eq!(
+        MetadataDocument::from_json(canonical.as_bytes()).unwrap(),
+        document
+    );
+}
@@ -1,30 +1,1588 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, Transfer};
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 use spl_associated_token_account::{create_associated_token_account};
-use spl_token_metadata::{
-    instruction::{
-        create_master_edition, create_metadata_accounts,
-        mint_new_edition_from_master_edition_via_token, puff_metadata_account,
-    },
-    state::Metadata,
-};
-
-use anchor_lang::solana_program::{
-    borsh::try_from_slice_unchecked,
-    program::{invoke, invoke_signed},
-};
+use state::*;
+use token_metadata::{MasterEdition, Metadata, TokenMetadata};
+
+use anchor_lang::solana_program::account_info::next_account_info;
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::solana_program::{hash::hash, system_instruction, sysvar::slot_hashes};
+
+pub mod state;
+pub mod token_metadata;
+pub mod utils;
 
 declare_id!("BFXsCPze92SjvpLfh2axdkR4qUzjrambaDtodEJG7qa9");
 
-#[program]
-pub mod charm {
-    use super::*;
+#[program]
+pub mod charm {
+    use super::*;
+
+    pub fn initialize(ctx: Context<Initialize>, bump: u8) -> ProgramResult {
+        //Only the upgrade authority can claim the config, so it can't be front-run
+        utils::assert_upgrade_authority(
+            &ctx.accounts.program_data,
+            ctx.accounts.admin.key,
+            ctx.program_id,
+        )?;
+        let config = &mut ctx.accounts.config;
+        config.admins = vec![*ctx.accounts.admin.key];
+        config.admin_threshold = 1;
+        config.reward_mint = Pubkey::default();
+        config.emission_cap = 0;
+        config.total_emitted = 0;
+        config.platform_fee_bps = 0;
+        config.fee_wallet = *ctx.accounts.admin.key;
+        config.referral_bps = 0;
+        config.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn create_profile(
+        ctx: Context<CreateProfile>,
+        bump: u8,
+        display_name: String,
+        uri: String,
+    ) -> ProgramResult {
+        utils::assert_name_and_uri(&display_name, &uri)?;
+        let profile = &mut ctx.accounts.profile;
+        profile.authority = *ctx.accounts.authority.key;
+        profile.display_name = display_name;
+        profile.uri = uri;
+        profile.verified = false;
+        profile.collection_count = 0;
+        profile.page_count = 0;
+        profile.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn update_profile(
+        ctx: Context<UpdateProfile>,
+        display_name: String,
+        uri: String,
+    ) -> ProgramResult {
+        utils::assert_name_and_uri(&display_name, &uri)?;
+        let profile = &mut ctx.accounts.profile;
+        profile.display_name = display_name;
+        profile.uri = uri;
+
+        Ok(())
+    }
+
+    pub fn verify_profile(ctx: Context<VerifyProfile>, verified: bool) -> ProgramResult {
+        utils::assert_admin_quorum(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        ctx.accounts.profile.verified = verified;
+
+        Ok(())
+    }
+
+    pub fn add_profile_page(ctx: Context<AddProfilePage>, bump: u8) -> ProgramResult {
+        let profile = &mut ctx.accounts.profile;
+        //Only open a new page once every existing page is full
+        let capacity = profile.page_count as usize * PROFILE_PAGE_CAPACITY;
+        if profile.collection_count as usize != capacity {
+            return Err(ErrorCode::ProfilePageNotFull.into());
+        }
+        let page = &mut ctx.accounts.page;
+        page.profile = profile.key();
+        page.page = profile.page_count;
+        page.collections = Vec::new();
+        page.bump = bump;
+        profile.page_count += 1;
+
+        Ok(())
+    }
+
+    pub fn create_collection(
+        ctx: Context<CreateCollection>,
+        bump: u8,
+        name: String,
+        uri: String,
+        royalty_policy: RoyaltyPolicy,
+    ) -> ProgramResult {
+        utils::assert_name_and_uri(&name, &uri)?;
+        if royalty_policy.seller_fee_basis_points > 10000 {
+            return Err(ErrorCode::InvalidRoyaltyPolicy.into());
+        }
+        let page = &mut ctx.accounts.page;
+        if page.collections.len() >= PROFILE_PAGE_CAPACITY {
+            return Err(ErrorCode::ProfilePageFull.into());
+        }
+        let collection = &mut ctx.accounts.collection;
+        collection.authority = *ctx.accounts.authority.key;
+        collection.profile = ctx.accounts.profile.key();
+        collection.name = name;
+        collection.uri = uri;
+        collection.royalty_policy = royalty_policy;
+        collection.member_count = 0;
+        collection.reward_rate = 0;
+        collection.bump = bump;
+
+        //Index the collection on the creator profile
+        page.collections.push(collection.key());
+        ctx.accounts.profile.collection_count += 1;
+
+        Ok(())
+    }
+
+    pub fn add_member(ctx: Context<AddMember>, bump: u8) -> ProgramResult {
+        utils::assert_royalty_policy(&ctx.accounts.collection, &ctx.accounts.metadata)?;
+        let membership = &mut ctx.accounts.membership;
+        membership.collection = ctx.accounts.collection.key();
+        membership.mint = ctx.accounts.metadata.mint;
+        membership.bump = bump;
+        ctx.accounts.collection.member_count += 1;
+
+        Ok(())
+    }
+
+    pub fn remove_member(ctx: Context<RemoveMember>) -> ProgramResult {
+        //The membership account is closed to the authority on exit
+        ctx.accounts.collection.member_count -= 1;
+
+        Ok(())
+    }
+
+    pub fn transfer_collection_authority(
+        ctx: Context<TransferCollectionAuthority>,
+        new_authority: Pubkey,
+    ) -> ProgramResult {
+        ctx.accounts.collection.authority = new_authority;
+
+        Ok(())
+    }
+
+    pub fn create_attributes(
+        ctx: Context<CreateAttributes>,
+        bump: u8,
+        attributes: Vec<Attribute>,
+    ) -> ProgramResult {
+        utils::assert_attributes(&attributes)?;
+        let record = &mut ctx.accounts.attributes;
+        record.master_edition = ctx.accounts.master_edition.key();
+        record.attributes = attributes;
+        record.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn set_attributes(ctx: Context<SetAttributes>, attributes: Vec<Attribute>) -> ProgramResult {
+        utils::assert_attributes(&attributes)?;
+        ctx.accounts.attributes.attributes = attributes;
+
+        Ok(())
+    }
+
+    pub fn create_content_hash(
+        ctx: Context<CreateContentHash>,
+        bump: u8,
+        metadata_hash: [u8; 32],
+        media_hash: Option<[u8; 32]>,
+    ) -> ProgramResult {
+        if !ctx.accounts.metadata.is_mutable {
+            return Err(ErrorCode::MetadataImmutable.into());
+        }
+        let content_hash = &mut ctx.accounts.content_hash;
+        content_hash.master_edition = utils::edition_address(&ctx.accounts.metadata.mint);
+        content_hash.metadata_hash = metadata_hash;
+        content_hash.media_hash = media_hash;
+        content_hash.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn update_content_hash(
+        ctx: Context<UpdateContentHash>,
+        metadata_hash: [u8; 32],
+        media_hash: Option<[u8; 32]>,
+    ) -> ProgramResult {
+        if !ctx.accounts.metadata.is_mutable {
+            return Err(ErrorCode::MetadataImmutable.into());
+        }
+        let content_hash = &mut ctx.accounts.content_hash;
+        content_hash.metadata_hash = metadata_hash;
+        content_hash.media_hash = media_hash;
+
+        Ok(())
+    }
+
+    pub fn create_bracelet(ctx: Context<CreateBracelet>, bump: u8) -> ProgramResult {
+        let bracelet = &mut ctx.accounts.bracelet;
+        bracelet.parent_mint = ctx.accounts.parent_mint.key();
+        bracelet.charms = Vec::new();
+        bracelet.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn attach_charm(ctx: Context<AttachCharm>, _escrow_bump: u8) -> ProgramResult {
+        if ctx.accounts.bracelet.charms.len() >= MAX_ATTACHED_CHARMS {
+            return Err(ErrorCode::BraceletFull.into());
+        }
+        msg!("Depositing charm into bracelet escrow");
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.charm_token.to_account_info(),
+                    to: ctx.accounts.escrow.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+        ctx.accounts
+            .bracelet
+            .charms
+            .push(*ctx.accounts.charm_mint.key);
+
+        Ok(())
+    }
+
+    pub fn detach_charm(ctx: Context<DetachCharm>) -> ProgramResult {
+        let bracelet = &ctx.accounts.bracelet;
+        let position = bracelet
+            .charms
+            .iter()
+            .position(|mint| mint == ctx.accounts.charm_mint.key)
+            .ok_or(ErrorCode::CharmNotAttached)?;
+
+        msg!("Returning charm from bracelet escrow");
+        let seeds = &[
+            b"bracelet".as_ref(),
+            bracelet.parent_mint.as_ref(),
+            &[bracelet.bump],
+        ];
+        let signer = &[&seeds[..]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.escrow.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: bracelet.to_account_info(),
+                },
+                signer,
+            ),
+            1,
+        )?;
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::CloseAccount {
+                account: ctx.accounts.escrow.to_account_info(),
+                destination: ctx.accounts.owner.to_account_info(),
+                authority: bracelet.to_account_info(),
+            },
+            signer,
+        ))?;
+        ctx.accounts.bracelet.charms.remove(position);
+
+        Ok(())
+    }
+
+    pub fn engrave(ctx: Context<Engrave>, bump: u8, message: String, lock: bool) -> ProgramResult {
+        //Soulbound editions pass their SoulboundEdition record as remaining account
+        utils::assert_edition_held_by(
+            ctx.accounts.holder.key,
+            &ctx.accounts.holder_token,
+            ctx.accounts.edition_mint.key,
+            ctx.remaining_accounts.first(),
+        )?;
+        if message.len() > MAX_ENGRAVING_LEN {
+            return Err(ErrorCode::EngravingTooLong.into());
+        }
+
+        let engraving_info = &ctx.accounts.engraving;
+        if engraving_info.data_is_empty() {
+            msg!("Creating engraving");
+            utils::create_pda_account(
+                &ctx.accounts.holder.to_account_info(),
+                engraving_info,
+                &ctx.accounts.system_program.to_account_info(),
+                Engraving::LEN,
+                ctx.program_id,
+                &[b"engraving", ctx.accounts.edition_mint.key.as_ref(), &[bump]],
+            )?;
+            Engraving {
+                mint: *ctx.accounts.edition_mint.key,
+                message: String::new(),
+                engraved_by: *ctx.accounts.holder.key,
+                locked: false,
+                bump,
+            }
+            .try_serialize(&mut &mut engraving_info.try_borrow_mut_data()?[..])?;
+        }
+        let mut engraving: Account<Engraving> = Account::try_from(engraving_info)?;
+        if engraving.locked {
+            return Err(ErrorCode::EngravingLocked.into());
+        }
+        engraving.message = message;
+        engraving.engraved_by = *ctx.accounts.holder.key;
+        engraving.locked = lock;
+        engraving.exit(ctx.program_id)
+    }
+
+    pub fn create_listing(
+        ctx: Context<CreateListing>,
+        bump: u8,
+        soulbound: bool,
+        pass: Option<PassTerms>,
+        window: Option<MintWindow>,
+    ) -> ProgramResult {
+        if matches!(pass, Some(terms) if terms.duration <= 0) {
+            return Err(ErrorCode::InvalidPassTerms.into());
+        }
+        if matches!(window, Some(window) if window.start >= window.end) {
+            return Err(ErrorCode::InvalidMintWindow.into());
+        }
+        //Passes are checked against the buyer's token account, soulbound editions are not in it
+        if soulbound && pass.is_some() {
+            return Err(ErrorCode::SoulboundPass.into());
+        }
+        //Open editions have no max supply, so only their window stops the prints
+        if ctx.accounts.master_edition.max_supply.is_none() && window.is_none() {
+            return Err(ErrorCode::OpenEditionWithoutWindow.into());
+        }
+        let listing = &mut ctx.accounts.listing;
+        listing.master_mint = ctx.accounts.metadata.mint;
+        listing.authority = *ctx.accounts.authority.key;
+        listing.soulbound = soulbound;
+        listing.pass = pass;
+        listing.window = window;
+        listing.closed = false;
+        listing.raffled = false;
+        listing.referral_bps = None;
+        listing.prices = Vec::new();
+        listing.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn revoke_soulbound(ctx: Context<RevokeSoulbound>) -> ProgramResult {
+        let soulbound_edition = &ctx.accounts.soulbound_edition;
+        let seeds = &[
+            b"soulbound".as_ref(),
+            soulbound_edition.mint.as_ref(),
+            &[soulbound_edition.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        msg!("Releasing soulbound edition to its holder");
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.custody.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: soulbound_edition.to_account_info(),
+                },
+                signer,
+            ),
+            1,
+        )?;
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::CloseAccount {
+                account: ctx.accounts.custody.to_account_info(),
+                destination: ctx.accounts.holder.clone(),
+                authority: soulbound_edition.to_account_info(),
+            },
+            signer,
+        ))?;
+
+        Ok(())
+    }
+
+    pub fn configure_rewards(
+        ctx: Context<ConfigureRewards>,
+        reward_mint: Pubkey,
+        emission_cap: u64,
+    ) -> ProgramResult {
+        utils::assert_admin_quorum(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        let config = &mut ctx.accounts.config;
+        config.reward_mint = reward_mint;
+        config.emission_cap = emission_cap;
+
+        Ok(())
+    }
+
+    pub fn set_reward_rate(ctx: Context<SetRewardRate>, reward_rate: u64) -> ProgramResult {
+        //Rates draw on the shared emission cap, so only the admins set them
+        utils::assert_admin_quorum(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        ctx.accounts.collection.reward_rate = reward_rate;
+
+        Ok(())
+    }
+
+    pub fn stake_edition(
+        ctx: Context<StakeEdition>,
+        stake_bump: u8,
+        _vault_bump: u8,
+    ) -> ProgramResult {
+        utils::assert_edition_holder(
+            ctx.accounts.owner.key,
+            &ctx.accounts.owner_token,
+            ctx.accounts.edition_mint.key,
+        )?;
+        msg!("Locking edition in stake vault");
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.owner_token.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+
+        let now = ctx.accounts.clock.unix_timestamp;
+        let stake = &mut ctx.accounts.stake;
+        stake.owner = *ctx.accounts.owner.key;
+        stake.edition_mint = *ctx.accounts.edition_mint.key;
+        stake.collection = ctx.accounts.collection.key();
+        stake.staked_at = now;
+        stake.claimed_until = now;
+        stake.bump = stake_bump;
+
+        Ok(())
+    }
+
+    pub fn claim_rewards(ctx: Context<ClaimRewards>, pda_nonce: u8) -> ProgramResult {
+        let now = ctx.accounts.clock.unix_timestamp;
+        let amount = utils::accrued_rewards(
+            &ctx.accounts.stake,
+            ctx.accounts.collection.reward_rate,
+            &ctx.accounts.config,
+            now,
+        )?;
+        ctx.accounts.stake.claimed_until = now;
+        utils::mint_rewards(
+            &mut ctx.accounts.config,
+            amount,
+            &ctx.accounts.reward_mint,
+            &ctx.accounts.reward_account.to_account_info(),
+            &ctx.accounts.pda,
+            &ctx.accounts.token_program.to_account_info(),
+            pda_nonce,
+        )
+    }
+
+    pub fn unstake_edition(ctx: Context<UnstakeEdition>, pda_nonce: u8) -> ProgramResult {
+        //Pay what was earned so far, the stake record is closed below
+        let now = ctx.accounts.clock.unix_timestamp;
+        let amount = utils::accrued_rewards(
+            &ctx.accounts.stake,
+            ctx.accounts.collection.reward_rate,
+            &ctx.accounts.config,
+            now,
+        )?;
+        utils::mint_rewards(
+            &mut ctx.accounts.config,
+            amount,
+            &ctx.accounts.reward_mint,
+            &ctx.accounts.reward_account.to_account_info(),
+            &ctx.accounts.pda,
+            &ctx.accounts.token_program.to_account_info(),
+            pda_nonce,
+        )?;
+
+        msg!("Returning edition from stake vault");
+        let stake = &ctx.accounts.stake;
+        let seeds = &[b"stake".as_ref(), stake.edition_mint.as_ref(), &[stake.bump]];
+        let signer = &[&seeds[..]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: stake.to_account_info(),
+                },
+                signer,
+            ),
+            1,
+        )?;
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::CloseAccount {
+                account: ctx.accounts.vault.to_account_info(),
+                destination: ctx.accounts.owner.to_account_info(),
+                authority: stake.to_account_info(),
+            },
+            signer,
+        ))?;
+
+        Ok(())
+    }
+
+    pub fn redeem_edition(
+        ctx: Context<RedeemEdition>,
+        bump: u8,
+        shipping_hash: [u8; 32],
+    ) -> ProgramResult {
+        utils::assert_edition_holder(
+            ctx.accounts.holder.key,
+            &ctx.accounts.holder_token,
+            ctx.accounts.edition_mint.key,
+        )?;
+
+        msg!("Burning redeemed edition");
+        token::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Burn {
+                    mint: ctx.accounts.edition_mint.clone(),
+                    to: ctx.accounts.holder_token.to_account_info(),
+                    authority: ctx.accounts.holder.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+        token::close_account(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::CloseAccount {
+                account: ctx.accounts.holder_token.to_account_info(),
+                destination: ctx.accounts.holder.to_account_info(),
+                authority: ctx.accounts.holder.to_account_info(),
+            },
+        ))?;
+
+        let redemption = &mut ctx.accounts.redemption;
+        redemption.edition_mint = *ctx.accounts.edition_mint.key;
+        redemption.master_mint = ctx.accounts.master_metadata.mint;
+        redemption.redeemer = *ctx.accounts.holder.key;
+        redemption.authority = ctx.accounts.master_metadata.update_authority;
+        redemption.redeemed_at = ctx.accounts.clock.unix_timestamp;
+        redemption.shipping_hash = shipping_hash;
+        redemption.fulfilled = false;
+        redemption.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn fulfil_redemption(ctx: Context<FulfilRedemption>) -> ProgramResult {
+        let redemption = &mut ctx.accounts.redemption;
+        if redemption.fulfilled {
+            return Err(ErrorCode::RedemptionFulfilled.into());
+        }
+        redemption.fulfilled = true;
+
+        Ok(())
+    }
+
+    pub fn renew_pass(ctx: Context<RenewPass>) -> ProgramResult {
+        let pass = match ctx.accounts.listing.pass {
+            Some(pass) => pass,
+            None => return Err(ErrorCode::NotAPass.into()),
+        };
+
+        msg!("Paying pass renewal");
+        invoke(
+            &system_instruction::transfer(
+                ctx.accounts.payer.key,
+                ctx.accounts.authority.key,
+                pass.renewal_price,
+            ),
+            &[
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.authority.clone(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        //A lapsed pass restarts from now instead of catching up
+        let expiry = &mut ctx.accounts.expiry;
+        expiry.expires_at = expiry
+            .expires_at
+            .max(ctx.accounts.clock.unix_timestamp)
+            .checked_add(pass.duration)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn assert_pass_valid(ctx: Context<AssertPassValid>) -> ProgramResult {
+        utils::assert_pass_valid(
+            ctx.accounts.holder.key,
+            &ctx.accounts.holder_token,
+            &ctx.accounts.expiry,
+            ctx.accounts.clock.unix_timestamp,
+        )
+    }
+
+    pub fn prove_ownership(ctx: Context<ProveOwnership>) -> ProgramResult {
+        utils::assert_holds_edition_of(
+            ctx.accounts.wallet.key,
+            &ctx.accounts.token_account,
+            &ctx.accounts.metadata,
+            &ctx.accounts.edition.key(),
+            &ctx.accounts.edition,
+            &ctx.accounts.master_edition.key(),
+            //Soulbound editions pass their SoulboundEdition record as remaining account
+            ctx.remaining_accounts.first(),
+        )
+    }
+
+    pub fn close_open_edition(ctx: Context<CloseOpenEdition>) -> ProgramResult {
+        let listing = &mut ctx.accounts.listing;
+        match listing.window {
+            Some(window) if ctx.accounts.clock.unix_timestamp >= window.end => {}
+            Some(_) => return Err(ErrorCode::MintWindowOpen.into()),
+            None => return Err(ErrorCode::InvalidMintWindow.into()),
+        }
+        listing.closed = true;
+
+        Ok(())
+    }
+
+    pub fn create_raffle(
+        ctx: Context<CreateRaffle>,
+        bump: u8,
+        price: u64,
+        entry_window: MintWindow,
+        winner_count: u32,
+        seed_commitment: [u8; 32],
+        reveal_deadline: i64,
+    ) -> ProgramResult {
+        if entry_window.start >= entry_window.end {
+            return Err(ErrorCode::InvalidMintWindow.into());
+        }
+        if winner_count == 0 || reveal_deadline <= entry_window.end {
+            return Err(ErrorCode::InvalidRaffle.into());
+        }
+        //Every winner must be able to print an edition
+        let master_edition = &ctx.accounts.master_edition;
+        if let Some(max_supply) = master_edition.max_supply {
+            if winner_count as u64 > max_supply.saturating_sub(master_edition.supply) {
+                return Err(ErrorCode::RaffleExceedsSupply.into());
+            }
+        }
+        ctx.accounts.listing.raffled = true;
+
+        let raffle = &mut ctx.accounts.raffle;
+        raffle.master_mint = ctx.accounts.listing.master_mint;
+        raffle.authority = *ctx.accounts.authority.key;
+        raffle.price = price;
+        raffle.entry_window = entry_window;
+        raffle.winner_count = winner_count;
+        raffle.entry_count = 0;
+        raffle.seed_commitment = seed_commitment;
+        raffle.reveal_deadline = reveal_deadline;
+        raffle.draw_slot = None;
+        raffle.randomness = None;
+        raffle.proceeds_withdrawn = false;
+        raffle.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn enter_raffle(ctx: Context<EnterRaffle>, bump: u8) -> ProgramResult {
+        let raffle = &mut ctx.accounts.raffle;
+        if !raffle.entry_window.contains(ctx.accounts.clock.unix_timestamp) {
+            return Err(ErrorCode::RaffleNotOpen.into());
+        }
+
+        msg!("Escrowing raffle entry");
+        invoke(
+            &system_instruction::transfer(
+                ctx.accounts.entrant.key,
+                &raffle.key(),
+                raffle.price,
+            ),
+            &[
+                ctx.accounts.entrant.to_account_info(),
+                raffle.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        let entry = &mut ctx.accounts.entry;
+        entry.raffle = raffle.key();
+        entry.entrant = *ctx.accounts.entrant.key;
+        entry.index = raffle.entry_count;
+        entry.claimed = false;
+        entry.bump = bump;
+        raffle.entry_count = raffle
+            .entry_count
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn close_raffle_entries(ctx: Context<CloseRaffleEntries>) -> ProgramResult {
+        let raffle = &mut ctx.accounts.raffle;
+        if ctx.accounts.clock.unix_timestamp < raffle.entry_window.end {
+            return Err(ErrorCode::RaffleNotOpen.into());
+        }
+        if raffle.randomness.is_some() {
+            return Err(ErrorCode::RaffleRevealed.into());
+        }
+        //A draw slot whose hash is gone can be replaced, or a late reveal would brick the raffle
+        let slot = ctx.accounts.clock.slot;
+        if raffle.draw_slot.is_some() && !raffle.draw_slot_expired(slot) {
+            return Err(ErrorCode::RaffleEntriesClosed.into());
+        }
+        //The hash of the current slot is unknown until it ends, so nobody can steer it
+        raffle.draw_slot = Some(slot);
+
+        Ok(())
+    }
+
+    pub fn reveal_raffle(ctx: Context<RevealRaffle>, seed: [u8; 32]) -> ProgramResult {
+        let raffle = &mut ctx.accounts.raffle;
+        if ctx.accounts.clock.unix_timestamp >= raffle.reveal_deadline {
+            return Err(ErrorCode::RevealDeadlinePassed.into());
+        }
+        if raffle.randomness.is_some() {
+            return Err(ErrorCode::RaffleRevealed.into());
+        }
+        if hash(&seed).to_bytes() != raffle.seed_commitment {
+            return Err(ErrorCode::InvalidRaffleSeed.into());
+        }
+
+        let draw_slot = raffle.draw_slot.ok_or(ErrorCode::RaffleNotOpen)?;
+        if raffle.draw_slot_expired(ctx.accounts.clock.slot) {
+            return Err(ErrorCode::DrawSlotUnavailable.into());
+        }
+        let slot_hash = utils::slot_hash_at(&ctx.accounts.slot_hashes, draw_slot)?
+            .ok_or(ErrorCode::DrawSlotUnavailable)?;
+        raffle.randomness = Some(utils::draw_randomness(&seed, &slot_hash));
+        msg!("Raffle drew {} winners", raffle.winners());
+
+        Ok(())
+    }
+
+    /// Lets the listing sell to everyone again once its raffle missed the reveal deadline.
+    pub fn expire_raffle(ctx: Context<ExpireRaffle>) -> ProgramResult {
+        let raffle = &ctx.accounts.raffle;
+        if raffle.randomness.is_some() {
+            return Err(ErrorCode::RaffleRevealed.into());
+        }
+        if ctx.accounts.clock.unix_timestamp < raffle.reveal_deadline {
+            return Err(ErrorCode::RevealDeadlineNotPassed.into());
+        }
+        //Entrants claim their refunds from the raffle, which stays around for them
+        ctx.accounts.listing.raffled = false;
+
+        Ok(())
+    }
+
+    pub fn claim_raffle_refund(ctx: Context<ClaimRaffleRefund>) -> ProgramResult {
+        let raffle = &ctx.accounts.raffle;
+        let index = ctx.accounts.entry.index;
+        if !raffle.is_refundable(index, ctx.accounts.clock.unix_timestamp) {
+            return Err(match raffle.is_winner(index) {
+                Some(_) => ErrorCode::RaffleWinner,
+                None => ErrorCode::RaffleNotRevealed,
+            }
+            .into());
+        }
+
+        msg!("Refunding raffle entry");
+        utils::transfer_lamports(
+            &raffle.to_account_info(),
+            &ctx.accounts.entrant.to_account_info(),
+            raffle.price,
+        )
+    }
+
+    pub fn withdraw_raffle_proceeds(ctx: Context<WithdrawRaffleProceeds>) -> ProgramResult {
+        let raffle = &mut ctx.accounts.raffle;
+        if raffle.randomness.is_none() {
+            return Err(ErrorCode::RaffleNotRevealed.into());
+        }
+        if raffle.proceeds_withdrawn {
+            return Err(ErrorCode::ProceedsWithdrawn.into());
+        }
+        raffle.proceeds_withdrawn = true;
+
+        //Entries of the winners are a sale of the listing, the rest stays escrowed for refunds
+        let amount = raffle
+            .price
+            .checked_mul(raffle.winners() as u64)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+        let raffle_info = raffle.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let split = utils::SaleSplit {
+            listing: &ctx.accounts.listing,
+            config: &ctx.accounts.config,
+            platform_treasury: &ctx.accounts.platform_treasury,
+            platform_vault: None,
+            royalty_treasury: &ctx.accounts.royalty_treasury,
+            royalty_vault: &ctx.accounts.royalty_vault,
+            listing_treasury: &ctx.accounts.listing_treasury,
+            listing_vault: None,
+        };
+        split.pay(
+            &spl_token::native_mint::id(),
+            amount,
+            ctx.accounts.master_metadata.data.seller_fee_basis_points,
+            None,
+            ctx.program_id,
+            |to, amount| {
+                utils::transfer_lamports(&raffle_info, to, amount)?;
+                utils::sync_wrapped_sol(to, &token_program)
+            },
+        )?;
+
+        Ok(())
+    }
+
+    pub fn create_bundle<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateBundle<'info>>,
+        bump: u8,
+        id: u64,
+        price: u64,
+    ) -> ProgramResult {
+        //Remaining accounts are the listings of the masters, all owned by the authority
+        let listings = ctx.remaining_accounts;
+        if listings.is_empty() || listings.len() > MAX_BUNDLE_MASTERS {
+            return Err(ErrorCode::InvalidBundle.into());
+        }
+        let mut masters = Vec::with_capacity(listings.len());
+        for listing_info in listings {
+            let listing: Account<Listing> = Account::try_from(listing_info)?;
+            if listing.authority != *ctx.accounts.authority.key {
+                return Err(ErrorCode::BundleAuthorityMismatch.into());
+            }
+            if masters.contains(&listing.master_mint) {
+                return Err(ErrorCode::InvalidBundle.into());
+            }
+            masters.push(listing.master_mint);
+        }
+
+        let bundle = &mut ctx.accounts.bundle;
+        bundle.authority = *ctx.accounts.authority.key;
+        bundle.id = id;
+        bundle.masters = masters;
+        bundle.price = price;
+        bundle.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn buy_bundle<'info>(
+        ctx: Context<'_, '_, '_, 'info, BuyBundle<'info>>,
+        editions: Vec<u64>,
+        pda_nonce: u8,
+    ) -> ProgramResult {
+        let bundle = &ctx.accounts.bundle;
+        if editions.len() != bundle.masters.len() {
+            return Err(ErrorCode::InvalidBundle.into());
+        }
+        let now = Clock::get()?.unix_timestamp;
+        let seeds = &[&b"charmpda"[..], &[pda_nonce]];
+        let signer = &[&seeds[..]];
+        let payer = ctx.accounts.payer.to_account_info();
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let native_mint = spl_token::native_mint::id();
+
+        //Each master takes a share of the price weighted by its native SOL listing price
+        let mut weights = Vec::with_capacity(bundle.masters.len());
+        for master_accounts in ctx.remaining_accounts.chunks(13) {
+            let listing_info = master_accounts.get(9).ok_or(ErrorCode::InvalidRemainingAccounts)?;
+            let listing: Account<Listing> = Account::try_from(listing_info)?;
+            let price = listing.price_in(&native_mint).ok_or(ErrorCode::CurrencyNotAccepted)?;
+            weights.push(price.amount);
+        }
+        if weights.len() != bundle.masters.len() {
+            return Err(ErrorCode::InvalidRemainingAccounts.into());
+        }
+
+        //Remaining accounts, for each master in bundle order:
+        //[new_metadata, new_edition, master_edition, new_mint, edition_pda, token_account,
+        // update_authority, metadata, metadata_mint, listing, royalty_treasury, royalty_vault,
+        // listing_treasury], the treasuries being the native SOL ones
+        let accounts = &mut ctx.remaining_accounts.iter();
+        for (index, (master_mint, edition)) in bundle.masters.iter().zip(editions).enumerate() {
+            let new_metadata = next_account_info(accounts)?;
+            let new_edition = next_account_info(accounts)?;
+            let master_edition = next_account_info(accounts)?;
+            let new_mint = next_account_info(accounts)?;
+            let edition_pda = next_account_info(accounts)?;
+            let token_account = next_account_info(accounts)?;
+            let update_authority = next_account_info(accounts)?;
+            let metadata_info = next_account_info(accounts)?;
+            let metadata_mint = next_account_info(accounts)?;
+            let listing_info = next_account_info(accounts)?;
+            let royalty_treasury = next_account_info(accounts)?;
+            let royalty_vault = next_account_info(accounts)?;
+            let listing_treasury = next_account_info(accounts)?;
+
+            let metadata: Account<Metadata> = Account::try_from(metadata_info)?;
+            let listing: Account<Listing> = Account::try_from(listing_info)?;
+            let master_token: Account<TokenAccount> = Account::try_from(token_account)?;
+            if master_token.mint != *master_mint || master_token.owner != *ctx.accounts.pda.key {
+                return Err(ErrorCode::MasterTokenNotEscrowed.into());
+            }
+            if metadata_mint.key != master_mint
+                || metadata.mint != *master_mint
+                || listing.master_mint != *master_mint
+                || *master_edition.key != utils::edition_address(master_mint)
+                || *update_authority.key != metadata.update_authority
+            {
+                return Err(ErrorCode::InvalidRemainingAccounts.into());
+            }
+            if listing.soulbound || listing.pass.is_some() || listing.raffled {
+                return Err(ErrorCode::BundleListingUnsupported.into());
+            }
+            if listing.closed {
+                return Err(ErrorCode::ListingClosed.into());
+            }
+            if matches!(listing.window, Some(window) if !window.contains(now)) {
+                return Err(ErrorCode::OutsideMintWindow.into());
+            }
+
+            msg!("Printing edition {} of bundle master {}", edition, index);
+            token_metadata::mint_new_edition_from_master_edition_via_token(
+                CpiContext::new_with_signer(
+                    ctx.accounts.metadata_program.to_account_info(),
+                    token_metadata::MintNewEditionFromMasterEditionViaToken {
+                        new_metadata: new_metadata.clone(),
+                        new_edition: new_edition.clone(),
+                        master_edition: master_edition.clone(),
+                        new_mint: new_mint.clone(),
+                        edition_mark_pda: edition_pda.clone(),
+                        new_mint_authority: payer.clone(),
+                        payer: payer.clone(),
+                        token_account_owner: ctx.accounts.pda.clone(),
+                        token_account: token_account.clone(),
+                        new_metadata_update_authority: update_authority.clone(),
+                        metadata: metadata_info.clone(),
+                        metadata_mint: metadata_mint.clone(),
+                        token_program: token_program.clone(),
+                        system_program: system_program.clone(),
+                        rent: ctx.accounts.rent.to_account_info(),
+                    },
+                    signer,
+                ),
+                edition,
+            )?;
+
+            let share = utils::bundle_share(bundle.price, &weights, index);
+            let split = utils::SaleSplit {
+                listing: &listing,
+                config: &ctx.accounts.config,
+                platform_treasury: &ctx.accounts.platform_treasury,
+                platform_vault: None,
+                royalty_treasury,
+                royalty_vault,
+                listing_treasury,
+                listing_vault: None,
+            };
+            split.pay(
+                &native_mint,
+                share,
+                metadata.data.seller_fee_basis_points,
+                None,
+                ctx.program_id,
+                |to, amount| {
+                    utils::collect_payment(
+                        &native_mint,
+                        &payer,
+                        &payer,
+                        to,
+                        &system_program,
+                        &token_program,
+                        amount,
+                    )
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_platform_fee(
+        ctx: Context<SetPlatformFee>,
+        platform_fee_bps: u16,
+        fee_wallet: Pubkey,
+    ) -> ProgramResult {
+        utils::assert_admin_quorum(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        if platform_fee_bps > 10_000 {
+            return Err(ErrorCode::InvalidFee.into());
+        }
+        let config = &mut ctx.accounts.config;
+        config.platform_fee_bps = platform_fee_bps;
+        config.fee_wallet = fee_wallet;
+
+        Ok(())
+    }
+
+    pub fn make_offer(
+        ctx: Context<MakeOffer>,
+        offer_bump: u8,
+        _escrow_bump: u8,
+        amount: u64,
+        expires_at: i64,
+        referrer: Option<Pubkey>,
+    ) -> ProgramResult {
+        if expires_at <= ctx.accounts.clock.unix_timestamp {
+            return Err(ErrorCode::InvalidOfferExpiry.into());
+        }
+        if amount == 0 {
+            return Err(ErrorCode::InvalidOfferAmount.into());
+        }
+        if referrer == Some(*ctx.accounts.bidder.key) {
+            return Err(ErrorCode::InvalidReferrer.into());
+        }
+
+        msg!("Escrowing offer");
+        utils::collect_payment(
+            &ctx.accounts.currency_mint.key(),
+            &ctx.accounts.bidder.to_account_info(),
+            &ctx.accounts.bidder_token,
+            &ctx.accounts.escrow.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            amount,
+        )?;
+
+        let offer = &mut ctx.accounts.offer;
+        offer.bidder = *ctx.accounts.bidder.key;
+        offer.edition_mint = ctx.accounts.metadata.mint;
+        offer.currency_mint = ctx.accounts.currency_mint.key();
+        offer.amount = amount;
+        offer.expires_at = expires_at;
+        offer.referrer = referrer;
+        offer.bump = offer_bump;
+
+        Ok(())
+    }
+
+    pub fn cancel_offer(ctx: Context<CancelOffer>) -> ProgramResult {
+        ctx.accounts.refund()
+    }
+
+    pub fn close_expired_offer(ctx: Context<CancelOffer>) -> ProgramResult {
+        //Anyone can clean up, everything goes back to the bidder
+        if ctx.accounts.clock.unix_timestamp < ctx.accounts.offer.expires_at {
+            return Err(ErrorCode::OfferNotExpired.into());
+        }
+        ctx.accounts.refund()
+    }
+
+    pub fn accept_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, AcceptOffer<'info>>,
+    ) -> ProgramResult {
+        let offer = &ctx.accounts.offer;
+        if ctx.accounts.clock.unix_timestamp >= offer.expires_at {
+            return Err(ErrorCode::OfferExpired.into());
+        }
+        utils::assert_edition_holder(
+            ctx.accounts.holder.key,
+            &ctx.accounts.holder_token,
+            &offer.edition_mint,
+        )?;
+
+        msg!("Transferring edition to bidder");
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.holder_token.to_account_info(),
+                    to: ctx.accounts.bidder_token.to_account_info(),
+                    authority: ctx.accounts.holder.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let escrow = ctx.accounts.escrow.to_account_info();
+        let offer_info = offer.to_account_info();
+        let seeds = &[
+            b"offer".as_ref(),
+            offer.edition_mint.as_ref(),
+            offer.bidder.as_ref(),
+            &[offer.bump],
+        ];
+        let mut remaining = offer.amount;
+        //Rent of the escrow, on top of the wrapped lamports for native SOL
+        let escrow_rent = escrow.lamports().saturating_sub(ctx.accounts.escrow.amount);
+        //Anything sent to the escrow beyond the offer goes back to the bidder
+        let surplus = ctx.accounts.escrow.amount.saturating_sub(offer.amount);
+
+        let master_edition = utils::master_edition_of(&ctx.accounts.edition)?;
+        if utils::edition_address(&ctx.accounts.listing.master_mint) != master_edition {
+            return Err(ErrorCode::ListingMismatch.into());
+        }
+
+        //Royalties are held for the creators to claim
+        let treasury = &mut ctx.accounts.treasury;
+        if treasury.master_edition != master_edition
+            || treasury.currency_mint != offer.currency_mint
+        {
+            return Err(ErrorCode::InvalidRoyaltyTreasury.into());
+        }
+        let royalty = utils::basis_points_of(
+            offer.amount,
+            ctx.accounts.metadata.data.seller_fee_basis_points,
+        );
+        utils::transfer_signed(
+            &token_program,
+            &escrow,
+            &ctx.accounts.royalty_vault.to_account_info(),
+            &offer_info,
+            seeds,
+            royalty,
+        )?;
+        treasury.total_received = treasury
+            .total_received
+            .checked_add(royalty)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+        remaining -= royalty;
+
+        let fee = utils::basis_points_of(offer.amount, ctx.accounts.config.platform_fee_bps);
+        let native = utils::is_native(&offer.currency_mint);
+        //Referrer picked by the bidder, remaining accounts are then [referrer,
+        //referrer_stats], the referrer being its currency token account unless the offer
+        //is in native SOL
+        let referral = match (offer.referrer, ctx.remaining_accounts) {
+            (None, []) => None,
+            (Some(referrer), [referrer_info, stats_info]) => {
+                if native {
+                    if *referrer_info.key != referrer {
+                        return Err(ErrorCode::InvalidReferrer.into());
+                    }
+                } else {
+                    utils::assert_payment_destination(
+                        &offer.currency_mint,
+                        referrer_info,
+                        Some(&referrer),
+                    )?;
+                }
+                if referrer == *ctx.accounts.holder.key {
+                    return Err(ErrorCode::InvalidReferrer.into());
+                }
+                let referral_bps = ctx.accounts.listing.referral_bps(&ctx.accounts.config);
+                let referral = utils::basis_points_of(fee, referral_bps);
+                utils::record_referral(
+                    stats_info,
+                    &referrer,
+                    &ctx.accounts.holder.to_account_info(),
+                    &ctx.accounts.system_program.to_account_info(),
+                    ctx.program_id,
+                    Some((offer.currency_mint, offer.amount, referral)),
+                )?;
+                Some((referrer_info, referral))
+            }
+            _ => return Err(ErrorCode::InvalidRemainingAccounts.into()),
+        };
+        let referral_amount = referral.map_or(0, |(_, amount)| amount);
+        let fee_destination = utils::record_platform_fee(
+            &ctx.accounts.platform_treasury,
+            Some(&ctx.accounts.platform_vault),
+            &offer.currency_mint,
+            fee - referral_amount,
+            ctx.program_id,
+        )?;
+        remaining = remaining
+            .checked_sub(fee)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+        msg!("Paying {} to the holder", remaining);
+
+        if native {
+            //Unwrap by closing the escrow to the holder, who then pays the fee, the
+            //referral and the escrow rent back in lamports
+            token::close_account(CpiContext::new_with_signer(
+                token_program,
+                token::CloseAccount {
+                    account: escrow,
+                    destination: ctx.accounts.holder.to_account_info(),
+                    authority: offer_info,
+                },
+                &[&seeds[..]],
+            ))?;
+            let holder = ctx.accounts.holder.to_account_info();
+            let system_program = ctx.accounts.system_program.to_account_info();
+            let refund = escrow_rent + surplus;
+            utils::pay_lamports(&holder, &ctx.accounts.bidder, &system_program, refund)?;
+            if let Some((referrer_info, referral)) = referral {
+                utils::pay_lamports(&holder, referrer_info, &system_program, referral)?;
+            }
+            return utils::pay_lamports(
+                &holder,
+                fee_destination,
+                &system_program,
+                fee - referral_amount,
+            );
+        }
+
+        if let Some((referrer_info, referral)) = referral {
+            utils::transfer_signed(
+                &token_program,
+                &escrow,
+                referrer_info,
+                &offer_info,
+                seeds,
+                referral,
+            )?;
+        }
+        utils::transfer_signed(
+            &token_program,
+            &escrow,
+            fee_destination,
+            &offer_info,
+            seeds,
+            fee - referral_amount,
+        )?;
+        utils::assert_payment_destination(&offer.currency_mint, &ctx.accounts.proceeds, None)?;
+        utils::transfer_signed(
+            &token_program,
+            &escrow,
+            &ctx.accounts.proceeds,
+            &offer_info,
+            seeds,
+            remaining,
+        )?;
+        if surplus > 0 {
+            utils::assert_payment_destination(
+                &offer.currency_mint,
+                &ctx.accounts.bidder_currency,
+                Some(&offer.bidder),
+            )?;
+            utils::transfer_signed(
+                &token_program,
+                &escrow,
+                &ctx.accounts.bidder_currency,
+                &offer_info,
+                seeds,
+                surplus,
+            )?;
+        }
+
+        token::close_account(CpiContext::new_with_signer(
+            token_program,
+            token::CloseAccount {
+                account: escrow,
+                destination: ctx.accounts.bidder.clone(),
+                authority: offer_info,
+            },
+            &[&seeds[..]],
+        ))
+    }
+
+    pub fn create_royalty_treasury(
+        ctx: Context<CreateRoyaltyTreasury>,
+        bump: u8,
+        _vault_bump: u8,
+    ) -> ProgramResult {
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.master_edition = ctx.accounts.master_edition.key();
+        treasury.currency_mint = ctx.accounts.currency_mint.key();
+        treasury.total_received = 0;
+        treasury.claims = ctx
+            .accounts
+            .master_metadata
+            .creators()
+            .iter()
+            .map(|creator| CreatorClaim {
+                creator: creator.address,
+                share: creator.share,
+                claimed: 0,
+            })
+            .collect();
+        treasury.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn claim_royalties(ctx: Context<ClaimRoyalties>) -> ProgramResult {
+        let treasury = &mut ctx.accounts.treasury;
+        let creator = ctx.accounts.creator.key;
+        let amount =
+            utils::claimable_royalties(treasury, &ctx.accounts.master_metadata.data, creator)?;
+        if let Some(claim) = treasury.claims.iter_mut().find(|claim| &claim.creator == creator) {
+            claim.claimed += amount;
+        }
+
+        msg!("Claiming {} royalties", amount);
+        let seeds = &[
+            b"royalty_treasury".as_ref(),
+            treasury.master_edition.as_ref(),
+            treasury.currency_mint.as_ref(),
+            &[treasury.bump],
+        ];
+        utils::transfer_signed(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.royalty_vault.to_account_info(),
+            &ctx.accounts.creator_token.to_account_info(),
+            &treasury.to_account_info(),
+            seeds,
+            amount,
+        )
+    }
+
+    pub fn set_referral_rate(ctx: Context<SetPlatformFee>, referral_bps: u16) -> ProgramResult {
+        utils::assert_admin_quorum(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        if referral_bps > 10_000 {
+            return Err(ErrorCode::InvalidFee.into());
+        }
+        ctx.accounts.config.referral_bps = referral_bps;
+
+        Ok(())
+    }
+
+    pub fn set_listing_referral_rate(
+        ctx: Context<UpdateListing>,
+        referral_bps: Option<u16>,
+    ) -> ProgramResult {
+        if matches!(referral_bps, Some(bps) if bps > 10_000) {
+            return Err(ErrorCode::InvalidFee.into());
+        }
+        ctx.accounts.listing.referral_bps = referral_bps;
+
+        Ok(())
+    }
+
+    /// Sets the price of editions in `currency_mint`, or stops accepting it when `None`.
+    pub fn set_listing_price(
+        ctx: Context<SetListingPrice>,
+        currency_mint: Pubkey,
+        price: Option<u64>,
+    ) -> ProgramResult {
+        let prices = &mut ctx.accounts.listing.prices;
+        let index = prices.iter().position(|price| price.mint == currency_mint);
+        match (index, price) {
+            (Some(index), Some(amount)) => prices[index].amount = amount,
+            (Some(index), None) => {
+                prices.remove(index);
+            }
+            (None, Some(amount)) => {
+                if prices.len() >= MAX_LISTING_CURRENCIES {
+                    return Err(ErrorCode::TooManyListingCurrencies.into());
+                }
+                prices.push(ListingPrice {
+                    mint: currency_mint,
+                    amount,
+                });
+            }
+            (None, None) => return Err(ErrorCode::CurrencyNotAccepted.into()),
+        }
+
+        Ok(())
+    }
+
+    pub fn create_listing_treasury(
+        ctx: Context<CreateListingTreasury>,
+        bump: u8,
+        vault_bump: u8,
+        beneficiary: Pubkey,
+    ) -> ProgramResult {
+        let currency_mint = ctx.accounts.currency_mint.key();
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.listing = ctx.accounts.listing.key();
+        treasury.currency_mint = currency_mint;
+        treasury.beneficiary = beneficiary;
+        treasury.gross_sales = 0;
+        treasury.fees = 0;
+        treasury.royalties = 0;
+        treasury.withdrawn = 0;
+        treasury.bump = bump;
+
+        //Native SOL stays in the treasury itself, other currencies need a vault
+        if !utils::is_native(&currency_mint) {
+            let treasury_key = treasury.key();
+            let vault = &ctx.accounts.vault;
+            utils::create_pda_account(
+                &ctx.accounts.authority.to_account_info(),
+                vault,
+                &ctx.accounts.system_program.to_account_info(),
+                TokenAccount::LEN,
+                &spl_token::id(),
+                &[b"listing_vault", treasury_key.as_ref(), &[vault_bump]],
+            )?;
+            token::initialize_account(CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::InitializeAccount {
+                    account: vault.clone(),
+                    mint: ctx.accounts.currency_mint.to_account_info(),
+                    authority: treasury.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn withdraw_proceeds(ctx: Context<WithdrawProceeds>, amount: u64) -> ProgramResult {
+        let treasury = &mut ctx.accounts.treasury;
+        if amount > treasury.available() {
+            return Err(ErrorCode::InsufficientProceeds.into());
+        }
+        utils::assert_payment_destination(
+            &treasury.currency_mint,
+            &ctx.accounts.destination,
+            Some(&treasury.beneficiary),
+        )?;
+
+        msg!("Withdrawing {} proceeds", amount);
+        if utils::is_native(&treasury.currency_mint) {
+            utils::transfer_lamports(
+                &treasury.to_account_info(),
+                &ctx.accounts.destination,
+                amount,
+            )?;
+        } else {
+            let seeds = &[
+                b"listing_treasury".as_ref(),
+                treasury.listing.as_ref(),
+                treasury.currency_mint.as_ref(),
+                &[treasury.bump],
+            ];
+            utils::transfer_signed(
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.vault,
+                &ctx.accounts.destination,
+                &treasury.to_account_info(),
+                seeds,
+                amount,
+            )?;
+        }
+        treasury.withdrawn = treasury
+            .withdrawn
+            .checked_add(amount)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+
+        Ok(())
+    }
+
+    /// Replaces the admin signer set, approved by a quorum of the current one.
+    pub fn set_admins(
+        ctx: Context<SetAdmins>,
+        admins: Vec<Pubkey>,
+        admin_threshold: u8,
+    ) -> ProgramResult {
+        utils::assert_admin_quorum(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        let distinct = admins
+            .iter()
+            .enumerate()
+            .all(|(index, admin)| !admins[..index].contains(admin));
+        if admins.len() > MAX_ADMINS
+            || !distinct
+            || admin_threshold == 0
+            || admin_threshold as usize > admins.len()
+        {
+            return Err(ErrorCode::InvalidAdminSet.into());
+        }
+        let config = &mut ctx.accounts.config;
+        config.admins = admins;
+        config.admin_threshold = admin_threshold;
+
+        Ok(())
+    }
+
+    pub fn create_platform_treasury(
+        ctx: Context<CreatePlatformTreasury>,
+        bump: u8,
+        vault_bump: u8,
+    ) -> ProgramResult {
+        let currency_mint = ctx.accounts.currency_mint.key();
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.currency_mint = currency_mint;
+        treasury.fees = 0;
+        treasury.withdrawn = 0;
+        treasury.bump = bump;
+
+        //Native SOL stays in the treasury itself, other currencies need a vault
+        if !utils::is_native(&currency_mint) {
+            let treasury_key = treasury.key();
+            let vault = &ctx.accounts.vault;
+            utils::create_pda_account(
+                &ctx.accounts.payer.to_account_info(),
+                vault,
+                &ctx.accounts.system_program.to_account_info(),
+                TokenAccount::LEN,
+                &spl_token::id(),
+                &[b"platform_vault", treasury_key.as_ref(), &[vault_bump]],
+            )?;
+            token::initialize_account(CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::InitializeAccount {
+                    account: vault.clone(),
+                    mint: ctx.accounts.currency_mint.to_account_info(),
+                    authority: treasury.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+            ))?;
+        }
+
+        Ok(())
+    }
 
-    pub fn initialize(ctx: Context<Initialize>, data: String) -> ProgramResult {
-        let base_account = &mut ctx.accounts.base_account;
-        let copy = data.clone();
-        base_account.data = data;
-        base_account.data_list.push(copy);
+    /// Withdraws platform fees to the config fee wallet, which signs.
+    pub fn withdraw_platform_fees(
+        ctx: Context<WithdrawPlatformFees>,
+        amount: u64,
+    ) -> ProgramResult {
+        let treasury = &mut ctx.accounts.treasury;
+        if amount > treasury.available() {
+            return Err(ErrorCode::InsufficientProceeds.into());
+        }
+        utils::assert_payment_destination(
+            &treasury.currency_mint,
+            &ctx.accounts.destination,
+            Some(ctx.accounts.fee_wallet.key),
+        )?;
+
+        msg!("Withdrawing {} platform fees", amount);
+        if utils::is_native(&treasury.currency_mint) {
+            utils::transfer_lamports(
+                &treasury.to_account_info(),
+                &ctx.accounts.destination,
+                amount,
+            )?;
+        } else {
+            let seeds = &[
+                b"platform_treasury".as_ref(),
+                treasury.currency_mint.as_ref(),
+                &[treasury.bump],
+            ];
+            utils::transfer_signed(
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.vault,
+                &ctx.accounts.destination,
+                &treasury.to_account_info(),
+                seeds,
+                amount,
+            )?;
+        }
+        treasury.withdrawn = treasury
+            .withdrawn
+            .checked_add(amount)
+            .ok_or(ErrorCode::NumericalOverflow)?;
 
         Ok(())
     }
@@ -80,241 +1638,1349 @@ pub mod charm {
 
     }
 
-    pub fn faucet(ctx: Context<Faucet>, bump: u8) -> Result<()> {
+    pub fn faucet(ctx: Context<Faucet>, bump: u8) -> Result<()> {
+
+        
+        msg!("Making instruction for faucet");
+        let ix = spl_token::instruction::mint_to(
+            &ctx.accounts.token_program.key,
+            &ctx.accounts.mint.key,
+            &ctx.accounts.user_account.key,
+            &ctx.accounts.pda.key,
+            &[&ctx.accounts.pda.key],
+            100 * 100_00_00,
+        )?;
+        msg!("Invoking instruction for faucet");
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.mint.clone(),
+                ctx.accounts.user_account.clone(),
+                ctx.accounts.pda.clone(),
+                ctx.accounts.token_program.to_account_info(),
+            ],
+            &[&[&b"charmpda"[..], &[bump]]],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn puff_metadata(ctx: Context<PuffMetadata>) -> ProgramResult {
+        msg!("Puff metadata ");
+        token_metadata::puff_metadata_account(CpiContext::new(
+            ctx.accounts.metadata_program.to_account_info(),
+            token_metadata::PuffMetadataAccount {
+                metadata: ctx.accounts.metadata_account.clone(),
+            },
+        ))?;
+        Ok(())
+    }
+
+    pub fn proxy_transfer(ctx: Context<ProxyTransfer>, amount: u64) -> ProgramResult {
+        msg!("Transffering tokens");
+        token::transfer(ctx.accounts.into(), amount)?;
+        Ok(())
+    }
+
+    /// Buys an edition, paying in `currency_mint` when the listing has prices.
+    pub fn buy<'info>(
+        ctx: Context<'_, '_, '_, 'info, Buy<'info>>,
+        edition: u64,
+        pda_nonce: u8,
+        currency_mint: Option<Pubkey>,
+    ) -> ProgramResult {
+        let listing = &ctx.accounts.listing;
+        if listing.closed {
+            return Err(ErrorCode::ListingClosed.into());
+        }
+        //Raffle winners already paid the raffle price when entering
+        let payment = match currency_mint {
+            _ if listing.prices.is_empty() || listing.raffled => None,
+            Some(mint) => Some(listing.price_in(&mint).ok_or(ErrorCode::CurrencyNotAccepted)?),
+            None => return Err(ErrorCode::CurrencyNotAccepted.into()),
+        };
+        if let Some(window) = listing.window {
+            if !window.contains(Clock::get()?.unix_timestamp) {
+                return Err(ErrorCode::OutsideMintWindow.into());
+            }
+        }
+        let edition_owner = if listing.soulbound {
+            ctx.accounts.soulbound_edition.key
+        } else {
+            ctx.accounts.payer.key
+        };
+        if ctx.accounts.new_token_account.owner != *edition_owner {
+            return Err(ErrorCode::InvalidEditionTokenAccount.into());
+        }
+        //Raffled listings only sell to winners, remaining accounts start with [raffle, entry]
+        let mut remaining_accounts = ctx.remaining_accounts;
+        if listing.raffled {
+            let (raffle_info, entry_info) = match remaining_accounts {
+                [raffle_info, entry_info, ..] => (raffle_info, entry_info),
+                _ => return Err(ErrorCode::InvalidRemainingAccounts.into()),
+            };
+            remaining_accounts = &remaining_accounts[2..];
+            let raffle: Account<Raffle> = Account::try_from(raffle_info)?;
+            let mut entry: Account<RaffleEntry> = Account::try_from(entry_info)?;
+            if raffle.master_mint != *ctx.accounts.metadata_mint.key
+                || entry.raffle != raffle.key()
+                || entry.entrant != *ctx.accounts.payer.key
+            {
+                return Err(ErrorCode::InvalidRaffleEntry.into());
+            }
+            match raffle.is_winner(entry.index) {
+                Some(true) if !entry.claimed => {}
+                Some(true) => return Err(ErrorCode::RaffleEntryClaimed.into()),
+                Some(false) => return Err(ErrorCode::NotRaffleWinner.into()),
+                None => return Err(ErrorCode::RaffleNotRevealed.into()),
+            }
+            entry.claimed = true;
+            entry.exit(ctx.program_id)?;
+        }
+        //Optionally followed by [referrer, referrer_stats]
+        let referrer = match remaining_accounts {
+            [] => None,
+            [referrer_info, stats_info] => {
+                let referrer = ctx.accounts.referrer_of(referrer_info, payment)?;
+                if referrer == *ctx.accounts.payer.key {
+                    return Err(ErrorCode::InvalidReferrer.into());
+                }
+                Some((referrer, referrer_info, stats_info))
+            }
+            _ => return Err(ErrorCode::InvalidRemainingAccounts.into()),
+        };
+
+        msg!("Making buy accounts...");
+        let cpi_accounts = token_metadata::MintNewEditionFromMasterEditionViaToken {
+            new_metadata: ctx.accounts.new_metadata_account.clone(),
+            new_edition: ctx.accounts.new_edition_account.clone(),
+            master_edition: ctx.accounts.master_edition_account.clone(),
+            new_mint: ctx.accounts.new_mint_account.clone(),
+            edition_mark_pda: ctx.accounts.edition_pda.clone(),
+            new_mint_authority: ctx.accounts.new_mint_authority.clone(),
+            payer: ctx.accounts.payer.to_account_info(),
+            token_account_owner: ctx.accounts.token_account_owner.clone(),
+            token_account: ctx.accounts.token_account.to_account_info(),
+            new_metadata_update_authority: ctx.accounts.new_metadata_update_authority.clone(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            metadata_mint: ctx.accounts.metadata_mint.clone(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent_program.to_account_info(),
+        };
+
+        msg!("Calling the metadata program to make edition...");
+        //charmpda signs as owner of the escrowed master edition token of the listed master
+        let seeds = &[&b"charmpda"[..], &[pda_nonce]];
+        let signer = &[&seeds[..]];
+        token_metadata::mint_new_edition_from_master_edition_via_token(
+            CpiContext::new_with_signer(
+                ctx.accounts.metadata_program.to_account_info(),
+                cpi_accounts,
+                signer,
+            ),
+            edition,
+        )?;
+        ctx.accounts.collect_price(payment, referrer, ctx.program_id)?;
+
+        //Soulbound editions stay in custody of their record until the listing revokes it
+        if ctx.accounts.listing.soulbound {
+            let new_mint = ctx.accounts.new_mint_account.key;
+            let (_, soulbound_bump) =
+                Pubkey::find_program_address(&[b"soulbound", new_mint.as_ref()], ctx.program_id);
+            msg!("Holding soulbound edition");
+            utils::create_pda_account(
+                &ctx.accounts.payer.to_account_info(),
+                &ctx.accounts.soulbound_edition,
+                &ctx.accounts.system_program.to_account_info(),
+                SoulboundEdition::LEN,
+                ctx.program_id,
+                &[b"soulbound", new_mint.as_ref(), &[soulbound_bump]],
+            )?;
+            SoulboundEdition {
+                mint: *new_mint,
+                master_mint: *ctx.accounts.metadata_mint.key,
+                holder: *ctx.accounts.payer.key,
+                bump: soulbound_bump,
+            }
+            .try_serialize(&mut &mut ctx.accounts.soulbound_edition.try_borrow_mut_data()?[..])?;
+        }
+
+        if let Some(pass) = ctx.accounts.listing.pass {
+            let new_mint = ctx.accounts.new_mint_account.key;
+            let (_, expiry_bump) =
+                Pubkey::find_program_address(&[b"expiry", new_mint.as_ref()], ctx.program_id);
+            msg!("Starting pass");
+            utils::create_pda_account(
+                &ctx.accounts.payer.to_account_info(),
+                &ctx.accounts.expiry,
+                &ctx.accounts.system_program.to_account_info(),
+                Expiry::LEN,
+                ctx.program_id,
+                &[b"expiry", new_mint.as_ref(), &[expiry_bump]],
+            )?;
+            Expiry {
+                mint: *new_mint,
+                master_mint: *ctx.accounts.metadata_mint.key,
+                expires_at: Clock::get()?
+                    .unix_timestamp
+                    .checked_add(pass.duration)
+                    .ok_or(ErrorCode::NumericalOverflow)?,
+                bump: expiry_bump,
+            }
+            .try_serialize(&mut &mut ctx.accounts.expiry.try_borrow_mut_data()?[..])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn metadata(ctx: Context<CreateMetadata>, data: String) -> ProgramResult {
+        //First 20 chars as Name,
+        let name = &data[0..20];
+        //Next 4 as Symbol
+        let symbol = &data[20..24];
+        //Next 65 as ARWEAVE URL
+        let url = &data[24..140];
+        msg!("{}, {}, {}", name.clone(), symbol.clone(), url.clone());
+
+        let creators: Vec<spl_token_metadata::state::Creator> =
+            vec![spl_token_metadata::state::Creator {
+                address: *ctx.accounts.payer.key,
+                verified: true,
+                share: 100,
+            }];
+        msg!("Making metadata accounts...");
+        let cpi_accounts = token_metadata::CreateMetadataAccounts {
+            metadata: ctx.accounts.metadata_account.clone(),
+            mint: ctx.accounts.mint.clone(),
+            mint_authority: ctx.accounts.mint_authority.clone(),
+            payer: ctx.accounts.payer.clone(),
+            update_authority: ctx.accounts.update_authority.clone(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent_program.to_account_info(),
+        };
+        let data = spl_token_metadata::state::Data {
+            name: name.to_string(),
+            symbol: symbol.to_string(),
+            uri: url.to_string(),
+            //Default creator royality.. will be changed to client
+            seller_fee_basis_points: 20,
+            creators: Some(creators),
+        };
+        msg!("Calling the metadata program to make metadata...");
+        token_metadata::create_metadata_accounts(
+            CpiContext::new(ctx.accounts.metadata_program.to_account_info(), cpi_accounts),
+            data,
+            //At the moment defaulting to update authority as signer as well... will be changed to client
+            true,
+            true,
+        )?;
+
+        msg!("Metadata created...");
+
+        let metadata_data = Metadata::try_deserialize(
+            &mut &ctx.accounts.metadata_account.data.borrow()[..],
+        )?;
+
+        msg!("Hopefully got data");
+        msg!(&metadata_data.data.uri);
+        Ok(())
+    }
+
+    //Adding a seprate call for minting master edition
+    pub fn edition<'info>(
+        ctx: Context<'_, '_, '_, 'info, Edition<'info>>,
+        open: bool,
+        metadata_hash: Option<[u8; 32]>,
+        media_hash: Option<[u8; 32]>,
+    ) -> ProgramResult {
+        if metadata_hash.is_none() && media_hash.is_some() {
+            return Err(ErrorCode::MissingMetadataHash.into());
+        }
+        msg!("Making edition accounts...");
+        let cpi_accounts = token_metadata::CreateMasterEdition {
+            edition: ctx.accounts.master_edition_account.clone(),
+            mint: ctx.accounts.mint.clone(),
+            update_authority: ctx.accounts.update_authority.clone(),
+            mint_authority: ctx.accounts.mint_authority.clone(),
+            payer: ctx.accounts.payer.clone(),
+            metadata: ctx.accounts.metadata_account.clone(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent_program.to_account_info(),
+        };
+
+        msg!("Calling the metadata program to make masteredition...");
+        token_metadata::create_master_edition(
+            CpiContext::new(ctx.accounts.metadata_program.to_account_info(), cpi_accounts),
+            //Open editions have no max supply, their listing window caps them instead
+            if open { None } else { Some(1) },
+        )?;
+        msg!("Metadata & editions created");
+
+        //Content hashes are recorded with the master edition, immutable charms included
+        if let Some(metadata_hash) = metadata_hash {
+            let master_edition = ctx.accounts.master_edition_account.key;
+            let (_, bump) = Pubkey::find_program_address(
+                &[b"content_hash", master_edition.as_ref()],
+                ctx.program_id,
+            );
+            msg!("Recording content hash");
+            utils::create_pda_account(
+                &ctx.accounts.payer,
+                &ctx.accounts.content_hash,
+                &ctx.accounts.system_program.to_account_info(),
+                ContentHash::LEN,
+                ctx.program_id,
+                &[b"content_hash", master_edition.as_ref(), &[bump]],
+            )?;
+            ContentHash {
+                master_edition: *master_edition,
+                metadata_hash,
+                media_hash,
+                bump,
+            }
+            .try_serialize(&mut &mut ctx.accounts.content_hash.try_borrow_mut_data()?[..])?;
+        }
+
+        //Optionally register the master edition in a collection,
+        //remaining accounts are [collection, membership]
+        match ctx.remaining_accounts {
+            [] => {}
+            [collection_info, membership_info] => {
+                let mut collection: Account<Collection> = Account::try_from(collection_info)?;
+                let authority_signed = [&ctx.accounts.payer, &ctx.accounts.update_authority]
+                    .iter()
+                    .any(|info| info.is_signer && info.key == &collection.authority);
+                if !authority_signed {
+                    return Err(ErrorCode::CollectionAuthorityMismatch.into());
+                }
+                let metadata = Metadata::try_deserialize(
+                    &mut &ctx.accounts.metadata_account.data.borrow()[..],
+                )?;
+                msg!("Registering master edition in collection");
+                utils::register_member(
+                    &mut collection,
+                    membership_info,
+                    &metadata,
+                    &ctx.accounts.payer,
+                    &ctx.accounts.system_program.to_account_info(),
+                    ctx.program_id,
+                )?;
+            }
+            _ => return Err(ErrorCode::InvalidRemainingAccounts.into()),
+        }
+
+        Ok(())
+    }
+
+    pub fn change_ownership(ctx: Context<ChangeOwnership>) -> ProgramResult {
+        msg!("Creating instruction to change master edition ownership to PDA");
+        let owner_change_ix = spl_token::instruction::set_authority(
+            &ctx.accounts.token_program.key,
+            &ctx.accounts.master_edition_account.key,
+            Some(&ctx.accounts.pda.key),
+            spl_token::instruction::AuthorityType::AccountOwner,
+            &ctx.accounts.signer.key,
+            &[&ctx.accounts.signer.key],
+        )?;
+        msg!("Calling the token program to transfer aster edition token account ownership...");
+        invoke(
+            &owner_change_ix,
+            &[
+                ctx.accounts.master_edition_account.clone(),
+                ctx.accounts.signer.clone(),
+                ctx.accounts.token_program.to_account_info().clone(),
+            ],
+        )?;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        seeds = [b"config".as_ref()],
+        bump = bump,
+        payer = admin,
+        space = Config::LEN
+    )]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// ProgramData of this program, naming `admin` as its upgrade authority.
+    pub program_data: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateProfile<'info> {
+    #[account(
+        init,
+        seeds = [b"profile".as_ref(), authority.key.as_ref()],
+        bump = bump,
+        payer = authority,
+        space = CreatorProfile::LEN
+    )]
+    pub profile: Account<'info, CreatorProfile>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProfile<'info> {
+    #[account(mut, has_one = authority)]
+    pub profile: Account<'info, CreatorProfile>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyProfile<'info> {
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub profile: Account<'info, CreatorProfile>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct AddProfilePage<'info> {
+    #[account(mut, has_one = authority)]
+    pub profile: Account<'info, CreatorProfile>,
+    #[account(
+        init,
+        seeds = [
+            b"profile_page".as_ref(),
+            authority.key.as_ref(),
+            &profile.page_count.to_le_bytes()
+        ],
+        bump = bump,
+        payer = authority,
+        space = ProfilePage::LEN
+    )]
+    pub page: Account<'info, ProfilePage>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateCollection<'info> {
+    #[account(mut, has_one = authority)]
+    pub profile: Account<'info, CreatorProfile>,
+    #[account(mut, has_one = profile, constraint = page.page + 1 == profile.page_count)]
+    pub page: Account<'info, ProfilePage>,
+    #[account(
+        init,
+        seeds = [
+            b"collection".as_ref(),
+            profile.key().as_ref(),
+            &profile.collection_count.to_le_bytes()
+        ],
+        bump = bump,
+        payer = authority,
+        space = Collection::LEN
+    )]
+    pub collection: Account<'info, Collection>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct AddMember<'info> {
+    #[account(mut, has_one = authority)]
+    pub collection: Account<'info, Collection>,
+    #[account(
+        init,
+        seeds = [b"membership".as_ref(), collection.key().as_ref(), metadata.mint.as_ref()],
+        bump = bump,
+        payer = authority,
+        space = Membership::LEN
+    )]
+    pub membership: Account<'info, Membership>,
+    #[account(constraint = metadata.update_authority == update_authority.key())]
+    pub metadata: Account<'info, Metadata>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    //Masters only join a collection with the consent of their update authority
+    pub update_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveMember<'info> {
+    #[account(mut, has_one = authority)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut, has_one = collection, close = authority)]
+    pub membership: Account<'info, Membership>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferCollectionAuthority<'info> {
+    #[account(mut, has_one = authority)]
+    pub collection: Account<'info, Collection>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateAttributes<'info> {
+    #[account(constraint = metadata.update_authority == update_authority.key())]
+    pub metadata: Account<'info, Metadata>,
+    #[account(address = utils::edition_address(&metadata.mint))]
+    pub master_edition: Account<'info, MasterEdition>,
+    #[account(
+        init,
+        seeds = [b"attributes".as_ref(), master_edition.key().as_ref()],
+        bump = bump,
+        payer = update_authority,
+        space = CharmAttributes::LEN
+    )]
+    pub attributes: Account<'info, CharmAttributes>,
+    #[account(mut)]
+    pub update_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAttributes<'info> {
+    #[account(constraint = metadata.update_authority == update_authority.key())]
+    pub metadata: Account<'info, Metadata>,
+    #[account(
+        mut,
+        seeds = [b"attributes".as_ref(), utils::edition_address(&metadata.mint).as_ref()],
+        bump = attributes.bump
+    )]
+    pub attributes: Account<'info, CharmAttributes>,
+    pub update_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateContentHash<'info> {
+    #[account(constraint = metadata.update_authority == update_authority.key())]
+    pub metadata: Account<'info, Metadata>,
+    #[account(
+        init,
+        seeds = [
+            b"content_hash".as_ref(),
+            utils::edition_address(&metadata.mint).as_ref()
+        ],
+        bump = bump,
+        payer = update_authority,
+        space = ContentHash::LEN
+    )]
+    pub content_hash: Account<'info, ContentHash>,
+    #[account(mut)]
+    pub update_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateContentHash<'info> {
+    #[account(constraint = metadata.update_authority == update_authority.key())]
+    pub metadata: Account<'info, Metadata>,
+    #[account(
+        mut,
+        seeds = [
+            b"content_hash".as_ref(),
+            utils::edition_address(&metadata.mint).as_ref()
+        ],
+        bump = content_hash.bump
+    )]
+    pub content_hash: Account<'info, ContentHash>,
+    pub update_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateBracelet<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    //Only a one of one NFT can carry charms
+    #[account(constraint = parent_mint.supply == 1, constraint = parent_mint.decimals == 0)]
+    pub parent_mint: Account<'info, token::Mint>,
+    #[account(
+        constraint = parent_token.owner == owner.key(),
+        constraint = parent_token.mint == parent_mint.key(),
+        constraint = parent_token.amount == 1
+    )]
+    pub parent_token: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        seeds = [b"bracelet".as_ref(), parent_mint.key().as_ref()],
+        bump = bump,
+        payer = owner,
+        space = Bracelet::LEN
+    )]
+    pub bracelet: Account<'info, Bracelet>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_bump: u8)]
+pub struct AttachCharm<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        constraint = parent_token.owner == owner.key(),
+        constraint = parent_token.mint == bracelet.parent_mint,
+        constraint = parent_token.amount == 1
+    )]
+    pub parent_token: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub bracelet: Account<'info, Bracelet>,
+    pub charm_mint: AccountInfo<'info>,
+    #[account(address = utils::edition_address(charm_mint.key))]
+    pub charm_edition: Account<'info, token_metadata::Edition>,
+    #[account(mut, constraint = charm_token.mint == charm_mint.key())]
+    pub charm_token: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        token::mint = charm_mint,
+        token::authority = bracelet,
+        seeds = [b"escrow".as_ref(), bracelet.key().as_ref(), charm_mint.key.as_ref()],
+        bump = escrow_bump,
+        payer = owner
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct DetachCharm<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        address = bracelet.parent_mint,
+        constraint = parent_mint.supply == 1,
+        constraint = parent_mint.decimals == 0
+    )]
+    pub parent_mint: Account<'info, token::Mint>,
+    #[account(
+        constraint = parent_token.owner == owner.key(),
+        constraint = parent_token.mint == bracelet.parent_mint,
+        constraint = parent_token.amount == 1
+    )]
+    pub parent_token: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub bracelet: Account<'info, Bracelet>,
+    pub charm_mint: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"escrow".as_ref(), bracelet.key().as_ref(), charm_mint.key.as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+    #[account(mut, constraint = destination.mint == charm_mint.key())]
+    pub destination: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct Engrave<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    pub edition_mint: AccountInfo<'info>,
+    pub holder_token: Account<'info, TokenAccount>,
+    #[account(address = utils::edition_address(edition_mint.key))]
+    pub edition: Account<'info, token_metadata::Edition>,
+    #[account(address = edition.parent)]
+    pub master_edition: Account<'info, MasterEdition>,
+    //Created on the first engraving
+    #[account(mut, seeds = [b"engraving".as_ref(), edition_mint.key.as_ref()], bump = bump)]
+    pub engraving: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureRewards<'info> {
+    #[account(mut, seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRewardRate<'info> {
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub collection: Account<'info, Collection>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(stake_bump: u8, vault_bump: u8)]
+pub struct StakeEdition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub edition_mint: AccountInfo<'info>,
+    #[account(address = utils::edition_address(edition_mint.key))]
+    pub edition: Account<'info, token_metadata::Edition>,
+    #[account(constraint = edition.parent == utils::edition_address(master_mint.key))]
+    pub master_mint: AccountInfo<'info>,
+    pub collection: Account<'info, Collection>,
+    #[account(
+        seeds = [b"membership".as_ref(), collection.key().as_ref(), master_mint.key.as_ref()],
+        bump = membership.bump
+    )]
+    pub membership: Account<'info, Membership>,
+    #[account(mut)]
+    pub owner_token: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        seeds = [b"stake".as_ref(), edition_mint.key.as_ref()],
+        bump = stake_bump,
+        payer = owner,
+        space = StakeRecord::LEN
+    )]
+    pub stake: Account<'info, StakeRecord>,
+    #[account(
+        init,
+        token::mint = edition_mint,
+        token::authority = stake,
+        seeds = [b"stake_vault".as_ref(), edition_mint.key.as_ref()],
+        bump = vault_bump,
+        payer = owner
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+#[instruction(pda_nonce: u8)]
+pub struct ClaimRewards<'info> {
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = collection,
+        seeds = [b"stake".as_ref(), stake.edition_mint.as_ref()],
+        bump = stake.bump
+    )]
+    pub stake: Account<'info, StakeRecord>,
+    pub collection: Account<'info, Collection>,
+    #[account(mut, seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, address = config.reward_mint)]
+    pub reward_mint: AccountInfo<'info>,
+    #[account(mut, constraint = reward_account.mint == config.reward_mint)]
+    pub reward_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"charmpda".as_ref()], bump = pda_nonce)]
+    pub pda: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+#[instruction(pda_nonce: u8)]
+pub struct UnstakeEdition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = collection,
+        seeds = [b"stake".as_ref(), stake.edition_mint.as_ref()],
+        bump = stake.bump,
+        close = owner
+    )]
+    pub stake: Account<'info, StakeRecord>,
+    pub collection: Account<'info, Collection>,
+    #[account(mut, seeds = [b"stake_vault".as_ref(), stake.edition_mint.as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, constraint = destination.mint == stake.edition_mint)]
+    pub destination: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, address = config.reward_mint)]
+    pub reward_mint: AccountInfo<'info>,
+    #[account(mut, constraint = reward_account.mint == config.reward_mint)]
+    pub reward_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"charmpda".as_ref()], bump = pda_nonce)]
+    pub pda: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct RedeemEdition<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    #[account(mut)]
+    pub edition_mint: AccountInfo<'info>,
+    #[account(mut)]
+    pub holder_token: Account<'info, TokenAccount>,
+    #[account(address = utils::edition_address(edition_mint.key))]
+    pub edition: Account<'info, token_metadata::Edition>,
+    #[account(constraint = utils::edition_address(&master_metadata.mint) == edition.parent)]
+    pub master_metadata: Account<'info, Metadata>,
+    #[account(
+        init,
+        seeds = [b"redemption".as_ref(), edition_mint.key.as_ref()],
+        bump = bump,
+        payer = holder,
+        space = Redemption::LEN
+    )]
+    pub redemption: Account<'info, Redemption>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct FulfilRedemption<'info> {
+    #[account(mut, has_one = authority)]
+    pub redemption: Account<'info, Redemption>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RenewPass<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"listing".as_ref(), expiry.master_mint.as_ref()],
+        bump = listing.bump,
+        has_one = authority
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(mut)]
+    pub authority: AccountInfo<'info>,
+    #[account(mut, seeds = [b"expiry".as_ref(), expiry.mint.as_ref()], bump = expiry.bump)]
+    pub expiry: Account<'info, Expiry>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct AssertPassValid<'info> {
+    pub holder: Signer<'info>,
+    pub holder_token: Account<'info, TokenAccount>,
+    #[account(seeds = [b"expiry".as_ref(), expiry.mint.as_ref()], bump = expiry.bump)]
+    pub expiry: Account<'info, Expiry>,
+    pub clock: Sysvar<'info, Clock>,
+}
 
-        
-        msg!("Making instruction for faucet");
-        let ix = spl_token::instruction::mint_to(
-            &ctx.accounts.token_program.key,
-            &ctx.accounts.mint.key,
-            &ctx.accounts.user_account.key,
-            &ctx.accounts.pda.key,
-            &[&ctx.accounts.pda.key],
-            100 * 100_00_00,
-        )?;
-        msg!("Invoking instruction for faucet");
-        invoke_signed(
-            &ix,
-            &[
-                ctx.accounts.mint.clone(),
-                ctx.accounts.user_account.clone(),
-                ctx.accounts.pda.clone(),
-                ctx.accounts.token_program.to_account_info(),
-            ],
-            &[&[&b"charmpda"[..], &[bump]]],
-        )?;
+#[derive(Accounts)]
+pub struct ProveOwnership<'info> {
+    pub wallet: Signer<'info>,
+    pub token_account: Account<'info, TokenAccount>,
+    /// Metadata of the edition mint.
+    pub metadata: Account<'info, Metadata>,
+    pub edition: Account<'info, token_metadata::Edition>,
+    pub master_edition: Account<'info, MasterEdition>,
+}
 
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct CloseOpenEdition<'info> {
+    #[account(mut, has_one = authority)]
+    pub listing: Account<'info, Listing>,
+    pub authority: Signer<'info>,
+    pub clock: Sysvar<'info, Clock>,
+}
 
-    pub fn puff_metadata(ctx: Context<PuffMetadata>) -> ProgramResult {
-        msg!("Puff metadata ");
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateRaffle<'info> {
+    #[account(mut, has_one = authority)]
+    pub listing: Account<'info, Listing>,
+    #[account(address = utils::edition_address(&listing.master_mint))]
+    pub master_edition: Account<'info, MasterEdition>,
+    #[account(
+        init,
+        seeds = [b"raffle".as_ref(), listing.master_mint.as_ref()],
+        bump = bump,
+        payer = authority,
+        space = Raffle::LEN
+    )]
+    pub raffle: Account<'info, Raffle>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-        let ix = puff_metadata_account(
-            *ctx.accounts.metadata_program.key,
-            *ctx.accounts.metadata_account.key,
-        );
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct EnterRaffle<'info> {
+    #[account(mut, seeds = [b"raffle".as_ref(), raffle.master_mint.as_ref()], bump = raffle.bump)]
+    pub raffle: Account<'info, Raffle>,
+    #[account(
+        init,
+        seeds = [b"raffle_entry".as_ref(), raffle.key().as_ref(), entrant.key.as_ref()],
+        bump = bump,
+        payer = entrant,
+        space = RaffleEntry::LEN
+    )]
+    pub entry: Account<'info, RaffleEntry>,
+    #[account(mut)]
+    pub entrant: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
 
-        invoke(
-            &ix,
-            &[
-                ctx.accounts.mint.clone(),
-                ctx.accounts.signer.clone(),
-                ctx.accounts.metadata_account.clone(),
-                ctx.accounts.metadata_program.to_account_info(),
-            ],
-        )?;
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct CloseRaffleEntries<'info> {
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,
+    pub clock: Sysvar<'info, Clock>,
+}
 
-    pub fn proxy_transfer(ctx: Context<ProxyTransfer>, amount: u64) -> ProgramResult {
-        msg!("Transffering tokens");
-        token::transfer(ctx.accounts.into(), amount)?;
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct RevealRaffle<'info> {
+    #[account(mut, has_one = authority)]
+    pub raffle: Account<'info, Raffle>,
+    pub authority: Signer<'info>,
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+    pub clock: Sysvar<'info, Clock>,
+}
 
-    pub fn buy(ctx: Context<Buy>, edition: u64, _pda_nonce: u8) -> ProgramResult {
-        msg!("Making buy accounts vector...");
-        let metadata_infos = vec![
-            ctx.accounts.metadata_program.clone(),
-            ctx.accounts.new_metadata_account.clone(),
-            ctx.accounts.new_edition_account.clone(),
-            ctx.accounts.master_edition_account.clone(),
-            ctx.accounts.new_mint_account.clone(),
-            ctx.accounts.new_mint_authority.clone(),
-            ctx.accounts.payer.to_account_info().clone(),
-            ctx.accounts.token_account_owner.clone(),
-            ctx.accounts.token_account.clone(),
-            ctx.accounts.new_metadata_update_authority.clone(),
-            ctx.accounts.metadata.clone(),
-            ctx.accounts.metadata_mint.clone(),
-            ctx.accounts.system_program.to_account_info(),
-            ctx.accounts.rent_program.to_account_info(),
-            ctx.accounts.edition_pda.clone(),
-            ctx.accounts.token_program.to_account_info(),
-        ];
+#[derive(Accounts)]
+pub struct ExpireRaffle<'info> {
+    #[account(seeds = [b"raffle".as_ref(), raffle.master_mint.as_ref()], bump = raffle.bump)]
+    pub raffle: Account<'info, Raffle>,
+    #[account(mut, seeds = [b"listing".as_ref(), raffle.master_mint.as_ref()], bump = listing.bump)]
+    pub listing: Account<'info, Listing>,
+    pub clock: Sysvar<'info, Clock>,
+}
 
-        msg!("Creating edition from master edition");
-        let ix = mint_new_edition_from_master_edition_via_token(
-            *ctx.accounts.metadata_program.key,
-            *ctx.accounts.new_metadata_account.key,
-            *ctx.accounts.new_edition_account.key,
-            *ctx.accounts.master_edition_account.key,
-            *ctx.accounts.new_mint_account.key,
-            *ctx.accounts.new_mint_authority.key,
-            *ctx.accounts.payer.key,
-            *ctx.accounts.token_account_owner.key,
-            *ctx.accounts.token_account.key,
-            *ctx.accounts.new_metadata_update_authority.key,
-            *ctx.accounts.metadata.key,
-            *ctx.accounts.metadata_mint.key,
-            edition,
-        );
+#[derive(Accounts)]
+pub struct ClaimRaffleRefund<'info> {
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,
+    #[account(mut, has_one = raffle, has_one = entrant, close = entrant)]
+    pub entry: Account<'info, RaffleEntry>,
+    #[account(mut)]
+    pub entrant: Signer<'info>,
+    pub clock: Sysvar<'info, Clock>,
+}
 
-        msg!("Calling the metadata program to make edition...");
-        invoke(&ix, metadata_infos.as_slice())?;
-        /* invoke_signed(
-            &ix,
-            metadata_infos.as_slice(),
-            &[&[b"charmpda", &[pda_nonce]]],
-        )?; */
+#[derive(Accounts)]
+pub struct WithdrawRaffleProceeds<'info> {
+    #[account(mut, has_one = authority)]
+    pub raffle: Account<'info, Raffle>,
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"listing".as_ref(), raffle.master_mint.as_ref()], bump = listing.bump)]
+    pub listing: Account<'info, Listing>,
+    #[account(constraint = master_metadata.mint == raffle.master_mint)]
+    pub master_metadata: Account<'info, Metadata>,
+    #[account(seeds = [b"config".as_ref()], bump)]
+    pub config: AccountInfo<'info>,
+    /// Native SOL `PlatformTreasury`.
+    #[account(mut)]
+    pub platform_treasury: AccountInfo<'info>,
+    /// Native SOL `RoyaltyTreasury` of the master edition.
+    #[account(mut)]
+    pub royalty_treasury: AccountInfo<'info>,
+    #[account(mut)]
+    pub royalty_vault: AccountInfo<'info>,
+    /// Native SOL `ListingTreasury` of the listing.
+    #[account(mut)]
+    pub listing_treasury: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+}
 
-        Ok(())
-    }
+#[derive(Accounts)]
+#[instruction(bump: u8, id: u64)]
+pub struct CreateBundle<'info> {
+    #[account(
+        init,
+        seeds = [b"bundle".as_ref(), authority.key.as_ref(), &id.to_le_bytes()],
+        bump = bump,
+        payer = authority,
+        space = Bundle::LEN
+    )]
+    pub bundle: Account<'info, Bundle>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-    pub fn metadata(ctx: Context<CreateMetadata>, data: String) -> ProgramResult {
-        //First 20 chars as Name,
-        let name = &data[0..20];
-        //Next 4 as Symbol
-        let symbol = &data[20..24];
-        //Next 65 as ARWEAVE URL
-        let url = &data[24..140];
-        msg!("{}, {}, {}", name.clone(), symbol.clone(), url.clone());
+#[derive(Accounts)]
+#[instruction(editions: Vec<u64>, pda_nonce: u8)]
+pub struct BuyBundle<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"bundle".as_ref(), bundle.authority.as_ref(), &bundle.id.to_le_bytes()],
+        bump = bundle.bump
+    )]
+    pub bundle: Account<'info, Bundle>,
+    #[account(seeds = [b"charmpda".as_ref()], bump = pda_nonce)]
+    pub pda: AccountInfo<'info>,
+    #[account(seeds = [b"config".as_ref()], bump)]
+    pub config: AccountInfo<'info>,
+    /// Native SOL `PlatformTreasury`.
+    #[account(mut)]
+    pub platform_treasury: AccountInfo<'info>,
+    #[account(executable)]
+    pub metadata_program: Program<'info, TokenMetadata>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
 
-        let creators: Vec<spl_token_metadata::state::Creator> =
-            vec![spl_token_metadata::state::Creator {
-                address: *ctx.accounts.payer.key,
-                verified: true,
-                share: 100,
-            }];
-        msg!("Making metadata accounts vector...");
-        let metadata_infos = vec![
-            ctx.accounts.metadata_account.clone(),
-            ctx.accounts.master_edition_account.clone(),
-            ctx.accounts.metadata_program.clone(),
-            ctx.accounts.mint.clone(),
-            ctx.accounts.mint_authority.clone(),
-            ctx.accounts.update_authority.clone(),
-            ctx.accounts.payer.clone(),
-            ctx.accounts.system_program.to_account_info(),
-            ctx.accounts.rent_program.to_account_info(),
-            ctx.accounts.token_program.to_account_info(),
-        ];
-        msg!("Making metadata instruction");
-        let instruction = create_metadata_accounts(
-            *ctx.accounts.metadata_program.key,
-            *ctx.accounts.metadata_account.key,
-            *ctx.accounts.mint.key,
-            *ctx.accounts.mint_authority.key,
-            *ctx.accounts.payer.key,
-            *ctx.accounts.update_authority.key,
-            name.to_string(),
-            symbol.to_string(),
-            url.to_string(),
-            Some(creators),
-            //Default creator royality.. will be changed to client
-            20,
-            //At the moment defaulting to update authority as signer as well... will be changed to client
-            true,
-            true,
-        );
-        msg!("Calling the metadata program to make metadata...");
-        invoke(&instruction, metadata_infos.as_slice())?;
+#[derive(Accounts)]
+pub struct SetPlatformFee<'info> {
+    #[account(mut, seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
 
-        msg!("Metadata created...");
+#[derive(Accounts)]
+#[instruction(offer_bump: u8, escrow_bump: u8)]
+pub struct MakeOffer<'info> {
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    /// Metadata of the edition bid on.
+    pub metadata: Account<'info, Metadata>,
+    pub currency_mint: Account<'info, token::Mint>,
+    /// Currency token account paying the offer, unused for native SOL which is
+    /// wrapped straight into the escrow.
+    #[account(mut)]
+    pub bidder_token: AccountInfo<'info>,
+    #[account(
+        init,
+        seeds = [b"offer".as_ref(), metadata.mint.as_ref(), bidder.key.as_ref()],
+        bump = offer_bump,
+        payer = bidder,
+        space = Offer::LEN
+    )]
+    pub offer: Account<'info, Offer>,
+    #[account(
+        init,
+        token::mint = currency_mint,
+        token::authority = offer,
+        seeds = [b"offer_escrow".as_ref(), offer.key().as_ref()],
+        bump = escrow_bump,
+        payer = bidder
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub clock: Sysvar<'info, Clock>,
+}
 
-        //let data = Metadata::unpack(&ctx.accounts.metadata_account.data.borrow());
-        let metadata_data: Metadata =
-            try_from_slice_unchecked(&ctx.accounts.metadata_account.data.borrow())?;
+#[derive(Accounts)]
+pub struct CancelOffer<'info> {
+    /// The bidder when cancelling, anyone when closing an expired offer.
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = bidder,
+        seeds = [b"offer".as_ref(), offer.edition_mint.as_ref(), offer.bidder.as_ref()],
+        bump = offer.bump,
+        close = bidder
+    )]
+    pub offer: Account<'info, Offer>,
+    #[account(mut)]
+    pub bidder: AccountInfo<'info>,
+    #[account(mut, seeds = [b"offer_escrow".as_ref(), offer.key().as_ref()], bump)]
+    pub escrow: Account<'info, TokenAccount>,
+    /// Currency token account of the bidder, unused for native SOL.
+    #[account(mut)]
+    pub bidder_token: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub clock: Sysvar<'info, Clock>,
+}
 
-        msg!("Hopefully got data");
-        msg!(&metadata_data.data.uri);
-        Ok(())
+impl<'info> CancelOffer<'info> {
+    /// Returns the escrowed tokens and the escrow rent to the bidder. Native SOL
+    /// is unwrapped by closing the escrow.
+    fn refund(&self) -> ProgramResult {
+        let offer = &self.offer;
+        let expired = self.clock.unix_timestamp >= offer.expires_at;
+        if !expired && self.signer.key != self.bidder.key {
+            return Err(ErrorCode::OfferNotExpired.into());
+        }
+        let seeds = &[
+            b"offer".as_ref(),
+            offer.edition_mint.as_ref(),
+            offer.bidder.as_ref(),
+            &[offer.bump],
+        ];
+        msg!("Refunding offer");
+        if !utils::is_native(&offer.currency_mint) {
+            utils::assert_payment_destination(
+                &offer.currency_mint,
+                &self.bidder_token,
+                Some(&offer.bidder),
+            )?;
+            utils::transfer_signed(
+                &self.token_program.to_account_info(),
+                &self.escrow.to_account_info(),
+                &self.bidder_token,
+                &offer.to_account_info(),
+                seeds,
+                self.escrow.amount,
+            )?;
+        }
+        token::close_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            token::CloseAccount {
+                account: self.escrow.to_account_info(),
+                destination: self.bidder.clone(),
+                authority: offer.to_account_info(),
+            },
+            &[&seeds[..]],
+        ))
     }
+}
 
-    //Adding a seprate call for minting master edition
-    pub fn edition(ctx: Context<Edition>) -> ProgramResult {
-        msg!("Making edition accounts vector...");
-        let metadata_infos = vec![
-            ctx.accounts.metadata_account.clone(),
-            ctx.accounts.master_edition_account.clone(),
-            ctx.accounts.metadata_program.clone(),
-            ctx.accounts.mint.clone(),
-            ctx.accounts.mint_authority.clone(),
-            ctx.accounts.update_authority.clone(),
-            ctx.accounts.payer.clone(),
-            ctx.accounts.system_program.to_account_info(),
-            ctx.accounts.rent_program.to_account_info(),
-            ctx.accounts.token_program.to_account_info(),
-        ];
+#[derive(Accounts)]
+pub struct AcceptOffer<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    #[account(
+        mut,
+        has_one = bidder,
+        seeds = [b"offer".as_ref(), offer.edition_mint.as_ref(), offer.bidder.as_ref()],
+        bump = offer.bump,
+        close = bidder
+    )]
+    pub offer: Account<'info, Offer>,
+    #[account(mut)]
+    pub bidder: AccountInfo<'info>,
+    #[account(mut, seeds = [b"offer_escrow".as_ref(), offer.key().as_ref()], bump)]
+    pub escrow: Account<'info, TokenAccount>,
+    #[account(constraint = metadata.mint == offer.edition_mint)]
+    pub metadata: Account<'info, Metadata>,
+    #[account(address = utils::edition_address(&offer.edition_mint))]
+    pub edition: AccountInfo<'info>,
+    /// Listing of the master edition, its referral rate overrides the config one.
+    pub listing: Account<'info, Listing>,
+    #[account(mut)]
+    pub treasury: Account<'info, RoyaltyTreasury>,
+    #[account(mut, seeds = [b"royalty_vault".as_ref(), treasury.key().as_ref()], bump)]
+    pub royalty_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub holder_token: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = bidder_token.mint == offer.edition_mint,
+        constraint = bidder_token.owner == offer.bidder
+    )]
+    pub bidder_token: Account<'info, TokenAccount>,
+    /// Currency token account of the bidder, refunded anything escrowed beyond the offer.
+    /// Unused for native SOL.
+    #[account(mut)]
+    pub bidder_currency: AccountInfo<'info>,
+    /// Currency token account the holder is paid to, unused for native SOL.
+    #[account(mut)]
+    pub proceeds: AccountInfo<'info>,
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    /// `PlatformTreasury` in the offer currency, holding native SOL.
+    #[account(mut)]
+    pub platform_treasury: AccountInfo<'info>,
+    /// Vault of the platform treasury, unused for native SOL.
+    #[account(mut)]
+    pub platform_vault: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
 
-        msg!("Metadata created...");
+#[derive(Accounts)]
+#[instruction(bump: u8, vault_bump: u8)]
+pub struct CreateRoyaltyTreasury<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(address = utils::edition_address(&master_metadata.mint))]
+    pub master_edition: Account<'info, MasterEdition>,
+    /// Creator shares are copied from it into the treasury.
+    pub master_metadata: Account<'info, Metadata>,
+    pub currency_mint: Account<'info, token::Mint>,
+    #[account(
+        init,
+        seeds = [
+            b"royalty_treasury".as_ref(),
+            master_edition.key().as_ref(),
+            currency_mint.key().as_ref()
+        ],
+        bump = bump,
+        payer = payer,
+        space = RoyaltyTreasury::LEN
+    )]
+    pub treasury: Account<'info, RoyaltyTreasury>,
+    #[account(
+        init,
+        token::mint = currency_mint,
+        token::authority = treasury,
+        seeds = [b"royalty_vault".as_ref(), treasury.key().as_ref()],
+        bump = vault_bump,
+        payer = payer
+    )]
+    pub royalty_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
 
-        msg!("Creating master edition");
-        let instruction_create_master_edition = create_master_edition(
-            *ctx.accounts.metadata_program.key,
-            *ctx.accounts.master_edition_account.key,
-            *ctx.accounts.mint.key,
-            *ctx.accounts.update_authority.key,
-            *ctx.accounts.mint_authority.key,
-            *ctx.accounts.metadata_account.key,
-            *ctx.accounts.payer.key,
-            //Default to 10 additonal prints... will update to client provided
-            Some(1),
-        );
+#[derive(Accounts)]
+pub struct ClaimRoyalties<'info> {
+    pub creator: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"royalty_treasury".as_ref(),
+            treasury.master_edition.as_ref(),
+            treasury.currency_mint.as_ref()
+        ],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, RoyaltyTreasury>,
+    #[account(mut, seeds = [b"royalty_vault".as_ref(), treasury.key().as_ref()], bump)]
+    pub royalty_vault: Account<'info, TokenAccount>,
+    #[account(constraint = utils::edition_address(&master_metadata.mint) == treasury.master_edition)]
+    pub master_metadata: Account<'info, Metadata>,
+    #[account(mut, constraint = creator_token.mint == treasury.currency_mint)]
+    pub creator_token: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
 
-        msg!("Calling the metadata program to make masteredition...");
-        invoke(
-            &instruction_create_master_edition,
-            metadata_infos.as_slice(),
-        )?;
-        msg!("Metadata & editions created");
-        msg!("Transffering master edition token ownership to charmpda");
+#[derive(Accounts)]
+pub struct UpdateListing<'info> {
+    #[account(mut, has_one = authority)]
+    pub listing: Account<'info, Listing>,
+    pub authority: Signer<'info>,
+}
 
-        Ok(())
-    }
+#[derive(Accounts)]
+#[instruction(currency_mint: Pubkey)]
+pub struct SetListingPrice<'info> {
+    #[account(mut, has_one = authority)]
+    pub listing: Account<'info, Listing>,
+    pub authority: Signer<'info>,
+    //Proceeds in a currency are held in its treasury, which must exist before it is accepted
+    #[account(
+        seeds = [b"listing_treasury".as_ref(), listing.key().as_ref(), currency_mint.as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, ListingTreasury>,
+}
 
-    pub fn change_ownership(ctx: Context<ChangeOwnership>) -> ProgramResult {
-        msg!("Creating instruction to change master edition ownership to PDA");
-        let owner_change_ix = spl_token::instruction::set_authority(
-            &ctx.accounts.token_program.key,
-            &ctx.accounts.master_edition_account.key,
-            Some(&ctx.accounts.pda.key),
-            spl_token::instruction::AuthorityType::AccountOwner,
-            &ctx.accounts.signer.key,
-            &[&ctx.accounts.signer.key],
-        )?;
-        msg!("Calling the token program to transfer aster edition token account ownership...");
-        invoke(
-            &owner_change_ix,
-            &[
-                ctx.accounts.master_edition_account.clone(),
-                ctx.accounts.signer.clone(),
-                ctx.accounts.token_program.to_account_info().clone(),
-            ],
-        )?;
+#[derive(Accounts)]
+#[instruction(bump: u8, vault_bump: u8)]
+pub struct CreateListingTreasury<'info> {
+    #[account(has_one = authority)]
+    pub listing: Account<'info, Listing>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub currency_mint: Account<'info, token::Mint>,
+    #[account(
+        init,
+        seeds = [
+            b"listing_treasury".as_ref(),
+            listing.key().as_ref(),
+            currency_mint.key().as_ref()
+        ],
+        bump = bump,
+        payer = authority,
+        space = ListingTreasury::LEN
+    )]
+    pub treasury: Account<'info, ListingTreasury>,
+    //Only created for currencies other than native SOL
+    #[account(mut, seeds = [b"listing_vault".as_ref(), treasury.key().as_ref()], bump = vault_bump)]
+    pub vault: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
 
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct WithdrawProceeds<'info> {
+    pub beneficiary: Signer<'info>,
+    #[account(
+        mut,
+        has_one = beneficiary,
+        seeds = [
+            b"listing_treasury".as_ref(),
+            treasury.listing.as_ref(),
+            treasury.currency_mint.as_ref()
+        ],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, ListingTreasury>,
+    #[account(mut, seeds = [b"listing_vault".as_ref(), treasury.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+    /// Beneficiary wallet for native SOL, otherwise its currency token account.
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = user, space = 64 + 64)]
-    pub base_account: Account<'info, BaseAccount>,
+pub struct SetAdmins<'info> {
+    #[account(mut, seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    /// One of the admins, the others sign as remaining accounts.
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8, vault_bump: u8)]
+pub struct CreatePlatformTreasury<'info> {
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub payer: Signer<'info>,
+    pub currency_mint: Account<'info, token::Mint>,
+    #[account(
+        init,
+        seeds = [b"platform_treasury".as_ref(), currency_mint.key().as_ref()],
+        bump = bump,
+        payer = payer,
+        space = PlatformTreasury::LEN
+    )]
+    pub treasury: Account<'info, PlatformTreasury>,
+    //Only created for currencies other than native SOL
+    #[account(
+        mut,
+        seeds = [b"platform_vault".as_ref(), treasury.key().as_ref()],
+        bump = vault_bump
+    )]
+    pub vault: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawPlatformFees<'info> {
+    pub fee_wallet: Signer<'info>,
+    #[account(seeds = [b"config".as_ref()], bump = config.bump, has_one = fee_wallet)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"platform_treasury".as_ref(), treasury.currency_mint.as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, PlatformTreasury>,
+    #[account(mut, seeds = [b"platform_vault".as_ref(), treasury.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+    /// Fee wallet for native SOL, otherwise its currency token account.
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
@@ -369,28 +3035,36 @@ pub struct PuffMetadata<'info> {
     pub metadata_account: AccountInfo<'info>,
     pub mint: AccountInfo<'info>,
     #[account(executable)]
-    pub metadata_program: AccountInfo<'info>,
+    pub metadata_program: Program<'info, TokenMetadata>,
 }
 
 #[derive(Accounts)]
+#[instruction(edition: u64, pda_nonce: u8)]
 pub struct Buy<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
     #[account(executable)]
-    pub metadata_program: AccountInfo<'info>,
+    pub metadata_program: Program<'info, TokenMetadata>,
     #[account(mut)]
     pub new_metadata_account: AccountInfo<'info>,
     #[account(mut)]
     pub new_edition_account: AccountInfo<'info>,
-    #[account(mut)]
+    #[account(mut, address = utils::edition_address(metadata_mint.key))]
     pub master_edition_account: AccountInfo<'info>,
     #[account(mut)]
     pub new_mint_account: AccountInfo<'info>,
     pub new_mint_authority: AccountInfo<'info>,
+    //charmpda only signs for master edition tokens escrowed with it by a listed master
+    #[account(address = pda.key())]
     pub token_account_owner: AccountInfo<'info>,
-    pub token_account: AccountInfo<'info>,
+    #[account(
+        constraint = token_account.mint == metadata_mint.key(),
+        constraint = token_account.owner == pda.key()
+    )]
+    pub token_account: Account<'info, TokenAccount>,
     pub new_metadata_update_authority: AccountInfo<'info>,
-    pub metadata: AccountInfo<'info>,
+    #[account(constraint = metadata.mint == metadata_mint.key())]
+    pub metadata: Account<'info, Metadata>,
     pub metadata_mint: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
     pub rent_program: Sysvar<'info, Rent>,
@@ -398,6 +3072,189 @@ pub struct Buy<'info> {
     pub edition_pda: AccountInfo<'info>,
     #[account(executable)]
     pub token_program: Program<'info, Token>,
+    #[account(seeds = [b"listing".as_ref(), metadata_mint.key.as_ref()], bump = listing.bump)]
+    pub listing: Account<'info, Listing>,
+    //Holds the edition for the payer, or for its soulbound record when soulbound
+    #[account(
+        mut,
+        constraint = new_token_account.mint == new_mint_account.key(),
+        constraint = new_token_account.amount == 1
+    )]
+    pub new_token_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"charmpda".as_ref()], bump = pda_nonce)]
+    pub pda: AccountInfo<'info>,
+    //Created when the listing sells passes
+    #[account(mut, seeds = [b"expiry".as_ref(), new_mint_account.key.as_ref()], bump)]
+    pub expiry: AccountInfo<'info>,
+    //Created when the listing sells soulbound editions
+    #[account(mut, seeds = [b"soulbound".as_ref(), new_mint_account.key.as_ref()], bump)]
+    pub soulbound_edition: AccountInfo<'info>,
+    //The accounts below are only read when the listing has a price
+    #[account(seeds = [b"config".as_ref()], bump)]
+    pub config: AccountInfo<'info>,
+    /// Currency token account of the payer, unused for native SOL.
+    #[account(mut)]
+    pub payment_source: AccountInfo<'info>,
+    /// `ListingTreasury` of the listing in the currency paid, holding native SOL.
+    #[account(mut)]
+    pub listing_treasury: AccountInfo<'info>,
+    /// Vault of the listing treasury for currencies other than native SOL.
+    #[account(mut)]
+    pub listing_vault: AccountInfo<'info>,
+    /// `PlatformTreasury` in the currency paid, holding native SOL.
+    #[account(mut)]
+    pub platform_treasury: AccountInfo<'info>,
+    /// Vault of the platform treasury for currencies other than native SOL.
+    #[account(mut)]
+    pub platform_vault: AccountInfo<'info>,
+    /// `RoyaltyTreasury` of the master edition in the listing currency.
+    #[account(mut)]
+    pub royalty_treasury: AccountInfo<'info>,
+    #[account(mut)]
+    pub royalty_vault: AccountInfo<'info>,
+}
+
+impl<'info> Buy<'info> {
+    /// Wallet credited for a referral paid to `referrer_info`, which is the
+    /// referrer's currency token account when the edition is paid in SPL tokens.
+    fn referrer_of(
+        &self,
+        referrer_info: &AccountInfo<'info>,
+        payment: Option<ListingPrice>,
+    ) -> Result<Pubkey> {
+        let currency_mint = match payment {
+            Some(price) if !utils::is_native(&price.mint) => price.mint,
+            _ => return Ok(*referrer_info.key),
+        };
+        let referrer_token: Account<TokenAccount> = Account::try_from(referrer_info)?;
+        if referrer_token.mint != currency_mint {
+            return Err(ErrorCode::InvalidReferrer.into());
+        }
+        Ok(referrer_token.owner)
+    }
+
+    /// Splits the price paid between the royalty treasury, the platform fee, the
+    /// referrer and the listing treasury, then records the sale and the referral.
+    fn collect_price(
+        &self,
+        payment: Option<ListingPrice>,
+        referrer: Option<(Pubkey, &AccountInfo<'info>, &AccountInfo<'info>)>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let payer = self.payer.to_account_info();
+        let system_program = self.system_program.to_account_info();
+        let ListingPrice {
+            mint: currency_mint,
+            amount: price,
+        } = match payment {
+            Some(payment) if payment.amount > 0 => payment,
+            _ => {
+                if let Some((referrer, _, stats_info)) = referrer {
+                    utils::record_referral(
+                        stats_info,
+                        &referrer,
+                        &payer,
+                        &system_program,
+                        program_id,
+                        None,
+                    )?;
+                }
+                return Ok(());
+            }
+        };
+        let currency = &currency_mint;
+        let token_program = self.token_program.to_account_info();
+        let split = utils::SaleSplit {
+            listing: &self.listing,
+            config: &self.config,
+            platform_treasury: &self.platform_treasury,
+            platform_vault: Some(&self.platform_vault),
+            royalty_treasury: &self.royalty_treasury,
+            royalty_vault: &self.royalty_vault,
+            listing_treasury: &self.listing_treasury,
+            listing_vault: Some(&self.listing_vault),
+        };
+        let referral = split.pay(
+            currency,
+            price,
+            self.metadata.data.seller_fee_basis_points,
+            referrer.map(|(_, referrer_info, _)| referrer_info),
+            program_id,
+            |to, amount| {
+                utils::collect_payment(
+                    currency,
+                    &payer,
+                    &self.payment_source,
+                    to,
+                    &system_program,
+                    &token_program,
+                    amount,
+                )
+            },
+        )?;
+        if let Some((referrer, _, stats_info)) = referrer {
+            utils::record_referral(
+                stats_info,
+                &referrer,
+                &payer,
+                &system_program,
+                program_id,
+                Some((*currency, price, referral)),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateListing<'info> {
+    #[account(constraint = metadata.update_authority == authority.key())]
+    pub metadata: Account<'info, Metadata>,
+    #[account(address = utils::edition_address(&metadata.mint))]
+    pub master_edition: Account<'info, MasterEdition>,
+    #[account(
+        init,
+        seeds = [b"listing".as_ref(), metadata.mint.as_ref()],
+        bump = bump,
+        payer = authority,
+        space = Listing::LEN
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSoulbound<'info> {
+    #[account(has_one = authority)]
+    pub listing: Account<'info, Listing>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        close = holder,
+        has_one = holder,
+        constraint = soulbound_edition.master_mint == listing.master_mint,
+        seeds = [b"soulbound".as_ref(), soulbound_edition.mint.as_ref()],
+        bump = soulbound_edition.bump
+    )]
+    pub soulbound_edition: Account<'info, SoulboundEdition>,
+    #[account(mut)]
+    pub holder: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = custody.owner == soulbound_edition.key(),
+        constraint = custody.mint == soulbound_edition.mint
+    )]
+    pub custody: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = destination.owner == holder.key(),
+        constraint = destination.mint == soulbound_edition.mint
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
@@ -425,7 +3282,7 @@ pub struct CreateMetadata<'info> {
     #[account(mut)]
     pub master_edition_account: AccountInfo<'info>,
     #[account(executable)]
-    pub metadata_program: AccountInfo<'info>,
+    pub metadata_program: Program<'info, TokenMetadata>,
     #[account(executable)]
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
@@ -434,7 +3291,7 @@ pub struct CreateMetadata<'info> {
 
 #[derive(Accounts)]
 pub struct Edition<'info> {
-    #[account(signer)]
+    #[account(mut, signer)]
     pub payer: AccountInfo<'info>,
     #[account(mut)]
     pub mint: AccountInfo<'info>,
@@ -446,11 +3303,18 @@ pub struct Edition<'info> {
     #[account(mut)]
     pub master_edition_account: AccountInfo<'info>,
     #[account(executable)]
-    pub metadata_program: AccountInfo<'info>,
+    pub metadata_program: Program<'info, TokenMetadata>,
     #[account(executable)]
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
     pub rent_program: Sysvar<'info, Rent>,
+    //Created when content hashes are given
+    #[account(
+        mut,
+        seeds = [b"content_hash".as_ref(), master_edition_account.key.as_ref()],
+        bump
+    )]
+    pub content_hash: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
@@ -464,12 +3328,6 @@ pub struct ProxyTransfer<'info> {
     pub token_program: AccountInfo<'info>,
 }
 
-#[account]
-pub struct BaseAccount {
-    pub data: String,
-    pub data_list: Vec<String>,
-}
-
 impl<'a, 'b, 'c, 'info> From<&mut ProxyTransfer<'info>>
     for CpiContext<'a, 'b, 'c, 'info, Transfer<'info>>
 {
@@ -488,4 +3346,152 @@ impl<'a, 'b, 'c, 'info> From<&mut ProxyTransfer<'info>>
 pub enum ErrorCode {
     #[msg("Insufficient Balance")]
     InsufficientBalance,
+    #[msg("Name is too long")]
+    NameTooLong,
+    #[msg("Uri is too long")]
+    UriTooLong,
+    #[msg("Profile page is full, add a new page")]
+    ProfilePageFull,
+    #[msg("Last profile page still has room")]
+    ProfilePageNotFull,
+    #[msg("Royalty policy basis points must be at most 10000")]
+    InvalidRoyaltyPolicy,
+    #[msg("Seller fee is below the royalty policy of the collection")]
+    RoyaltyBelowPolicy,
+    #[msg("Membership account does not match the collection and mint")]
+    InvalidMembership,
+    #[msg("Collection authority must sign")]
+    CollectionAuthorityMismatch,
+    #[msg("Unexpected remaining accounts")]
+    InvalidRemainingAccounts,
+    #[msg("Too many attributes")]
+    TooManyAttributes,
+    #[msg("Attribute key or value is too long")]
+    AttributeTooLong,
+    #[msg("Account is not a master edition or edition")]
+    InvalidEdition,
+    #[msg("Attributes account does not belong to the master edition")]
+    InvalidAttributes,
+    #[msg("Metadata is immutable")]
+    MetadataImmutable,
+    #[msg("Bracelet has no room for another charm")]
+    BraceletFull,
+    #[msg("Charm is not attached to this bracelet")]
+    CharmNotAttached,
+    #[msg("Token account does not hold the edition")]
+    NotEditionHolder,
+    #[msg("Engraving is too long")]
+    EngravingTooLong,
+    #[msg("Engraving is locked")]
+    EngravingLocked,
+    #[msg("Edition token account must be owned by the buyer, or its soulbound record")]
+    InvalidEditionTokenAccount,
+    #[msg("Numerical overflow")]
+    NumericalOverflow,
+    #[msg("Redemption is already fulfilled")]
+    RedemptionFulfilled,
+    #[msg("Pass duration must be positive")]
+    InvalidPassTerms,
+    #[msg("Listing does not sell passes")]
+    NotAPass,
+    #[msg("Pass has expired")]
+    PassExpired,
+    #[msg("Token account is not for the edition mint")]
+    TokenMintMismatch,
+    #[msg("Token account does not hold exactly one edition token")]
+    EditionBalanceNotOne,
+    #[msg("Edition was not printed from the master edition")]
+    NotEditionOfMaster,
+    #[msg("Mint window must end after it starts")]
+    InvalidMintWindow,
+    #[msg("Editions can not be bought outside the mint window")]
+    OutsideMintWindow,
+    #[msg("Mint window has not ended yet")]
+    MintWindowOpen,
+    #[msg("Listing is closed")]
+    ListingClosed,
+    #[msg("Raffle must have at least one winner and a reveal deadline after its entries")]
+    InvalidRaffle,
+    #[msg("Raffle is not taking entries")]
+    RaffleNotOpen,
+    #[msg("Raffle has already been revealed")]
+    RaffleRevealed,
+    #[msg("Raffle has not been revealed yet")]
+    RaffleNotRevealed,
+    #[msg("Seed does not match the raffle commitment")]
+    InvalidRaffleSeed,
+    #[msg("Raffle entry is not for this raffle and buyer")]
+    InvalidRaffleEntry,
+    #[msg("Raffle entry did not win")]
+    NotRaffleWinner,
+    #[msg("Winning raffle entries are not refunded")]
+    RaffleWinner,
+    #[msg("Raffle entry already bought its edition")]
+    RaffleEntryClaimed,
+    #[msg("Raffle proceeds were already withdrawn")]
+    ProceedsWithdrawn,
+    #[msg("Bundle must hold between one and four distinct masters")]
+    InvalidBundle,
+    #[msg("Soulbound, pass and raffled listings can not be bundled")]
+    BundleListingUnsupported,
+    #[msg("Creator account does not match the metadata creators")]
+    CreatorMismatch,
+    #[msg("Fee can not exceed 10000 basis points")]
+    InvalidFee,
+    #[msg("Offer must expire in the future")]
+    InvalidOfferExpiry,
+    #[msg("Offer has expired")]
+    OfferExpired,
+    #[msg("Offer has not expired yet")]
+    OfferNotExpired,
+    #[msg("Royalty treasury is not for this charm and currency")]
+    InvalidRoyaltyTreasury,
+    #[msg("Signer is not a verified creator of the charm")]
+    NotVerifiedCreator,
+    #[msg("Referrer is not valid for this purchase")]
+    InvalidReferrer,
+    #[msg("Referrer stats track too many currencies")]
+    TooManyReferralCurrencies,
+    #[msg("Payment account does not match the currency or recipient")]
+    PaymentAccountMismatch,
+    #[msg("Listing does not accept this currency")]
+    CurrencyNotAccepted,
+    #[msg("Listing accepts too many currencies")]
+    TooManyListingCurrencies,
+    #[msg("Listing treasury is not for this listing and currency")]
+    InvalidListingTreasury,
+    #[msg("Treasury does not hold that many proceeds")]
+    InsufficientProceeds,
+    #[msg("Not enough admins signed")]
+    AdminQuorumNotReached,
+    #[msg("Admins must be distinct, at most seven, with a threshold between one and their count")]
+    InvalidAdminSet,
+    #[msg("Master edition token is not escrowed with the charm PDA")]
+    MasterTokenNotEscrowed,
+    #[msg("Signer is not the upgrade authority of the program")]
+    NotUpgradeAuthority,
+    #[msg("A media hash needs the hash of the metadata JSON")]
+    MissingMetadataHash,
+    #[msg("Open editions must be listed with a mint window")]
+    OpenEditionWithoutWindow,
+    #[msg("Raffle has more winners than editions left to print")]
+    RaffleExceedsSupply,
+    #[msg("Raffle entries are already closed")]
+    RaffleEntriesClosed,
+    #[msg("Raffle seed was not revealed before its deadline")]
+    RevealDeadlinePassed,
+    #[msg("Hash of the raffle draw slot is not in the SlotHashes sysvar")]
+    DrawSlotUnavailable,
+    #[msg("Bundle authority is not the authority of every listing")]
+    BundleAuthorityMismatch,
+    #[msg("Offer amount must be greater than zero")]
+    InvalidOfferAmount,
+    #[msg("Listing is not the one of the master edition")]
+    ListingMismatch,
+    #[msg("Platform treasury is not the one of the currency paid")]
+    InvalidPlatformTreasury,
+    #[msg("Raffle can still be revealed")]
+    RevealDeadlineNotPassed,
+    #[msg("Soulbound listings can not sell passes")]
+    SoulboundPass,
 }
@@ -0,0 +1,636 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::slot_hashes::MAX_ENTRIES;
+use spl_token_metadata::state::MAX_CREATOR_LIMIT;
+
+pub const MAX_NAME_LEN: usize = 32;
+pub const MAX_URI_LEN: usize = 200;
+/// Number of collections indexed by a single `ProfilePage`.
+pub const PROFILE_PAGE_CAPACITY: usize = 32;
+
+pub const MAX_ADMINS: usize = 7;
+
+/// Program wide settings, at seeds `[b"config"]`.
+#[account]
+pub struct Config {
+    /// Signer set allowed to change the config.
+    pub admins: Vec<Pubkey>,
+    /// How many distinct `admins` must sign an admin instruction.
+    pub admin_threshold: u8,
+    /// Faucet mint, controlled by the charm PDA, that staking rewards are minted from.
+    pub reward_mint: Pubkey,
+    /// Most reward tokens staking may ever mint.
+    pub emission_cap: u64,
+    pub total_emitted: u64,
+    /// Share of secondary sales kept by the platform, in basis points.
+    pub platform_fee_bps: u16,
+    /// Owner of the token accounts platform fees are paid to.
+    pub fee_wallet: Pubkey,
+    /// Share of the platform fee paid to referrers, in basis points.
+    pub referral_bps: u16,
+    pub bump: u8,
+}
+
+impl Config {
+    pub const LEN: usize = 8 + 4 + 32 * MAX_ADMINS + 1 + 32 + 8 + 8 + 2 + 32 + 2 + 1;
+
+    /// Distinct admins among `signers`.
+    pub fn admin_approvals<'a>(&self, signers: impl IntoIterator<Item = &'a Pubkey>) -> usize {
+        let mut approvals: Vec<&Pubkey> = Vec::new();
+        for signer in signers {
+            if self.admins.contains(signer) && !approvals.contains(&signer) {
+                approvals.push(signer);
+            }
+        }
+        approvals.len()
+    }
+}
+
+/// Public profile of a creator wallet, at seeds `[b"profile", authority]`.
+///
+/// The collections published by the creator are indexed in `ProfilePage`
+/// accounts, so the profile itself never grows.
+#[account]
+pub struct CreatorProfile {
+    pub authority: Pubkey,
+    pub display_name: String,
+    /// Points at the avatar and bio of the creator.
+    pub uri: String,
+    /// Set by the config admin only.
+    pub verified: bool,
+    /// Number of collections indexed over all pages.
+    pub collection_count: u32,
+    pub page_count: u32,
+    pub bump: u8,
+}
+
+impl CreatorProfile {
+    pub const LEN: usize = 8 + 32 + 4 + MAX_NAME_LEN + 4 + MAX_URI_LEN + 1 + 4 + 4 + 1;
+}
+
+/// A page of the collection index of a profile, at seeds
+/// `[b"profile_page", authority, page.to_le_bytes()]`.
+#[account]
+pub struct ProfilePage {
+    pub profile: Pubkey,
+    pub page: u32,
+    pub collections: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl ProfilePage {
+    pub const LEN: usize = 8 + 32 + 4 + 4 + 32 * PROFILE_PAGE_CAPACITY + 1;
+}
+
+/// Royalty every member of a collection must carry.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct RoyaltyPolicy {
+    pub seller_fee_basis_points: u16,
+    /// Reject members whose metadata pays less than `seller_fee_basis_points`.
+    pub enforce: bool,
+}
+
+impl RoyaltyPolicy {
+    pub const LEN: usize = 2 + 1;
+}
+
+/// Charms grouped by a creator, at seeds `[b"collection", profile, index.to_le_bytes()]`
+/// where `index` is the collection count of the profile when it was created.
+#[account]
+pub struct Collection {
+    pub authority: Pubkey,
+    pub profile: Pubkey,
+    pub name: String,
+    pub uri: String,
+    pub royalty_policy: RoyaltyPolicy,
+    pub member_count: u32,
+    /// Reward tokens, in base units, earned per second by each staked edition. Set by the
+    /// config admins since every collection draws on the same emission cap.
+    pub reward_rate: u64,
+    pub bump: u8,
+}
+
+impl Collection {
+    pub const LEN: usize =
+        8 + 32 + 32 + 4 + MAX_NAME_LEN + 4 + MAX_URI_LEN + RoyaltyPolicy::LEN + 4 + 8 + 1;
+}
+
+/// Verified membership of a master mint in a collection, at seeds
+/// `[b"membership", collection, mint]`. Only the collection authority creates it.
+#[account]
+pub struct Membership {
+    pub collection: Pubkey,
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+impl Membership {
+    pub const LEN: usize = 8 + 32 + 32 + 1;
+}
+
+pub const MAX_ATTRIBUTES: usize = 16;
+pub const MAX_ATTRIBUTE_KEY_LEN: usize = 32;
+pub const MAX_ATTRIBUTE_VALUE_LEN: usize = 64;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum AttributeValue {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct Attribute {
+    pub key: String,
+    pub value: AttributeValue,
+}
+
+impl Attribute {
+    /// Largest serialized attribute, a string value being the widest variant.
+    pub const LEN: usize = 4 + MAX_ATTRIBUTE_KEY_LEN + 1 + 4 + MAX_ATTRIBUTE_VALUE_LEN;
+}
+
+/// Traits of a charm, at seeds `[b"attributes", master_edition]`.
+///
+/// Keyed by the master edition account so editions find the attributes they
+/// inherit through `Edition.parent`.
+#[account]
+pub struct CharmAttributes {
+    pub master_edition: Pubkey,
+    pub attributes: Vec<Attribute>,
+    pub bump: u8,
+}
+
+impl CharmAttributes {
+    pub const LEN: usize = 8 + 32 + 4 + MAX_ATTRIBUTES * Attribute::LEN + 1;
+
+    pub fn get(&self, key: &str) -> Option<&AttributeValue> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .map(|attribute| &attribute.value)
+    }
+}
+
+/// SHA-256 of the off-chain content of a charm, at seeds `[b"content_hash", master_edition]`
+/// so editions are checked against the hashes of their master edition.
+#[account]
+pub struct ContentHash {
+    pub master_edition: Pubkey,
+    /// Hash of the metadata JSON that `Data.uri` points at.
+    pub metadata_hash: [u8; 32],
+    /// Hash of the primary media referenced by the metadata JSON.
+    pub media_hash: Option<[u8; 32]>,
+    pub bump: u8,
+}
+
+impl ContentHash {
+    pub const LEN: usize = 8 + 32 + 32 + 1 + 32 + 1;
+}
+
+pub const MAX_ATTACHED_CHARMS: usize = 12;
+
+/// Charms attached to a parent NFT, at seeds `[b"bracelet", parent_mint]`.
+///
+/// The bracelet owns the escrow token account of every attached charm, at seeds
+/// `[b"escrow", bracelet, charm_mint]`, and whoever holds the parent token controls them.
+#[account]
+pub struct Bracelet {
+    pub parent_mint: Pubkey,
+    /// Mints of the attached charms, in the order they were attached.
+    pub charms: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl Bracelet {
+    pub const LEN: usize = 8 + 32 + 4 + 32 * MAX_ATTACHED_CHARMS + 1;
+}
+
+pub const MAX_ENGRAVING_LEN: usize = 128;
+
+/// Message written by the holder of an edition, at seeds `[b"engraving", edition_mint]`.
+#[account]
+pub struct Engraving {
+    pub mint: Pubkey,
+    pub message: String,
+    /// Holder that wrote the current message.
+    pub engraved_by: Pubkey,
+    /// A locked engraving can never be rewritten.
+    pub locked: bool,
+    pub bump: u8,
+}
+
+impl Engraving {
+    pub const LEN: usize = 8 + 32 + 4 + MAX_ENGRAVING_LEN + 32 + 1 + 1;
+}
+
+/// Terms editions of a master mint are sold under, at seeds `[b"listing", master_mint]`.
+#[account]
+pub struct Listing {
+    pub master_mint: Pubkey,
+    /// Update authority of the master metadata when the listing was created.
+    pub authority: Pubkey,
+    /// Editions are held in custody of a `SoulboundEdition` for the buyer.
+    pub soulbound: bool,
+    /// Editions are passes that expire, see `Expiry`.
+    pub pass: Option<PassTerms>,
+    /// Editions can only be bought inside this window. Open editions, whose
+    /// master edition has no max supply, are capped by it.
+    pub window: Option<MintWindow>,
+    /// Set by `close_open_edition`, no edition can be bought anymore.
+    pub closed: bool,
+    /// Only winners of the listing's `Raffle` can buy editions.
+    pub raffled: bool,
+    /// Overrides `Config.referral_bps` for sales of this listing.
+    pub referral_bps: Option<u16>,
+    /// Currencies editions can be bought in, each at its own price. Editions are
+    /// free when empty.
+    pub prices: Vec<ListingPrice>,
+    pub bump: u8,
+}
+
+impl Listing {
+    pub const LEN: usize = 8
+        + 32
+        + 32
+        + 1
+        + 1
+        + PassTerms::LEN
+        + 1
+        + MintWindow::LEN
+        + 1
+        + 1
+        + 1
+        + 3
+        + 4
+        + ListingPrice::LEN * MAX_LISTING_CURRENCIES;
+
+    /// Price of an edition in `mint`, when the listing accepts it.
+    pub fn price_in(&self, mint: &Pubkey) -> Option<ListingPrice> {
+        self.prices
+            .iter()
+            .find(|price| price.mint == *mint)
+            .copied()
+    }
+
+    /// Share of the platform fee paid to the referrer of a sale of this listing.
+    pub fn referral_bps(&self, config: &Config) -> u16 {
+        self.referral_bps.unwrap_or(config.referral_bps)
+    }
+}
+
+pub const MAX_LISTING_CURRENCIES: usize = 4;
+
+/// Price of an edition in one currency, the native mint for SOL.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ListingPrice {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+impl ListingPrice {
+    pub const LEN: usize = 32 + 8;
+}
+
+/// Unix timestamps editions of a listing can be bought between, `end` excluded.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct MintWindow {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl MintWindow {
+    pub const LEN: usize = 8 + 8;
+
+    pub fn contains(&self, now: i64) -> bool {
+        self.start <= now && now < self.end
+    }
+}
+
+/// Validity of the editions of a pass listing.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PassTerms {
+    /// Seconds a pass is valid for after it is bought or renewed.
+    pub duration: i64,
+    /// Lamports paid to the listing authority for each renewal.
+    pub renewal_price: u64,
+}
+
+impl PassTerms {
+    pub const LEN: usize = 8 + 8;
+}
+
+/// When the pass held as an edition stops being valid, at seeds `[b"expiry", edition_mint]`.
+#[account]
+pub struct Expiry {
+    pub mint: Pubkey,
+    pub master_mint: Pubkey,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl Expiry {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+/// A soulbound edition held in custody for its holder, at seeds `[b"soulbound", edition_mint]`.
+/// The record owns the token account holding the edition until the listing revokes it.
+#[account]
+pub struct SoulboundEdition {
+    pub mint: Pubkey,
+    pub master_mint: Pubkey,
+    pub holder: Pubkey,
+    pub bump: u8,
+}
+
+impl SoulboundEdition {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 1;
+}
+
+/// A staked edition, at seeds `[b"stake", edition_mint]`.
+///
+/// The edition token sits in a vault token account at seeds
+/// `[b"stake_vault", edition_mint]` owned by this record.
+#[account]
+pub struct StakeRecord {
+    pub owner: Pubkey,
+    pub edition_mint: Pubkey,
+    /// Collection of the master edition, whose rate the rewards accrue at.
+    pub collection: Pubkey,
+    pub staked_at: i64,
+    /// Rewards are paid up to this timestamp.
+    pub claimed_until: i64,
+    pub bump: u8,
+}
+
+impl StakeRecord {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 8 + 1;
+}
+
+/// A burned edition waiting for its physical or digital item, at seeds
+/// `[b"redemption", edition_mint]`.
+#[account]
+pub struct Redemption {
+    pub edition_mint: Pubkey,
+    pub master_mint: Pubkey,
+    pub redeemer: Pubkey,
+    /// Update authority of the master metadata, who fulfils the redemption.
+    pub authority: Pubkey,
+    pub redeemed_at: i64,
+    /// Hash of the shipping details, encrypted off-chain for the creator.
+    pub shipping_hash: [u8; 32],
+    pub fulfilled: bool,
+    pub bump: u8,
+}
+
+impl Redemption {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 8 + 32 + 1 + 1;
+}
+
+/// Commit-reveal raffle of the editions of a listing, at seeds `[b"raffle", master_mint]`.
+///
+/// Entrants escrow `price` lamports in this account. Once entries close anyone fixes
+/// `draw_slot`, and the authority reveals the seed it committed to, which is mixed with
+/// the hash of that slot into `randomness`. Winners then buy through `buy`, losers claim
+/// a refund. Every entry is refunded when the seed is not revealed by `reveal_deadline`.
+#[account]
+pub struct Raffle {
+    pub master_mint: Pubkey,
+    pub authority: Pubkey,
+    pub price: u64,
+    pub entry_window: MintWindow,
+    pub winner_count: u32,
+    pub entry_count: u32,
+    /// SHA-256 of the seed the authority reveals.
+    pub seed_commitment: [u8; 32],
+    /// Unix timestamp the seed must be revealed before.
+    pub reveal_deadline: i64,
+    /// Slot whose hash is drawn from, fixed when entries close so the authority can not
+    /// pick it.
+    pub draw_slot: Option<u64>,
+    pub randomness: Option<[u8; 32]>,
+    pub proceeds_withdrawn: bool,
+    pub bump: u8,
+}
+
+impl Raffle {
+    pub const LEN: usize =
+        8 + 32 + 32 + 8 + MintWindow::LEN + 4 + 4 + 32 + 8 + 1 + 8 + 1 + 32 + 1 + 1;
+
+    /// Number of entries that win, every entry wins an undersubscribed raffle.
+    pub fn winners(&self) -> u32 {
+        self.winner_count.min(self.entry_count)
+    }
+
+    /// Whether the entry at `index` won, `None` until the raffle is revealed.
+    ///
+    /// Winners are the `winner_count` entries following an offset drawn from
+    /// `randomness`, wrapping around the entry order.
+    pub fn is_winner(&self, index: u32) -> Option<bool> {
+        let randomness = self.randomness?;
+        if self.entry_count == 0 {
+            return Some(false);
+        }
+        let mut offset = [0u8; 8];
+        offset.copy_from_slice(&randomness[..8]);
+        let entries = self.entry_count as u64;
+        let offset = u64::from_le_bytes(offset) % entries;
+        let position = (index as u64 + entries - offset) % entries;
+        Some(position < self.winners() as u64)
+    }
+
+    /// Whether the hash of the draw slot may be gone from the SlotHashes sysvar at `slot`,
+    /// which only keeps the hashes of the last `MAX_ENTRIES` slots.
+    pub fn draw_slot_expired(&self, slot: u64) -> bool {
+        match self.draw_slot {
+            Some(draw_slot) => slot.saturating_sub(draw_slot) > MAX_ENTRIES as u64,
+            None => false,
+        }
+    }
+
+    /// Whether the entry at `index` can be refunded at `now`, because it lost or because
+    /// the raffle was not revealed before its deadline.
+    pub fn is_refundable(&self, index: u32, now: i64) -> bool {
+        match self.is_winner(index) {
+            Some(won) => !won,
+            None => now >= self.reveal_deadline,
+        }
+    }
+}
+
+/// An entry in a raffle, at seeds `[b"raffle_entry", raffle, entrant]`.
+#[account]
+pub struct RaffleEntry {
+    pub raffle: Pubkey,
+    pub entrant: Pubkey,
+    /// Position in the entry order.
+    pub index: u32,
+    /// The winner bought its edition.
+    pub claimed: bool,
+    pub bump: u8,
+}
+
+impl RaffleEntry {
+    pub const LEN: usize = 8 + 32 + 32 + 4 + 1 + 1;
+}
+
+pub const MAX_BUNDLE_MASTERS: usize = 4;
+
+/// Master editions sold together at one price, at seeds
+/// `[b"bundle", authority, id.to_le_bytes()]`.
+#[account]
+pub struct Bundle {
+    /// Authority of the listing of every master in the bundle.
+    pub authority: Pubkey,
+    pub id: u64,
+    /// Master mints, `buy_bundle` prints one edition of each.
+    pub masters: Vec<Pubkey>,
+    /// Lamports for the whole bundle, split between the masters in proportion to
+    /// their native SOL listing prices.
+    pub price: u64,
+    pub bump: u8,
+}
+
+impl Bundle {
+    pub const LEN: usize = 8 + 32 + 8 + 4 + 32 * MAX_BUNDLE_MASTERS + 8 + 1;
+}
+
+/// Bid on an edition by `bidder`, at seeds `[b"offer", edition_mint, bidder]`.
+///
+/// The offered tokens sit in an escrow token account at seeds `[b"offer_escrow", offer]`
+/// owned by this account until the offer is accepted, cancelled or expires.
+#[account]
+pub struct Offer {
+    pub bidder: Pubkey,
+    pub edition_mint: Pubkey,
+    pub currency_mint: Pubkey,
+    pub amount: u64,
+    pub expires_at: i64,
+    /// Wallet the bidder credits for the offer, paid a referral when it is accepted.
+    pub referrer: Option<Pubkey>,
+    pub bump: u8,
+}
+
+impl Offer {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 8 + 1 + 32 + 1;
+}
+
+/// Royalties of a charm paid in one currency, at seeds
+/// `[b"royalty_treasury", master_edition, currency_mint]`.
+///
+/// Royalties accumulate in a vault token account at seeds `[b"royalty_vault", treasury]`
+/// owned by this account. Creator shares of the master metadata are copied into `claims`
+/// when the treasury is created, each creator claims its share of `total_received`
+/// whenever it chooses once verified.
+#[account]
+pub struct RoyaltyTreasury {
+    pub master_edition: Pubkey,
+    pub currency_mint: Pubkey,
+    /// Royalties ever paid in, in the smallest unit of the currency.
+    pub total_received: u64,
+    pub claims: Vec<CreatorClaim>,
+    pub bump: u8,
+}
+
+impl RoyaltyTreasury {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 4 + CreatorClaim::LEN * MAX_CREATOR_LIMIT + 1;
+
+    pub fn claimed_by(&self, creator: &Pubkey) -> u64 {
+        self.claims
+            .iter()
+            .find(|claim| &claim.creator == creator)
+            .map_or(0, |claim| claim.claimed)
+    }
+}
+
+/// Share of a creator in a treasury, and the royalties it has already withdrawn.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct CreatorClaim {
+    pub creator: Pubkey,
+    /// Percentage of the royalties, as set in the metadata when the treasury was created.
+    pub share: u8,
+    pub claimed: u64,
+}
+
+impl CreatorClaim {
+    pub const LEN: usize = 32 + 1 + 8;
+}
+
+pub const MAX_REFERRAL_CURRENCIES: usize = 8;
+
+/// Referral totals of an affiliate, at seeds `[b"referrer_stats", referrer]`.
+#[account]
+pub struct ReferrerStats {
+    pub referrer: Pubkey,
+    /// Purchases made through the referrer.
+    pub referrals: u64,
+    /// Paid volume and referral earnings, per currency.
+    pub currencies: Vec<ReferralVolume>,
+    pub bump: u8,
+}
+
+impl ReferrerStats {
+    pub const LEN: usize = 8 + 32 + 8 + 4 + ReferralVolume::LEN * MAX_REFERRAL_CURRENCIES + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct ReferralVolume {
+    pub mint: Pubkey,
+    pub volume: u64,
+    pub earnings: u64,
+}
+
+impl ReferralVolume {
+    pub const LEN: usize = 32 + 8 + 8;
+}
+
+/// Proceeds of the sales of a listing in one currency, at seeds
+/// `[b"listing_treasury", listing, currency_mint]`. Native SOL is held as lamports of
+/// the treasury itself, other currencies in its vault at `[b"listing_vault", treasury]`.
+#[account]
+pub struct ListingTreasury {
+    pub listing: Pubkey,
+    pub currency_mint: Pubkey,
+    /// Only signer allowed to withdraw the proceeds.
+    pub beneficiary: Pubkey,
+    /// Total paid by buyers, before fees and royalties.
+    pub gross_sales: u64,
+    /// Platform fees taken from the sales, referrals included.
+    pub fees: u64,
+    /// Royalties paid into the royalty treasury of the master edition.
+    pub royalties: u64,
+    pub withdrawn: u64,
+    pub bump: u8,
+}
+
+impl ListingTreasury {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1;
+
+    /// Proceeds held for the beneficiary.
+    pub fn available(&self) -> u64 {
+        self.gross_sales
+            .saturating_sub(self.fees)
+            .saturating_sub(self.royalties)
+            .saturating_sub(self.withdrawn)
+    }
+}
+
+/// Platform fees in one currency, at seeds `[b"platform_treasury", currency_mint]`,
+/// withdrawn by the config fee wallet. Native SOL is held as lamports of the treasury
+/// itself, other currencies in its vault at `[b"platform_vault", treasury]`.
+#[account]
+pub struct PlatformTreasury {
+    pub currency_mint: Pubkey,
+    /// Platform fees paid in, the referrals excluded.
+    pub fees: u64,
+    pub withdrawn: u64,
+    pub bump: u8,
+}
+
+impl PlatformTreasury {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 1;
+
+    /// Fees held for the fee wallet.
+    pub fn available(&self) -> u64 {
+        self.fees.saturating_sub(self.withdrawn)
+    }
+}
@@ -0,0 +1,410 @@
+//! CPI helpers for the token metadata program, in the style of `anchor_spl::token`.
+//!
+//! Each instruction gets an `Accounts` struct naming the accounts it needs, so the
+//! order expected by the metadata program is encoded once, here.
+
+use anchor_lang::solana_program;
+use anchor_lang::solana_program::account_info::AccountInfo;
+use anchor_lang::solana_program::borsh::try_from_slice_unchecked;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::{Accounts, AnchorDeserialize, CpiContext};
+use spl_token_metadata::error::MetadataError;
+use spl_token_metadata::state::{Creator, Data, Key};
+use std::io::Write;
+use std::ops::Deref;
+
+pub use spl_token_metadata::ID;
+
+pub fn create_metadata_accounts<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, CreateMetadataAccounts<'info>>,
+    data: Data,
+    update_authority_is_signer: bool,
+    is_mutable: bool,
+) -> ProgramResult {
+    let ix = spl_token_metadata::instruction::create_metadata_accounts(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.mint.key,
+        *ctx.accounts.mint_authority.key,
+        *ctx.accounts.payer.key,
+        *ctx.accounts.update_authority.key,
+        data.name,
+        data.symbol,
+        data.uri,
+        data.creators,
+        data.seller_fee_basis_points,
+        update_authority_is_signer,
+        is_mutable,
+    );
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.accounts.metadata.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.mint_authority.clone(),
+            ctx.accounts.payer.clone(),
+            ctx.accounts.update_authority.clone(),
+            ctx.accounts.system_program.clone(),
+            ctx.accounts.rent.clone(),
+            ctx.program.clone(),
+        ],
+        ctx.signer_seeds,
+    )
+}
+
+pub fn create_master_edition<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, CreateMasterEdition<'info>>,
+    max_supply: Option<u64>,
+) -> ProgramResult {
+    let ix = spl_token_metadata::instruction::create_master_edition(
+        ID,
+        *ctx.accounts.edition.key,
+        *ctx.accounts.mint.key,
+        *ctx.accounts.update_authority.key,
+        *ctx.accounts.mint_authority.key,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.payer.key,
+        max_supply,
+    );
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.accounts.edition.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.update_authority.clone(),
+            ctx.accounts.mint_authority.clone(),
+            ctx.accounts.payer.clone(),
+            ctx.accounts.metadata.clone(),
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.system_program.clone(),
+            ctx.accounts.rent.clone(),
+            ctx.program.clone(),
+        ],
+        ctx.signer_seeds,
+    )
+}
+
+pub fn mint_new_edition_from_master_edition_via_token<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, MintNewEditionFromMasterEditionViaToken<'info>>,
+    edition: u64,
+) -> ProgramResult {
+    let ix = spl_token_metadata::instruction::mint_new_edition_from_master_edition_via_token(
+        ID,
+        *ctx.accounts.new_metadata.key,
+        *ctx.accounts.new_edition.key,
+        *ctx.accounts.master_edition.key,
+        *ctx.accounts.new_mint.key,
+        *ctx.accounts.new_mint_authority.key,
+        *ctx.accounts.payer.key,
+        *ctx.accounts.token_account_owner.key,
+        *ctx.accounts.token_account.key,
+        *ctx.accounts.new_metadata_update_authority.key,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.metadata_mint.key,
+        edition,
+    );
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.accounts.new_metadata.clone(),
+            ctx.accounts.new_edition.clone(),
+            ctx.accounts.master_edition.clone(),
+            ctx.accounts.new_mint.clone(),
+            ctx.accounts.edition_mark_pda.clone(),
+            ctx.accounts.new_mint_authority.clone(),
+            ctx.accounts.payer.clone(),
+            ctx.accounts.token_account_owner.clone(),
+            ctx.accounts.token_account.clone(),
+            ctx.accounts.new_metadata_update_authority.clone(),
+            ctx.accounts.metadata.clone(),
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.system_program.clone(),
+            ctx.accounts.rent.clone(),
+            ctx.program.clone(),
+        ],
+        ctx.signer_seeds,
+    )
+}
+
+pub fn update_metadata_accounts<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, UpdateMetadataAccounts<'info>>,
+    new_update_authority: Option<Pubkey>,
+    data: Option<Data>,
+    primary_sale_happened: Option<bool>,
+) -> ProgramResult {
+    let ix = spl_token_metadata::instruction::update_metadata_accounts(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.update_authority.key,
+        new_update_authority,
+        data,
+        primary_sale_happened,
+    );
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.accounts.metadata.clone(),
+            ctx.accounts.update_authority.clone(),
+            ctx.program.clone(),
+        ],
+        ctx.signer_seeds,
+    )
+}
+
+pub fn sign_metadata<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SignMetadata<'info>>,
+) -> ProgramResult {
+    let ix = spl_token_metadata::instruction::sign_metadata(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.creator.key,
+    );
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.accounts.metadata.clone(),
+            ctx.accounts.creator.clone(),
+            ctx.program.clone(),
+        ],
+        ctx.signer_seeds,
+    )
+}
+
+pub fn puff_metadata_account<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, PuffMetadataAccount<'info>>,
+) -> ProgramResult {
+    let ix = spl_token_metadata::instruction::puff_metadata_account(ID, *ctx.accounts.metadata.key);
+    solana_program::program::invoke_signed(
+        &ix,
+        &[ctx.accounts.metadata.clone(), ctx.program.clone()],
+        ctx.signer_seeds,
+    )
+}
+
+pub fn update_primary_sale_happened_via_token<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, UpdatePrimarySaleHappenedViaToken<'info>>,
+) -> ProgramResult {
+    let ix = spl_token_metadata::instruction::update_primary_sale_happened_via_token(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.owner.key,
+        *ctx.accounts.token.key,
+    );
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            ctx.accounts.metadata.clone(),
+            ctx.accounts.owner.clone(),
+            ctx.accounts.token.clone(),
+            ctx.program.clone(),
+        ],
+        ctx.signer_seeds,
+    )
+}
+
+#[derive(Accounts)]
+pub struct CreateMetadataAccounts<'info> {
+    pub metadata: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub mint_authority: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub update_authority: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateMasterEdition<'info> {
+    pub edition: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub update_authority: AccountInfo<'info>,
+    pub mint_authority: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub metadata: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MintNewEditionFromMasterEditionViaToken<'info> {
+    pub new_metadata: AccountInfo<'info>,
+    pub new_edition: AccountInfo<'info>,
+    pub master_edition: AccountInfo<'info>,
+    pub new_mint: AccountInfo<'info>,
+    /// Edition marker PDA for `edition / EDITION_MARKER_BIT_SIZE` of the master mint.
+    pub edition_mark_pda: AccountInfo<'info>,
+    pub new_mint_authority: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub token_account_owner: AccountInfo<'info>,
+    pub token_account: AccountInfo<'info>,
+    pub new_metadata_update_authority: AccountInfo<'info>,
+    pub metadata: AccountInfo<'info>,
+    /// Only its key is used, to derive the edition marker.
+    pub metadata_mint: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMetadataAccounts<'info> {
+    pub metadata: AccountInfo<'info>,
+    pub update_authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SignMetadata<'info> {
+    pub metadata: AccountInfo<'info>,
+    pub creator: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PuffMetadataAccount<'info> {
+    pub metadata: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePrimarySaleHappenedViaToken<'info> {
+    pub metadata: AccountInfo<'info>,
+    pub owner: AccountInfo<'info>,
+    pub token: AccountInfo<'info>,
+}
+
+/// Deserializes `buf` as `T` after checking the leading `Key` byte.
+fn try_deserialize_keyed<T: AnchorDeserialize>(buf: &[u8], key: Key) -> Result<T, ProgramError> {
+    if buf.first() != Some(&(key as u8)) {
+        return Err(MetadataError::DataTypeMismatch.into());
+    }
+    try_from_slice_unchecked(buf).map_err(|err| ProgramError::BorshIoError(err.to_string()))
+}
+
+#[derive(Clone)]
+pub struct Metadata(spl_token_metadata::state::Metadata);
+
+impl Metadata {
+    /// Creators of the metadata, empty when none were set.
+    pub fn creators(&self) -> &[Creator] {
+        self.0.data.creators.as_deref().unwrap_or_default()
+    }
+}
+
+impl anchor_lang::AccountDeserialize for Metadata {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self, ProgramError> {
+        Metadata::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self, ProgramError> {
+        try_deserialize_keyed(buf, Key::MetadataV1).map(Metadata)
+    }
+}
+
+impl anchor_lang::AccountSerialize for Metadata {
+    fn try_serialize<W: Write>(&self, _writer: &mut W) -> Result<(), ProgramError> {
+        // no-op
+        Ok(())
+    }
+}
+
+impl anchor_lang::Owner for Metadata {
+    fn owner() -> Pubkey {
+        ID
+    }
+}
+
+impl Deref for Metadata {
+    type Target = spl_token_metadata::state::Metadata;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Clone)]
+pub struct MasterEdition(spl_token_metadata::state::MasterEditionV2);
+
+impl anchor_lang::AccountDeserialize for MasterEdition {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self, ProgramError> {
+        MasterEdition::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self, ProgramError> {
+        try_deserialize_keyed(buf, Key::MasterEditionV2).map(MasterEdition)
+    }
+}
+
+impl anchor_lang::AccountSerialize for MasterEdition {
+    fn try_serialize<W: Write>(&self, _writer: &mut W) -> Result<(), ProgramError> {
+        // no-op
+        Ok(())
+    }
+}
+
+impl anchor_lang::Owner for MasterEdition {
+    fn owner() -> Pubkey {
+        ID
+    }
+}
+
+impl Deref for MasterEdition {
+    type Target = spl_token_metadata::state::MasterEditionV2;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Clone)]
+pub struct Edition(spl_token_metadata::state::Edition);
+
+impl anchor_lang::AccountDeserialize for Edition {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self, ProgramError> {
+        Edition::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self, ProgramError> {
+        try_deserialize_keyed(buf, Key::EditionV1).map(Edition)
+    }
+}
+
+impl anchor_lang::AccountSerialize for Edition {
+    fn try_serialize<W: Write>(&self, _writer: &mut W) -> Result<(), ProgramError> {
+        // no-op
+        Ok(())
+    }
+}
+
+impl anchor_lang::Owner for Edition {
+    fn owner() -> Pubkey {
+        ID
+    }
+}
+
+impl Deref for Edition {
+    type Target = spl_token_metadata::state::Edition;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Clone)]
+pub struct TokenMetadata;
+
+impl anchor_lang::AccountDeserialize for TokenMetadata {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self, ProgramError> {
+        TokenMetadata::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(_buf: &mut &[u8]) -> Result<Self, ProgramError> {
+        Ok(TokenMetadata)
+    }
+}
+
+impl anchor_lang::Id for TokenMetadata {
+    fn id() -> Pubkey {
+        ID
+    }
+}
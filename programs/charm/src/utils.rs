@@ -0,0 +1,806 @@
+use crate::state::{
+    Attribute, AttributeValue, CharmAttributes, Collection, Config, Expiry, Listing,
+    ListingTreasury, Membership, PlatformTreasury, ReferralVolume, ReferrerStats, RoyaltyTreasury,
+    SoulboundEdition, StakeRecord, MAX_ATTRIBUTES, MAX_ATTRIBUTE_KEY_LEN, MAX_ATTRIBUTE_VALUE_LEN,
+    MAX_NAME_LEN, MAX_REFERRAL_CURRENCIES, MAX_URI_LEN,
+};
+use crate::token_metadata::{self, Metadata};
+use crate::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    borsh::try_from_slice_unchecked,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    hash::hashv,
+    program::{invoke, invoke_signed},
+    system_instruction,
+};
+use anchor_spl::token::{self, MintTo, TokenAccount, Transfer};
+use spl_token_metadata::state::{Edition, Key as MetadataKey, EDITION, PREFIX};
+
+pub fn assert_name_and_uri(name: &str, uri: &str) -> ProgramResult {
+    if name.len() > MAX_NAME_LEN {
+        return Err(ErrorCode::NameTooLong.into());
+    }
+    if uri.len() > MAX_URI_LEN {
+        return Err(ErrorCode::UriTooLong.into());
+    }
+    Ok(())
+}
+
+/// Creates a rent exempt account at a PDA of this program.
+///
+/// Anyone can send lamports to the PDA before it is created, which would make
+/// `create_account` fail, so a funded PDA is topped up, allocated and assigned instead.
+pub fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    new_account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    program_id: &Pubkey,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let lamports = Rent::get()?.minimum_balance(space);
+    let balance = new_account.lamports();
+    if balance == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                new_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[payer.clone(), new_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        );
+    }
+    pay_lamports(
+        payer,
+        new_account,
+        system_program,
+        lamports.saturating_sub(balance),
+    )?;
+    let accounts = &[new_account.clone(), system_program.clone()];
+    invoke_signed(
+        &system_instruction::allocate(new_account.key, space as u64),
+        accounts,
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(new_account.key, program_id),
+        accounts,
+        &[signer_seeds],
+    )
+}
+
+/// Checks `metadata` satisfies the royalty policy of `collection`.
+pub fn assert_royalty_policy(collection: &Collection, metadata: &Metadata) -> ProgramResult {
+    let policy = &collection.royalty_policy;
+    if policy.enforce && metadata.data.seller_fee_basis_points < policy.seller_fee_basis_points {
+        return Err(ErrorCode::RoyaltyBelowPolicy.into());
+    }
+    Ok(())
+}
+
+/// Registers the mint of `metadata` in `collection` by creating its membership account.
+/// The caller checks the collection authority signed.
+pub fn register_member<'info>(
+    collection: &mut Account<'info, Collection>,
+    membership: &AccountInfo<'info>,
+    metadata: &Metadata,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    assert_royalty_policy(collection, metadata)?;
+
+    let collection_key = collection.key();
+    let seeds = &[
+        b"membership".as_ref(),
+        collection_key.as_ref(),
+        metadata.mint.as_ref(),
+    ];
+    let (address, bump) = Pubkey::find_program_address(seeds, program_id);
+    if membership.key != &address {
+        return Err(ErrorCode::InvalidMembership.into());
+    }
+    create_pda_account(
+        payer,
+        membership,
+        system_program,
+        Membership::LEN,
+        program_id,
+        &[seeds[0], seeds[1], seeds[2], &[bump]],
+    )?;
+    let record = Membership {
+        collection: collection_key,
+        mint: metadata.mint,
+        bump,
+    };
+    record.try_serialize(&mut &mut membership.try_borrow_mut_data()?[..])?;
+
+    collection.member_count += 1;
+    collection.exit(program_id)
+}
+
+/// Address of the master edition or edition account of `mint`.
+pub fn edition_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            token_metadata::ID.as_ref(),
+            mint.as_ref(),
+            EDITION.as_bytes(),
+        ],
+        &token_metadata::ID,
+    )
+    .0
+}
+
+pub fn attributes_address(master_edition: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"attributes", master_edition.as_ref()], &crate::ID)
+}
+
+pub fn assert_attributes(attributes: &[Attribute]) -> ProgramResult {
+    if attributes.len() > MAX_ATTRIBUTES {
+        return Err(ErrorCode::TooManyAttributes.into());
+    }
+    for attribute in attributes {
+        let value_too_long = matches!(
+            &attribute.value,
+            AttributeValue::String(value) if value.len() > MAX_ATTRIBUTE_VALUE_LEN
+        );
+        if attribute.key.len() > MAX_ATTRIBUTE_KEY_LEN || value_too_long {
+            return Err(ErrorCode::AttributeTooLong.into());
+        }
+    }
+    Ok(())
+}
+
+/// Address of the master edition `edition` was printed from, or of `edition` itself
+/// when it is a master edition.
+pub fn master_edition_of(edition: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    if edition.owner != &token_metadata::ID {
+        return Err(ErrorCode::InvalidEdition.into());
+    }
+    let data = edition.try_borrow_data()?;
+    match data.first() {
+        Some(key) if *key == MetadataKey::MasterEditionV2 as u8 => Ok(*edition.key),
+        Some(key) if *key == MetadataKey::EditionV1 as u8 => {
+            Ok(try_from_slice_unchecked::<Edition>(&data)
+                .map_err(|_| ErrorCode::InvalidEdition)?
+                .parent)
+        }
+        _ => Err(ErrorCode::InvalidEdition.into()),
+    }
+}
+
+/// Reads the attributes of a charm without a CPI, so other programs can use them.
+///
+/// `edition` is the master edition of the charm or an edition printed from it, in
+/// which case the attributes of its master edition are returned.
+pub fn read_attributes(
+    edition: &AccountInfo,
+    attributes: &AccountInfo,
+) -> Result<CharmAttributes, ProgramError> {
+    let master_edition = master_edition_of(edition)?;
+    if attributes.key != &attributes_address(&master_edition).0 || attributes.owner != &crate::ID {
+        return Err(ErrorCode::InvalidAttributes.into());
+    }
+    let record = CharmAttributes::try_deserialize(&mut &attributes.try_borrow_data()?[..])?;
+    Ok(record)
+}
+
+/// Checks `token` holds the single token of `edition_mint` on behalf of `holder`.
+pub fn assert_edition_holder(
+    holder: &Pubkey,
+    token: &TokenAccount,
+    edition_mint: &Pubkey,
+) -> ProgramResult {
+    if &token.owner != holder {
+        return Err(ErrorCode::NotEditionHolder.into());
+    }
+    if &token.mint != edition_mint {
+        return Err(ErrorCode::TokenMintMismatch.into());
+    }
+    if token.amount != 1 {
+        return Err(ErrorCode::EditionBalanceNotOne.into());
+    }
+    Ok(())
+}
+
+/// Like `assert_edition_holder`, but a soulbound edition is held by `holder` when
+/// `soulbound` is its `SoulboundEdition` record and owns `token`.
+pub fn assert_edition_held_by(
+    holder: &Pubkey,
+    token: &TokenAccount,
+    edition_mint: &Pubkey,
+    soulbound: Option<&AccountInfo>,
+) -> ProgramResult {
+    let soulbound = match soulbound {
+        Some(soulbound) => soulbound,
+        None => return assert_edition_holder(holder, token, edition_mint),
+    };
+    if soulbound.owner != &crate::ID {
+        return Err(ErrorCode::NotEditionHolder.into());
+    }
+    let record = SoulboundEdition::try_deserialize(&mut &soulbound.try_borrow_data()?[..])?;
+    let address = Pubkey::create_program_address(
+        &[b"soulbound", record.mint.as_ref(), &[record.bump]],
+        &crate::ID,
+    )?;
+    if soulbound.key != &address || &record.holder != holder || &record.mint != edition_mint {
+        return Err(ErrorCode::NotEditionHolder.into());
+    }
+    assert_edition_holder(soulbound.key, token, edition_mint)
+}
+
+/// Checks `wallet` holds, in `token`, an edition printed from `master_edition`.
+///
+/// `metadata` and `edition` are the accounts of the edition mint, `edition_key` being
+/// the address `edition` was loaded from. `soulbound` is the `SoulboundEdition` record
+/// of a soulbound edition, whose token is held in custody for `wallet`. Programs
+/// depending on this crate call it directly, others CPI into the `prove_ownership`
+/// instruction.
+pub fn assert_holds_edition_of(
+    wallet: &Pubkey,
+    token: &TokenAccount,
+    metadata: &spl_token_metadata::state::Metadata,
+    edition_key: &Pubkey,
+    edition: &Edition,
+    master_edition: &Pubkey,
+    soulbound: Option<&AccountInfo>,
+) -> ProgramResult {
+    assert_edition_held_by(wallet, token, &metadata.mint, soulbound)?;
+    if edition_key != &edition_address(&metadata.mint) {
+        return Err(ErrorCode::InvalidEdition.into());
+    }
+    if &edition.parent != master_edition {
+        return Err(ErrorCode::NotEditionOfMaster.into());
+    }
+    Ok(())
+}
+
+/// Rewards `stake` earned up to `now` at `rate` per second, limited to what is left
+/// under the emission cap of `config`.
+pub fn accrued_rewards(
+    stake: &StakeRecord,
+    rate: u64,
+    config: &Config,
+    now: i64,
+) -> Result<u64, ProgramError> {
+    let seconds = now.saturating_sub(stake.claimed_until).max(0) as u64;
+    let earned = seconds
+        .checked_mul(rate)
+        .ok_or(ErrorCode::NumericalOverflow)?;
+    let remaining = config.emission_cap.saturating_sub(config.total_emitted);
+    Ok(earned.min(remaining))
+}
+
+/// Mints `amount` reward tokens to `destination`, signed by the charm PDA, and counts
+/// them against the emission cap.
+pub fn mint_rewards<'info>(
+    config: &mut Config,
+    amount: u64,
+    reward_mint: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    pda: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    pda_nonce: u8,
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+    config.total_emitted = config
+        .total_emitted
+        .checked_add(amount)
+        .ok_or(ErrorCode::NumericalOverflow)?;
+    msg!("Minting {} reward tokens", amount);
+    let seeds = &[&b"charmpda"[..], &[pda_nonce]];
+    token::mint_to(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            MintTo {
+                mint: reward_mint.clone(),
+                to: destination.clone(),
+                authority: pda.clone(),
+            },
+            &[&seeds[..]],
+        ),
+        amount,
+    )
+}
+
+/// Fails unless `holder` holds the edition of `expiry` in `token_account` and the
+/// pass has not expired at `now`.
+///
+/// Programs gating a feature on a pass call this with accounts they have checked, or
+/// CPI into the `assert_pass_valid` instruction.
+pub fn assert_pass_valid(
+    holder: &Pubkey,
+    token_account: &TokenAccount,
+    expiry: &Expiry,
+    now: i64,
+) -> ProgramResult {
+    assert_edition_holder(holder, token_account, &expiry.mint)?;
+    if now >= expiry.expires_at {
+        return Err(ErrorCode::PassExpired.into());
+    }
+    Ok(())
+}
+
+/// Hash of `slot` in the `SlotHashes` sysvar, `None` when it is not produced yet or
+/// already dropped out of the sysvar.
+///
+/// Entries are scanned in place, deserializing the whole sysvar does not fit in the
+/// compute budget.
+pub fn slot_hash_at(
+    slot_hashes: &AccountInfo,
+    slot: u64,
+) -> Result<Option<[u8; 32]>, ProgramError> {
+    let data = slot_hashes.try_borrow_data()?;
+    //u64 entry count, then (slot, hash) entries newest first
+    if data.len() < 8 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut count = [0u8; 8];
+    count.copy_from_slice(&data[..8]);
+    let entries = data[8..]
+        .chunks_exact(8 + 32)
+        .take(u64::from_le_bytes(count) as usize);
+    for entry in entries {
+        let mut entry_slot = [0u8; 8];
+        entry_slot.copy_from_slice(&entry[..8]);
+        if u64::from_le_bytes(entry_slot) == slot {
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(&entry[8..]);
+            return Ok(Some(hash));
+        }
+    }
+    Ok(None)
+}
+
+/// Randomness of a raffle from the revealed seed and a slot hash.
+pub fn draw_randomness(seed: &[u8; 32], slot_hash: &[u8; 32]) -> [u8; 32] {
+    hashv(&[seed, slot_hash]).to_bytes()
+}
+
+/// Moves lamports out of an account owned by this program.
+pub fn transfer_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> ProgramResult {
+    let remaining = from
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(ErrorCode::InsufficientBalance)?;
+    **from.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? = to
+        .lamports()
+        .checked_add(amount)
+        .ok_or(ErrorCode::NumericalOverflow)?;
+    Ok(())
+}
+
+/// Part of a bundle `price` paid for the master at `index`, `weights` being the native
+/// SOL listing price of each master.
+///
+/// The price is split in proportion to the weights, evenly when they are all zero, and
+/// the last master takes the rounding remainder.
+pub fn bundle_share(price: u64, weights: &[u64], index: usize) -> u64 {
+    let total: u128 = weights.iter().map(|weight| *weight as u128).sum();
+    let share_of = |index: usize| {
+        let weighted = price as u128 * weights[index] as u128;
+        match weighted.checked_div(total) {
+            Some(share) => share as u64,
+            None => price / weights.len() as u64,
+        }
+    };
+    if index == weights.len() - 1 {
+        price - (0..index).map(share_of).sum::<u64>()
+    } else {
+        share_of(index)
+    }
+}
+
+/// Pays `amount` lamports from a system account that signed the transaction.
+pub fn pay_lamports<'info>(
+    payer: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    amount: u64,
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+    invoke(
+        &system_instruction::transfer(payer.key, to.key, amount),
+        &[payer.clone(), to.clone(), system_program.clone()],
+    )
+}
+
+/// `bps` basis points of `amount`, rounded down.
+pub fn basis_points_of(amount: u64, bps: u16) -> u64 {
+    (amount as u128 * bps as u128 / 10_000) as u64
+}
+
+/// Share of `treasury` that `creator` can still claim, `data` being the current master
+/// metadata it must be verified in.
+///
+/// Shares come from the treasury, so updating the metadata can not entitle creators to
+/// more than was paid in.
+pub fn claimable_royalties(
+    treasury: &RoyaltyTreasury,
+    data: &spl_token_metadata::state::Data,
+    creator: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let verified = data
+        .creators
+        .iter()
+        .flatten()
+        .any(|candidate| &candidate.address == creator && candidate.verified);
+    let claim = treasury
+        .claims
+        .iter()
+        .find(|claim| &claim.creator == creator)
+        .filter(|_| verified)
+        .ok_or(ErrorCode::NotVerifiedCreator)?;
+    let entitled = (treasury.total_received as u128 * claim.share as u128 / 100) as u64;
+    Ok(entitled.saturating_sub(claim.claimed))
+}
+
+/// Token transfer out of an account owned by a PDA of this program.
+pub fn transfer_signed<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[u8]],
+    amount: u64,
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            Transfer {
+                from: from.clone(),
+                to: to.clone(),
+                authority: authority.clone(),
+            },
+            &[signer_seeds],
+        ),
+        amount,
+    )
+}
+
+/// Listings and offers in the native mint are paid in SOL.
+pub fn is_native(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::id()
+}
+
+/// Pays `amount` of `currency_mint` from `payer`, who signed the transaction.
+///
+/// Native SOL moves as lamports with a system transfer. When `to` is a wrapped
+/// SOL token account, such as a treasury vault or an offer escrow, the lamports
+/// are wrapped into it with `sync_native`. Any other currency is transferred
+/// from `source`, a token account of `payer`.
+pub fn collect_payment<'info>(
+    currency_mint: &Pubkey,
+    payer: &AccountInfo<'info>,
+    source: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    amount: u64,
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+    if !is_native(currency_mint) {
+        return token::transfer(
+            CpiContext::new(
+                token_program.clone(),
+                Transfer {
+                    from: source.clone(),
+                    to: to.clone(),
+                    authority: payer.clone(),
+                },
+            ),
+            amount,
+        );
+    }
+    pay_lamports(payer, to, system_program, amount)?;
+    sync_wrapped_sol(to, token_program)
+}
+
+/// Updates the token amount of `account` after lamports were moved into it,
+/// when it is a wrapped SOL token account rather than a system account.
+pub fn sync_wrapped_sol<'info>(
+    account: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> ProgramResult {
+    if account.owner == &spl_token::id() {
+        invoke(
+            &spl_token::instruction::sync_native(token_program.key, account.key)?,
+            &[account.clone(), token_program.clone()],
+        )?;
+    }
+    Ok(())
+}
+
+/// Checks an account a payment in `currency_mint` is sent to.
+///
+/// Native SOL is paid straight to the `owner` wallet, any other currency to a
+/// token account of that mint, owned by `owner` when given.
+pub fn assert_payment_destination(
+    currency_mint: &Pubkey,
+    destination: &AccountInfo,
+    owner: Option<&Pubkey>,
+) -> ProgramResult {
+    let recipient = if is_native(currency_mint) {
+        *destination.key
+    } else {
+        let token_account: Account<TokenAccount> = Account::try_from(destination)?;
+        if token_account.mint != *currency_mint {
+            return Err(ErrorCode::PaymentAccountMismatch.into());
+        }
+        token_account.owner
+    };
+    let valid = match owner {
+        Some(owner) => recipient == *owner,
+        None => true,
+    };
+    if !valid {
+        return Err(ErrorCode::PaymentAccountMismatch.into());
+    }
+    Ok(())
+}
+
+/// Accounts the price of an edition of `listing` is split between.
+pub struct SaleSplit<'a, 'info> {
+    pub listing: &'a Account<'info, Listing>,
+    pub config: &'a AccountInfo<'info>,
+    /// `PlatformTreasury` in the currency paid, holding native SOL.
+    pub platform_treasury: &'a AccountInfo<'info>,
+    /// Vault of the platform treasury, only needed for currencies other than native SOL.
+    pub platform_vault: Option<&'a AccountInfo<'info>>,
+    /// `RoyaltyTreasury` of the listed master edition in the currency paid.
+    pub royalty_treasury: &'a AccountInfo<'info>,
+    pub royalty_vault: &'a AccountInfo<'info>,
+    /// `ListingTreasury` of the listing in the currency paid, holding native SOL.
+    pub listing_treasury: &'a AccountInfo<'info>,
+    /// Vault of the listing treasury, only needed for currencies other than native SOL.
+    pub listing_vault: Option<&'a AccountInfo<'info>>,
+}
+
+impl<'a, 'info> SaleSplit<'a, 'info> {
+    /// Splits `price` between the royalty treasury, the platform fee, the
+    /// referrer paid at `referrer_info` and the listing treasury, moving each part
+    /// with `pay`, and records the sale in the listing treasury.
+    ///
+    /// Returns the referral paid.
+    pub fn pay(
+        &self,
+        currency: &Pubkey,
+        price: u64,
+        seller_fee_basis_points: u16,
+        referrer_info: Option<&AccountInfo<'info>>,
+        program_id: &Pubkey,
+        pay: impl Fn(&AccountInfo<'info>, u64) -> ProgramResult,
+    ) -> Result<u64, ProgramError> {
+        let listing = self.listing;
+        let config: Account<Config> = Account::try_from(self.config)?;
+
+        //Royalties are held for the creators to claim
+        let royalty = basis_points_of(price, seller_fee_basis_points);
+        if royalty > 0 {
+            let mut treasury: Account<RoyaltyTreasury> = Account::try_from(self.royalty_treasury)?;
+            let (vault, _) = Pubkey::find_program_address(
+                &[b"royalty_vault", treasury.key().as_ref()],
+                program_id,
+            );
+            if treasury.master_edition != edition_address(&listing.master_mint)
+                || treasury.currency_mint != *currency
+                || vault != *self.royalty_vault.key
+            {
+                return Err(ErrorCode::InvalidRoyaltyTreasury.into());
+            }
+            pay(self.royalty_vault, royalty)?;
+            treasury.total_received = treasury
+                .total_received
+                .checked_add(royalty)
+                .ok_or(ErrorCode::NumericalOverflow)?;
+            treasury.exit(program_id)?;
+        }
+
+        let fee = basis_points_of(price, config.platform_fee_bps);
+        let referral = match referrer_info {
+            None => 0,
+            Some(referrer_info) => {
+                let referral = basis_points_of(fee, listing.referral_bps(&config));
+                pay(referrer_info, referral)?;
+                referral
+            }
+        };
+        let fee_destination = record_platform_fee(
+            self.platform_treasury,
+            self.platform_vault,
+            currency,
+            fee - referral,
+            program_id,
+        )?;
+        pay(fee_destination, fee - referral)?;
+
+        let proceeds = price
+            .checked_sub(royalty)
+            .and_then(|rest| rest.checked_sub(fee))
+            .ok_or(ErrorCode::NumericalOverflow)?;
+        let mut treasury: Account<ListingTreasury> = Account::try_from(self.listing_treasury)?;
+        if treasury.listing != listing.key() || treasury.currency_mint != *currency {
+            return Err(ErrorCode::InvalidListingTreasury.into());
+        }
+        let destination = if is_native(currency) {
+            self.listing_treasury
+        } else {
+            let (vault, _) = Pubkey::find_program_address(
+                &[b"listing_vault", treasury.key().as_ref()],
+                program_id,
+            );
+            match self.listing_vault {
+                Some(listing_vault) if *listing_vault.key == vault => listing_vault,
+                _ => return Err(ErrorCode::InvalidListingTreasury.into()),
+            }
+        };
+        msg!("Holding {} for the beneficiary", proceeds);
+        pay(destination, proceeds)?;
+        treasury.gross_sales = treasury
+            .gross_sales
+            .checked_add(price)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+        treasury.fees = treasury
+            .fees
+            .checked_add(fee)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+        treasury.royalties = treasury
+            .royalties
+            .checked_add(royalty)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+        treasury.exit(program_id)?;
+        Ok(referral)
+    }
+}
+
+/// Adds `fee` paid in `currency_mint` to the platform treasury and returns the
+/// account it is paid to: the treasury itself for native SOL, otherwise its vault.
+pub fn record_platform_fee<'a, 'info>(
+    platform_treasury: &'a AccountInfo<'info>,
+    platform_vault: Option<&'a AccountInfo<'info>>,
+    currency_mint: &Pubkey,
+    fee: u64,
+    program_id: &Pubkey,
+) -> Result<&'a AccountInfo<'info>, ProgramError> {
+    let mut treasury: Account<PlatformTreasury> = Account::try_from(platform_treasury)?;
+    if treasury.currency_mint != *currency_mint {
+        return Err(ErrorCode::InvalidPlatformTreasury.into());
+    }
+    let destination = if is_native(currency_mint) {
+        platform_treasury
+    } else {
+        let (vault, _) = Pubkey::find_program_address(
+            &[b"platform_vault", platform_treasury.key.as_ref()],
+            program_id,
+        );
+        match platform_vault {
+            Some(platform_vault) if *platform_vault.key == vault => platform_vault,
+            _ => return Err(ErrorCode::InvalidPlatformTreasury.into()),
+        }
+    };
+    treasury.fees = treasury
+        .fees
+        .checked_add(fee)
+        .ok_or(ErrorCode::NumericalOverflow)?;
+    treasury.exit(program_id)?;
+    Ok(destination)
+}
+
+/// Checks `authority` is the upgrade authority recorded in `program_data`, the
+/// ProgramData account of this program.
+pub fn assert_upgrade_authority(
+    program_data: &AccountInfo,
+    authority: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (address, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    if *program_data.key != address {
+        return Err(ErrorCode::NotUpgradeAuthority.into());
+    }
+    match bincode::deserialize(&program_data.try_borrow_data()?) {
+        Ok(UpgradeableLoaderState::ProgramData {
+            upgrade_authority_address: Some(upgrade_authority),
+            ..
+        }) if upgrade_authority == *authority => Ok(()),
+        _ => Err(ErrorCode::NotUpgradeAuthority.into()),
+    }
+}
+
+/// Checks `admin_threshold` distinct admins of `config` signed, counting `admin` and
+/// the co-signers passed as remaining accounts.
+pub fn assert_admin_quorum(
+    config: &Config,
+    admin: &AccountInfo,
+    cosigners: &[AccountInfo],
+) -> ProgramResult {
+    let cosigners = cosigners
+        .iter()
+        .filter(|cosigner| cosigner.is_signer)
+        .map(|cosigner| cosigner.key);
+    let signers = std::iter::once(admin.key).chain(cosigners);
+    if config.admin_approvals(signers) < config.admin_threshold as usize {
+        return Err(ErrorCode::AdminQuorumNotReached.into());
+    }
+    Ok(())
+}
+
+pub fn referrer_stats_address(referrer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"referrer_stats", referrer.as_ref()], &crate::ID)
+}
+
+/// Counts a purchase through `referrer` in its stats, created on its first referral.
+///
+/// `payment` is the currency mint, volume and referral earnings of a paid purchase. It
+/// is left out of the stats once they track `MAX_REFERRAL_CURRENCIES` other currencies,
+/// the sale still goes through.
+pub fn record_referral<'info>(
+    stats_info: &AccountInfo<'info>,
+    referrer: &Pubkey,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+    payment: Option<(Pubkey, u64, u64)>,
+) -> ProgramResult {
+    let (address, bump) = referrer_stats_address(referrer);
+    if stats_info.key != &address {
+        return Err(ErrorCode::InvalidReferrer.into());
+    }
+    if stats_info.data_is_empty() {
+        create_pda_account(
+            payer,
+            stats_info,
+            system_program,
+            ReferrerStats::LEN,
+            program_id,
+            &[b"referrer_stats", referrer.as_ref(), &[bump]],
+        )?;
+        ReferrerStats {
+            referrer: *referrer,
+            referrals: 0,
+            currencies: Vec::new(),
+            bump,
+        }
+        .try_serialize(&mut &mut stats_info.try_borrow_mut_data()?[..])?;
+    }
+
+    let mut stats: Account<ReferrerStats> = Account::try_from(stats_info)?;
+    stats.referrals += 1;
+    if let Some((mint, volume, earnings)) = payment {
+        match stats.currencies.iter().position(|entry| entry.mint == mint) {
+            Some(index) => {
+                let entry = &mut stats.currencies[index];
+                entry.volume = entry.volume.saturating_add(volume);
+                entry.earnings = entry.earnings.saturating_add(earnings);
+            }
+            None if stats.currencies.len() < MAX_REFERRAL_CURRENCIES => {
+                stats.currencies.push(ReferralVolume {
+                    mint,
+                    volume,
+                    earnings,
+                })
+            }
+            None => msg!(
+                "Referrer stats track too many currencies, skipping {}",
+                mint
+            ),
+        }
+    }
+    stats.exit(program_id)
+}
@@ -0,0 +1,365 @@
+// The program is registered through its entrypoint, run with `cargo test -p charm`.
+#![cfg(not(feature = "no-entrypoint"))]
+mod utils;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize};
+use charm::{
+    state::{Bundle, Listing, ListingPrice, ListingTreasury, PlatformTreasury},
+    ErrorCode,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    signature::Signer,
+    signer::keypair::Keypair,
+    system_program, sysvar,
+};
+use spl_token_metadata::state::Creator;
+use utils::*;
+
+const PRICE: u64 = 1_000_000_001;
+const SELLER_FEE_BPS: u16 = 500;
+const PLATFORM_FEE_BPS: u16 = 250;
+const LISTING_PRICES: [u64; 3] = [1_000_000_000, 2_000_000_000, 5_000_000_000];
+
+fn listing_address(master_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"listing", master_mint.as_ref()], &charm::id()).0
+}
+
+fn bundle_address(authority: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"bundle", authority.as_ref(), &id.to_le_bytes()],
+        &charm::id(),
+    )
+}
+
+fn create_bundle(authority: &Pubkey, id: u64, listings: &[Pubkey]) -> Instruction {
+    let (bundle, bump) = bundle_address(authority, id);
+    let mut instruction = charm_instruction(
+        charm::accounts::CreateBundle {
+            bundle,
+            authority: *authority,
+            system_program: system_program::id(),
+        },
+        charm::instruction::CreateBundle {
+            bump,
+            id,
+            price: PRICE,
+        },
+    );
+    instruction.accounts.extend(
+        listings
+            .iter()
+            .map(|listing| AccountMeta::new_readonly(*listing, false)),
+    );
+    instruction
+}
+
+/// Listing of `master_mint` selling editions for `price` lamports.
+fn priced_listing(master_mint: Pubkey, authority: Pubkey, price: u64) -> Listing {
+    Listing {
+        prices: vec![ListingPrice {
+            mint: spl_token::native_mint::id(),
+            amount: price,
+        }],
+        ..listing(master_mint, authority)
+    }
+}
+
+/// Sets up a master of `authority` for each of `prices`, listed at that price with
+/// its native SOL treasuries and split 60/40 between `authority` and `cocreator`.
+fn add_masters(
+    program_test: &mut ProgramTest,
+    authority: &Keypair,
+    cocreator: &Pubkey,
+    prices: &[u64],
+) -> Vec<(Master, NativeTreasuries)> {
+    add_metadata_program(program_test);
+    add_wallet(program_test, &authority.pubkey());
+    add_wallet(program_test, cocreator);
+    prices
+        .iter()
+        .map(|price| {
+            let master = Master::new();
+            let master_mint = master.mint.pubkey();
+            let listing_key = listing_address(&master_mint);
+            add_charm_account(
+                program_test,
+                listing_key,
+                &priced_listing(master_mint, authority.pubkey(), *price),
+                Listing::LEN,
+                10_000_000,
+            );
+            let treasuries =
+                add_native_treasuries(program_test, listing_key, &master_mint, authority.pubkey());
+            (master, treasuries)
+        })
+        .collect()
+}
+
+fn creators(authority: &Keypair, cocreator: &Pubkey) -> Vec<Creator> {
+    vec![
+        Creator {
+            address: authority.pubkey(),
+            verified: true,
+            share: 60,
+        },
+        Creator {
+            address: *cocreator,
+            verified: false,
+            share: 40,
+        },
+    ]
+}
+
+// Natively loaded programs can not create accounts, bundles need the BPF build.
+#[cfg_attr(not(feature = "test-bpf"), ignore)]
+#[tokio::test]
+async fn creates_bundles_of_the_authority_listings_only() {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    let other = Keypair::new();
+    add_wallet(&mut program_test, &authority.pubkey());
+    add_wallet(&mut program_test, &other.pubkey());
+    let mine = listing_address(&Pubkey::new_unique());
+    let theirs = listing_address(&Pubkey::new_unique());
+    for (listing_key, owner) in [(mine, &authority), (theirs, &other)].iter() {
+        let master_mint = Pubkey::new_unique();
+        add_charm_account(
+            &mut program_test,
+            *listing_key,
+            &listing(master_mint, owner.pubkey()),
+            Listing::LEN,
+            10_000_000,
+        );
+    }
+    let mut context = program_test.start_with_context().await;
+
+    let error = process(
+        &mut context,
+        create_bundle(&authority.pubkey(), 1, &[mine, theirs]),
+        &[&authority],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::BundleAuthorityMismatch);
+
+    let error = process(
+        &mut context,
+        create_bundle(&authority.pubkey(), 2, &[mine, mine]),
+        &[&authority],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::InvalidBundle);
+
+    process(
+        &mut context,
+        create_bundle(&authority.pubkey(), 3, &[mine]),
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let account = get_account(&mut context, &bundle_address(&authority.pubkey(), 3).0)
+        .await
+        .unwrap();
+    let bundle = Bundle::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!(bundle.authority, authority.pubkey());
+    assert_eq!(bundle.price, PRICE);
+    assert_eq!(bundle.masters.len(), 1);
+}
+
+#[cfg_attr(not(feature = "test-bpf"), ignore)]
+#[tokio::test]
+async fn buys_an_edition_of_each_master_and_splits_the_price() {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    let cocreator = Pubkey::new_unique();
+    add_config(&mut program_test, Pubkey::new_unique(), PLATFORM_FEE_BPS, 0);
+    let (platform_treasury, _) =
+        add_platform_treasury(&mut program_test, spl_token::native_mint::id());
+    let masters = add_masters(&mut program_test, &authority, &cocreator, &LISTING_PRICES);
+    let mut context = program_test.start_with_context().await;
+    for (master, _) in masters.iter() {
+        master
+            .create(
+                &mut context,
+                &authority,
+                creators(&authority, &cocreator),
+                SELLER_FEE_BPS,
+                Some(10),
+            )
+            .await
+            .unwrap();
+    }
+    let listings: Vec<Pubkey> = masters
+        .iter()
+        .map(|(master, _)| listing_address(&master.mint.pubkey()))
+        .collect();
+    process(
+        &mut context,
+        create_bundle(&authority.pubkey(), 1, &listings),
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    let buyer = Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let mut remaining_accounts = vec![];
+    let mut edition_mints = vec![];
+    for ((master, treasuries), listing) in masters.iter().zip(listings) {
+        let edition_mint = Keypair::new();
+        let edition_token = Keypair::new();
+        create_nft(
+            &mut context,
+            &edition_mint,
+            &buyer,
+            &edition_token,
+            &buyer.pubkey(),
+        )
+        .await
+        .unwrap();
+        let master_mint = master.mint.pubkey();
+        remaining_accounts.extend(vec![
+            AccountMeta::new(metadata_address(&edition_mint.pubkey()), false),
+            AccountMeta::new(charm::utils::edition_address(&edition_mint.pubkey()), false),
+            AccountMeta::new(master.edition, false),
+            AccountMeta::new(edition_mint.pubkey(), false),
+            AccountMeta::new(edition_marker_address(&master_mint, 1), false),
+            AccountMeta::new_readonly(master.token.pubkey(), false),
+            AccountMeta::new(authority.pubkey(), false),
+            AccountMeta::new_readonly(master.metadata, false),
+            AccountMeta::new_readonly(master_mint, false),
+            AccountMeta::new_readonly(listing, false),
+            AccountMeta::new(treasuries.royalty_treasury, false),
+            AccountMeta::new(treasuries.royalty_vault, false),
+            AccountMeta::new(treasuries.listing_treasury, false),
+        ]);
+        edition_mints.push(edition_mint.pubkey());
+    }
+    let (pda, pda_nonce) = charm_pda();
+    let mut buy = charm_instruction(
+        charm::accounts::BuyBundle {
+            payer: buyer.pubkey(),
+            bundle: bundle_address(&authority.pubkey(), 1).0,
+            pda,
+            config: Pubkey::find_program_address(&[b"config"], &charm::id()).0,
+            platform_treasury,
+            metadata_program: spl_token_metadata::id(),
+            token_program: spl_token::id(),
+            system_program: system_program::id(),
+            rent: sysvar::rent::id(),
+        },
+        charm::instruction::BuyBundle {
+            editions: vec![1; 3],
+            pda_nonce,
+        },
+    );
+    buy.accounts.extend(remaining_accounts);
+
+    process(&mut context, buy, &[]).await.unwrap();
+
+    for edition_mint in edition_mints.iter() {
+        let edition = charm::utils::edition_address(edition_mint);
+        assert!(get_account(&mut context, &edition).await.is_some());
+    }
+    // Each master sells for a share of the price in proportion to its listing price,
+    // the last one takes the rounding remainder, and each share is split like the sale
+    // of a single edition.
+    let shares: Vec<u64> = (0..masters.len())
+        .map(|index| charm::utils::bundle_share(PRICE, &LISTING_PRICES, index))
+        .collect();
+    assert_eq!(shares, vec![125_000_000, 250_000_000, 625_000_001]);
+    let mut fees = 0;
+    for ((_, treasuries), share) in masters.iter().zip(shares) {
+        let royalty = charm::utils::basis_points_of(share, SELLER_FEE_BPS);
+        let fee = charm::utils::basis_points_of(share, PLATFORM_FEE_BPS);
+        let vault_balance = get_token_balance(&mut context, &treasuries.royalty_vault).await;
+        assert_eq!(vault_balance, royalty);
+        let account = get_account(&mut context, &treasuries.listing_treasury)
+            .await
+            .unwrap();
+        let treasury = ListingTreasury::try_deserialize(&mut &account.data[..]).unwrap();
+        assert_eq!(treasury.gross_sales, share);
+        assert_eq!(treasury.royalties, royalty);
+        assert_eq!(treasury.fees, fee);
+        assert_eq!(account.lamports, 10_000_000 + share - royalty - fee);
+        fees += fee;
+    }
+    let account = get_account(&mut context, &platform_treasury).await.unwrap();
+    assert_eq!(account.lamports, 10_000_000 + fees);
+    let platform = PlatformTreasury::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!(platform.fees, fees);
+}
+
+// The metadata program only loads in the BPF build.
+#[cfg_attr(not(feature = "test-bpf"), ignore)]
+#[tokio::test]
+async fn masters_without_a_sol_price_are_not_bundled() {
+    let mut program_test = program_test();
+    let authority = Pubkey::new_unique();
+    add_metadata_program(&mut program_test);
+    let masters = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let (bundle, bump) = bundle_address(&authority, 1);
+    add_charm_account(
+        &mut program_test,
+        bundle,
+        &Bundle {
+            authority,
+            id: 1,
+            masters: masters.to_vec(),
+            price: PRICE,
+            bump,
+        },
+        Bundle::LEN,
+        10_000_000,
+    );
+    add_charm_account(
+        &mut program_test,
+        listing_address(&masters[0]),
+        &priced_listing(masters[0], authority, LISTING_PRICES[0]),
+        Listing::LEN,
+        10_000_000,
+    );
+    add_charm_account(
+        &mut program_test,
+        listing_address(&masters[1]),
+        &listing(masters[1], authority),
+        Listing::LEN,
+        10_000_000,
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let (pda, pda_nonce) = charm_pda();
+    let mut buy = charm_instruction(
+        charm::accounts::BuyBundle {
+            payer: context.payer.pubkey(),
+            bundle,
+            pda,
+            config: Pubkey::find_program_address(&[b"config"], &charm::id()).0,
+            platform_treasury: Pubkey::new_unique(),
+            metadata_program: spl_token_metadata::id(),
+            token_program: spl_token::id(),
+            system_program: system_program::id(),
+            rent: sysvar::rent::id(),
+        },
+        charm::instruction::BuyBundle {
+            editions: vec![1; 2],
+            pda_nonce,
+        },
+    );
+    // Only the listings are read before the prices are checked.
+    for master_mint in masters.iter() {
+        buy.accounts
+            .extend((0..9).map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false)));
+        buy.accounts.push(AccountMeta::new_readonly(
+            listing_address(master_mint),
+            false,
+        ));
+        buy.accounts
+            .extend((0..3).map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false)));
+    }
+
+    let error = process(&mut context, buy, &[]).await.unwrap_err();
+    assert_charm_error(error, ErrorCode::CurrencyNotAccepted);
+}
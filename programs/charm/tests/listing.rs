@@ -0,0 +1,69 @@
+// The program is registered through its entrypoint, run with `cargo test -p charm`.
+#![cfg(not(feature = "no-entrypoint"))]
+mod utils;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize};
+use charm::state::Listing;
+use solana_program_test::*;
+use solana_sdk::{signature::Signer, signer::keypair::Keypair};
+use utils::*;
+
+#[tokio::test]
+async fn currencies_are_only_accepted_with_a_treasury() {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    let master_mint = Pubkey::new_unique();
+    let usdc = Pubkey::new_unique();
+    add_wallet(&mut program_test, &authority.pubkey());
+    let listing_key = Pubkey::new_unique();
+    add_charm_account(
+        &mut program_test,
+        listing_key,
+        &listing(master_mint, authority.pubkey()),
+        Listing::LEN,
+        10_000_000,
+    );
+    let treasuries = add_native_treasuries(
+        &mut program_test,
+        listing_key,
+        &master_mint,
+        authority.pubkey(),
+    );
+    let usdc_treasury = Pubkey::find_program_address(
+        &[b"listing_treasury", listing_key.as_ref(), usdc.as_ref()],
+        &charm::id(),
+    )
+    .0;
+    let mut context = program_test.start_with_context().await;
+    let set_price = |currency_mint, treasury| {
+        charm_instruction(
+            charm::accounts::SetListingPrice {
+                listing: listing_key,
+                authority: authority.pubkey(),
+                treasury,
+            },
+            charm::instruction::SetListingPrice {
+                currency_mint,
+                price: Some(5),
+            },
+        )
+    };
+
+    // Nothing could hold the USDC paid for an edition.
+    process(&mut context, set_price(usdc, usdc_treasury), &[&authority])
+        .await
+        .unwrap_err();
+
+    let native_mint = spl_token::native_mint::id();
+    process(
+        &mut context,
+        set_price(native_mint, treasuries.listing_treasury),
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let account = get_account(&mut context, &listing_key).await.unwrap();
+    let listing = Listing::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!(listing.prices.len(), 1);
+    assert_eq!(listing.prices[0].mint, native_mint);
+}
@@ -0,0 +1,489 @@
+// The program is registered through its entrypoint, run with `cargo test -p charm`.
+#![cfg(not(feature = "no-entrypoint"))]
+mod utils;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize};
+use charm::{
+    state::{Listing, Offer, PlatformTreasury, ReferrerStats, RoyaltyTreasury},
+    ErrorCode,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    signature::Signer,
+    signer::keypair::Keypair,
+    system_program, sysvar,
+};
+use utils::*;
+
+const AMOUNT: u64 = 10_000;
+
+fn offer_address(edition_mint: &Pubkey, bidder: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"offer", edition_mint.as_ref(), bidder.as_ref()],
+        &charm::id(),
+    )
+}
+
+fn escrow_address(offer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"offer_escrow", offer.as_ref()], &charm::id())
+}
+
+/// An offer of `bidder` on an edition, paid in an SPL currency.
+struct TestOffer {
+    bidder: Keypair,
+    edition_mint: Pubkey,
+    bidder_currency: Pubkey,
+    referrer: Option<Pubkey>,
+    address: Pubkey,
+    escrow: Pubkey,
+}
+
+impl TestOffer {
+    /// Adds the offer with `escrowed` tokens in its escrow.
+    fn add(
+        program_test: &mut ProgramTest,
+        currency_mint: Pubkey,
+        expires_at: i64,
+        escrowed: u64,
+        referrer: Option<Pubkey>,
+    ) -> Self {
+        let bidder = Keypair::new();
+        let edition_mint = Pubkey::new_unique();
+        let bidder_currency = Pubkey::new_unique();
+        let (address, bump) = offer_address(&edition_mint, &bidder.pubkey());
+        let escrow = escrow_address(&address).0;
+        add_wallet(program_test, &bidder.pubkey());
+        add_token_account(
+            program_test,
+            bidder_currency,
+            currency_mint,
+            bidder.pubkey(),
+            0,
+        );
+        let offer = Offer {
+            bidder: bidder.pubkey(),
+            edition_mint,
+            currency_mint,
+            amount: AMOUNT,
+            expires_at,
+            referrer,
+            bump,
+        };
+        add_charm_account(program_test, address, &offer, Offer::LEN, 10_000_000);
+        add_token_account(program_test, escrow, currency_mint, address, escrowed);
+        TestOffer {
+            bidder,
+            edition_mint,
+            bidder_currency,
+            referrer,
+            address,
+            escrow,
+        }
+    }
+
+    fn cancel(&self, signer: &Pubkey, expired: bool) -> Instruction {
+        let accounts = charm::accounts::CancelOffer {
+            signer: *signer,
+            offer: self.address,
+            bidder: self.bidder.pubkey(),
+            escrow: self.escrow,
+            bidder_token: self.bidder_currency,
+            token_program: spl_token::id(),
+            clock: sysvar::clock::id(),
+        };
+        if expired {
+            charm_instruction(accounts, charm::instruction::CloseExpiredOffer {})
+        } else {
+            charm_instruction(accounts, charm::instruction::CancelOffer {})
+        }
+    }
+}
+
+// Natively loaded programs can not call the token program, this needs the BPF build.
+#[cfg_attr(not(feature = "test-bpf"), ignore)]
+#[tokio::test]
+async fn only_the_bidder_cancels_before_expiry_and_anyone_after() {
+    let mut program_test = program_test();
+    let currency_mint = Pubkey::new_unique();
+    let stranger = Keypair::new();
+    add_wallet(&mut program_test, &stranger.pubkey());
+    add_mint(&mut program_test, currency_mint, 1_000_000);
+    let live = TestOffer::add(&mut program_test, currency_mint, i64::MAX, AMOUNT, None);
+    let expired = TestOffer::add(&mut program_test, currency_mint, 1, AMOUNT, None);
+    let mut context = program_test.start_with_context().await;
+
+    for closing in [false, true].iter() {
+        let error = process(
+            &mut context,
+            live.cancel(&stranger.pubkey(), *closing),
+            &[&stranger],
+        )
+        .await
+        .unwrap_err();
+        assert_charm_error(error, ErrorCode::OfferNotExpired);
+    }
+
+    let bidder_before = get_lamports(&mut context, &live.bidder.pubkey()).await;
+    process(
+        &mut context,
+        live.cancel(&live.bidder.pubkey(), false),
+        &[&live.bidder],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        get_token_balance(&mut context, &live.bidder_currency).await,
+        AMOUNT
+    );
+    assert!(get_account(&mut context, &live.escrow).await.is_none());
+    // The rent of the offer and of its escrow goes back to the bidder.
+    assert_eq!(
+        get_lamports(&mut context, &live.bidder.pubkey()).await - bidder_before,
+        20_000_000
+    );
+
+    process(
+        &mut context,
+        expired.cancel(&stranger.pubkey(), true),
+        &[&stranger],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        get_token_balance(&mut context, &expired.bidder_currency).await,
+        AMOUNT
+    );
+    assert!(get_account(&mut context, &expired.address).await.is_none());
+}
+
+/// Accounts of a holder accepting an offer on its edition.
+struct Sale {
+    offer: TestOffer,
+    holder: Keypair,
+    referrer: Pubkey,
+    treasury: Pubkey,
+    royalty_vault: Pubkey,
+    listing: Pubkey,
+    holder_token: Pubkey,
+    bidder_token: Pubkey,
+    proceeds: Pubkey,
+    platform_treasury: Pubkey,
+    platform_vault: Pubkey,
+    referrer_currency: Pubkey,
+}
+
+impl Sale {
+    /// Adds an offer of `AMOUNT` with `escrowed` tokens on an edition of `holder` with 5%
+    /// royalties, platform fees being paid into the platform treasury of the currency.
+    fn add(
+        program_test: &mut ProgramTest,
+        holder: Keypair,
+        escrowed: u64,
+        referrer: Option<Pubkey>,
+        referral_bps: Option<u16>,
+    ) -> Self {
+        let currency_mint = Pubkey::new_unique();
+        let master_mint = Pubkey::new_unique();
+        let master_edition = charm::utils::edition_address(&master_mint);
+        add_wallet(program_test, &holder.pubkey());
+        add_mint(program_test, currency_mint, 1_000_000);
+        let offer = TestOffer::add(program_test, currency_mint, i64::MAX, escrowed, referrer);
+
+        add_mint(program_test, offer.edition_mint, 1);
+        add_metadata(
+            program_test,
+            offer.edition_mint,
+            Pubkey::new_unique(),
+            500,
+            None,
+        );
+        add_edition(program_test, &offer.edition_mint, master_edition);
+        let listing =
+            Pubkey::find_program_address(&[b"listing", master_mint.as_ref()], &charm::id()).0;
+        add_charm_account(
+            program_test,
+            listing,
+            &Listing {
+                referral_bps,
+                ..utils::listing(master_mint, Pubkey::new_unique())
+            },
+            Listing::LEN,
+            10_000_000,
+        );
+        let (treasury, treasury_bump) = Pubkey::find_program_address(
+            &[
+                b"royalty_treasury",
+                master_edition.as_ref(),
+                currency_mint.as_ref(),
+            ],
+            &charm::id(),
+        );
+        let royalty_vault =
+            Pubkey::find_program_address(&[b"royalty_vault", treasury.as_ref()], &charm::id()).0;
+        add_charm_account(
+            program_test,
+            treasury,
+            &RoyaltyTreasury {
+                master_edition,
+                currency_mint,
+                total_received: 0,
+                claims: vec![],
+                bump: treasury_bump,
+            },
+            RoyaltyTreasury::LEN,
+            10_000_000,
+        );
+        add_token_account(program_test, royalty_vault, currency_mint, treasury, 0);
+        let (platform_treasury, platform_vault) =
+            add_platform_treasury(program_test, currency_mint);
+
+        let sale = Sale {
+            holder,
+            referrer: referrer.unwrap_or_default(),
+            treasury,
+            royalty_vault,
+            listing,
+            holder_token: Pubkey::new_unique(),
+            bidder_token: Pubkey::new_unique(),
+            proceeds: Pubkey::new_unique(),
+            platform_treasury,
+            platform_vault,
+            referrer_currency: Pubkey::new_unique(),
+            offer,
+        };
+        let edition_mint = sale.offer.edition_mint;
+        let holder = sale.holder.pubkey();
+        add_token_account(program_test, sale.holder_token, edition_mint, holder, 1);
+        let bidder = sale.offer.bidder.pubkey();
+        add_token_account(program_test, sale.bidder_token, edition_mint, bidder, 0);
+        add_token_account(program_test, sale.proceeds, currency_mint, holder, 0);
+        let referrer = sale.referrer;
+        add_token_account(
+            program_test,
+            sale.referrer_currency,
+            currency_mint,
+            referrer,
+            0,
+        );
+        sale
+    }
+
+    fn accept(&self) -> Instruction {
+        let offer = &self.offer;
+        let mut instruction = charm_instruction(
+            charm::accounts::AcceptOffer {
+                holder: self.holder.pubkey(),
+                offer: offer.address,
+                bidder: offer.bidder.pubkey(),
+                escrow: offer.escrow,
+                metadata: metadata_address(&offer.edition_mint),
+                edition: charm::utils::edition_address(&offer.edition_mint),
+                listing: self.listing,
+                treasury: self.treasury,
+                royalty_vault: self.royalty_vault,
+                holder_token: self.holder_token,
+                bidder_token: self.bidder_token,
+                bidder_currency: offer.bidder_currency,
+                proceeds: self.proceeds,
+                config: Pubkey::find_program_address(&[b"config"], &charm::id()).0,
+                platform_treasury: self.platform_treasury,
+                platform_vault: self.platform_vault,
+                token_program: spl_token::id(),
+                system_program: system_program::id(),
+                clock: sysvar::clock::id(),
+            },
+            charm::instruction::AcceptOffer {},
+        );
+        if offer.referrer.is_some() {
+            let stats = Pubkey::find_program_address(
+                &[b"referrer_stats", self.referrer.as_ref()],
+                &charm::id(),
+            )
+            .0;
+            instruction.accounts.extend(vec![
+                AccountMeta::new(self.referrer_currency, false),
+                AccountMeta::new(stats, false),
+            ]);
+        }
+        instruction
+    }
+}
+
+// Natively loaded programs can not call the token program, this needs the BPF build.
+#[cfg_attr(not(feature = "test-bpf"), ignore)]
+#[tokio::test]
+async fn accepting_pays_royalties_fee_and_holder_and_returns_the_surplus() {
+    let mut program_test = program_test();
+    add_config(&mut program_test, Pubkey::new_unique(), 250, 0);
+    // Something sent 100 more tokens to the escrow than the bidder offered.
+    let sale = Sale::add(&mut program_test, Keypair::new(), AMOUNT + 100, None, None);
+    let mut context = program_test.start_with_context().await;
+
+    process(&mut context, sale.accept(), &[&sale.holder])
+        .await
+        .unwrap();
+
+    assert_eq!(get_token_balance(&mut context, &sale.bidder_token).await, 1);
+    assert_eq!(get_token_balance(&mut context, &sale.holder_token).await, 0);
+    // 5% royalties, 2.5% platform fee, the rest to the holder.
+    assert_eq!(
+        get_token_balance(&mut context, &sale.royalty_vault).await,
+        500
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &sale.platform_vault).await,
+        250
+    );
+    let account = get_account(&mut context, &sale.platform_treasury)
+        .await
+        .unwrap();
+    assert_eq!(
+        PlatformTreasury::try_deserialize(&mut &account.data[..])
+            .unwrap()
+            .fees,
+        250
+    );
+    assert_eq!(get_token_balance(&mut context, &sale.proceeds).await, 9_250);
+    assert_eq!(
+        get_token_balance(&mut context, &sale.offer.bidder_currency).await,
+        100
+    );
+    assert!(get_account(&mut context, &sale.offer.escrow)
+        .await
+        .is_none());
+    assert!(get_account(&mut context, &sale.offer.address)
+        .await
+        .is_none());
+    let account = get_account(&mut context, &sale.treasury).await.unwrap();
+    assert_eq!(
+        RoyaltyTreasury::try_deserialize(&mut &account.data[..])
+            .unwrap()
+            .total_received,
+        500
+    );
+}
+
+#[cfg_attr(not(feature = "test-bpf"), ignore)]
+#[tokio::test]
+async fn accepting_pays_the_referrer_picked_by_the_bidder_at_the_listing_rate() {
+    let mut program_test = program_test();
+    add_config(&mut program_test, Pubkey::new_unique(), 250, 0);
+    let referrer = Pubkey::new_unique();
+    let sale = Sale::add(
+        &mut program_test,
+        Keypair::new(),
+        AMOUNT,
+        Some(referrer),
+        Some(5_000),
+    );
+    let holder = Keypair::new();
+    let self_referred = holder.pubkey();
+    let self_sale = Sale::add(&mut program_test, holder, AMOUNT, Some(self_referred), None);
+    let mut context = program_test.start_with_context().await;
+
+    // The holder can not take the referral of its own sale.
+    let error = process(&mut context, self_sale.accept(), &[&self_sale.holder])
+        .await
+        .unwrap_err();
+    assert_charm_error(error, ErrorCode::InvalidReferrer);
+
+    process(&mut context, sale.accept(), &[&sale.holder])
+        .await
+        .unwrap();
+
+    // Half of the 2.5% fee, the listing overriding the config referral rate.
+    assert_eq!(
+        get_token_balance(&mut context, &sale.referrer_currency).await,
+        125
+    );
+    assert_eq!(
+        get_token_balance(&mut context, &sale.platform_vault).await,
+        125
+    );
+    let stats =
+        Pubkey::find_program_address(&[b"referrer_stats", referrer.as_ref()], &charm::id()).0;
+    let account = get_account(&mut context, &stats).await.unwrap();
+    let stats = ReferrerStats::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!(stats.referrals, 1);
+    assert_eq!(stats.currencies[0].earnings, 125);
+}
+
+// Natively loaded programs can not create accounts, `make_offer` needs the BPF build.
+#[cfg_attr(not(feature = "test-bpf"), ignore)]
+#[tokio::test]
+async fn escrows_offers_and_rejects_empty_and_self_referred_ones() {
+    let mut program_test = program_test();
+    let bidder = Keypair::new();
+    let edition_mint = Pubkey::new_unique();
+    let currency_mint = Pubkey::new_unique();
+    let bidder_currency = Pubkey::new_unique();
+    add_wallet(&mut program_test, &bidder.pubkey());
+    add_mint(&mut program_test, currency_mint, 1_000_000);
+    add_metadata(
+        &mut program_test,
+        edition_mint,
+        Pubkey::new_unique(),
+        500,
+        None,
+    );
+    add_token_account(
+        &mut program_test,
+        bidder_currency,
+        currency_mint,
+        bidder.pubkey(),
+        AMOUNT,
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let (offer, offer_bump) = offer_address(&edition_mint, &bidder.pubkey());
+    let (escrow, escrow_bump) = escrow_address(&offer);
+    let now = context
+        .banks_client
+        .get_clock()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    let make_offer = |amount, referrer| {
+        charm_instruction(
+            charm::accounts::MakeOffer {
+                bidder: bidder.pubkey(),
+                metadata: metadata_address(&edition_mint),
+                currency_mint,
+                bidder_token: bidder_currency,
+                offer,
+                escrow,
+                token_program: spl_token::id(),
+                system_program: system_program::id(),
+                rent: sysvar::rent::id(),
+                clock: sysvar::clock::id(),
+            },
+            charm::instruction::MakeOffer {
+                offer_bump,
+                _escrow_bump: escrow_bump,
+                amount,
+                expires_at: now + 3_600,
+                referrer,
+            },
+        )
+    };
+
+    let error = process(&mut context, make_offer(0, None), &[&bidder])
+        .await
+        .unwrap_err();
+    assert_charm_error(error, ErrorCode::InvalidOfferAmount);
+    let error = process(
+        &mut context,
+        make_offer(AMOUNT, Some(bidder.pubkey())),
+        &[&bidder],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::InvalidReferrer);
+
+    process(&mut context, make_offer(AMOUNT, None), &[&bidder])
+        .await
+        .unwrap();
+    assert_eq!(get_token_balance(&mut context, &escrow).await, AMOUNT);
+    assert_eq!(get_token_balance(&mut context, &bidder_currency).await, 0);
+}
@@ -0,0 +1,467 @@
+// The program is registered through its entrypoint, run with `cargo test -p charm`.
+#![cfg(not(feature = "no-entrypoint"))]
+mod utils;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize};
+use charm::{
+    state::{Listing, ListingTreasury, MintWindow, PlatformTreasury, Raffle, RaffleEntry},
+    ErrorCode,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    hash::hash,
+    signature::Signer,
+    signer::keypair::Keypair,
+    system_program,
+    sysvar::{self, slot_hashes::SlotHashes},
+};
+use utils::*;
+
+const PRICE: u64 = 100_000_000;
+
+fn raffle(master_mint: Pubkey, authority: Pubkey, seed: &[u8; 32], deadline: i64) -> Raffle {
+    Raffle {
+        master_mint,
+        authority,
+        price: PRICE,
+        entry_window: MintWindow { start: 0, end: 100 },
+        winner_count: 1,
+        entry_count: 1,
+        seed_commitment: hash(seed).to_bytes(),
+        reveal_deadline: deadline,
+        draw_slot: None,
+        randomness: None,
+        proceeds_withdrawn: false,
+        bump: 255,
+    }
+}
+
+/// Adds a raffle holding the price of the single entry of `entrant`.
+fn add_raffle(
+    program_test: &mut ProgramTest,
+    raffle: &Raffle,
+    entrant: &Pubkey,
+) -> (Pubkey, Pubkey) {
+    let raffle_key =
+        Pubkey::find_program_address(&[b"raffle", raffle.master_mint.as_ref()], &charm::id()).0;
+    let entry_key = Pubkey::find_program_address(
+        &[b"raffle_entry", raffle_key.as_ref(), entrant.as_ref()],
+        &charm::id(),
+    )
+    .0;
+    add_charm_account(
+        program_test,
+        raffle_key,
+        raffle,
+        Raffle::LEN,
+        10_000_000 + PRICE,
+    );
+    let entry = RaffleEntry {
+        raffle: raffle_key,
+        entrant: *entrant,
+        index: 0,
+        claimed: false,
+        bump: 255,
+    };
+    add_charm_account(
+        program_test,
+        entry_key,
+        &entry,
+        RaffleEntry::LEN,
+        10_000_000,
+    );
+    (raffle_key, entry_key)
+}
+
+fn reveal(
+    raffle: Pubkey,
+    authority: &Keypair,
+    seed: [u8; 32],
+) -> solana_sdk::instruction::Instruction {
+    charm_instruction(
+        charm::accounts::RevealRaffle {
+            raffle,
+            authority: authority.pubkey(),
+            slot_hashes: sysvar::slot_hashes::id(),
+            clock: sysvar::clock::id(),
+        },
+        charm::instruction::RevealRaffle { seed },
+    )
+}
+
+fn claim_refund(
+    raffle: Pubkey,
+    entry: Pubkey,
+    entrant: &Keypair,
+) -> solana_sdk::instruction::Instruction {
+    charm_instruction(
+        charm::accounts::ClaimRaffleRefund {
+            raffle,
+            entry,
+            entrant: entrant.pubkey(),
+            clock: sysvar::clock::id(),
+        },
+        charm::instruction::ClaimRaffleRefund {},
+    )
+}
+
+// Natively loaded programs can not create accounts, `create_raffle` needs the BPF build.
+#[cfg_attr(not(feature = "test-bpf"), ignore)]
+#[tokio::test]
+async fn winners_are_capped_by_the_editions_left() {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    let master_mint = Pubkey::new_unique();
+    let listing_key = Pubkey::new_unique();
+    add_wallet(&mut program_test, &authority.pubkey());
+    add_master_edition(&mut program_test, &master_mint, 3, Some(5));
+    add_charm_account(
+        &mut program_test,
+        listing_key,
+        &listing(master_mint, authority.pubkey()),
+        Listing::LEN,
+        10_000_000,
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let (raffle_key, bump) =
+        Pubkey::find_program_address(&[b"raffle", master_mint.as_ref()], &charm::id());
+    let create = |winner_count| {
+        charm_instruction(
+            charm::accounts::CreateRaffle {
+                listing: listing_key,
+                master_edition: charm::utils::edition_address(&master_mint),
+                raffle: raffle_key,
+                authority: authority.pubkey(),
+                system_program: system_program::id(),
+            },
+            charm::instruction::CreateRaffle {
+                bump,
+                price: PRICE,
+                entry_window: MintWindow { start: 0, end: 100 },
+                winner_count,
+                seed_commitment: [7; 32],
+                reveal_deadline: 200,
+            },
+        )
+    };
+
+    let error = process(&mut context, create(3), &[&authority])
+        .await
+        .unwrap_err();
+    assert_charm_error(error, ErrorCode::RaffleExceedsSupply);
+
+    process(&mut context, create(2), &[&authority])
+        .await
+        .unwrap();
+    let raffle = get_account(&mut context, &raffle_key).await.unwrap();
+    let raffle = Raffle::try_deserialize(&mut &raffle.data[..]).unwrap();
+    assert_eq!(raffle.winner_count, 2);
+}
+
+#[tokio::test]
+async fn entries_are_refunded_when_the_seed_is_not_revealed_in_time() {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    let entrant = Keypair::new();
+    let seed = [3; 32];
+    add_wallet(&mut program_test, &authority.pubkey());
+    add_wallet(&mut program_test, &entrant.pubkey());
+    let expired = Raffle {
+        draw_slot: Some(0),
+        ..raffle(Pubkey::new_unique(), authority.pubkey(), &seed, 200)
+    };
+    let (expired_key, expired_entry) = add_raffle(&mut program_test, &expired, &entrant.pubkey());
+    let open = raffle(Pubkey::new_unique(), authority.pubkey(), &seed, i64::MAX);
+    let (open_key, open_entry) = add_raffle(&mut program_test, &open, &entrant.pubkey());
+    let mut context = program_test.start_with_context().await;
+
+    // The deadline is long gone, the authority can no longer draw winners.
+    let error = process(
+        &mut context,
+        reveal(expired_key, &authority, seed),
+        &[&authority],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::RevealDeadlinePassed);
+
+    let error = process(
+        &mut context,
+        claim_refund(open_key, open_entry, &entrant),
+        &[&entrant],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::RaffleNotRevealed);
+
+    let before = get_account(&mut context, &entrant.pubkey())
+        .await
+        .unwrap()
+        .lamports;
+    process(
+        &mut context,
+        claim_refund(expired_key, expired_entry, &entrant),
+        &[&entrant],
+    )
+    .await
+    .unwrap();
+    let after = get_account(&mut context, &entrant.pubkey())
+        .await
+        .unwrap()
+        .lamports;
+    assert_eq!(after - before, PRICE + 10_000_000);
+    assert!(get_account(&mut context, &expired_entry).await.is_none());
+}
+
+#[tokio::test]
+async fn winners_are_drawn_from_the_slot_fixed_when_entries_close() {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    let entrant = Keypair::new();
+    let seed = [3; 32];
+    add_wallet(&mut program_test, &authority.pubkey());
+    let unrevealed = raffle(Pubkey::new_unique(), authority.pubkey(), &seed, i64::MAX);
+    let (raffle_key, _) = add_raffle(&mut program_test, &unrevealed, &entrant.pubkey());
+    let mut context = program_test.start_with_context().await;
+
+    let close = charm_instruction(
+        charm::accounts::CloseRaffleEntries {
+            raffle: raffle_key,
+            clock: sysvar::clock::id(),
+        },
+        charm::instruction::CloseRaffleEntries {},
+    );
+    process(&mut context, close, &[]).await.unwrap();
+    let account = get_account(&mut context, &raffle_key).await.unwrap();
+    let draw_slot = Raffle::try_deserialize(&mut &account.data[..])
+        .unwrap()
+        .draw_slot
+        .unwrap();
+
+    // The hash of the draw slot is unknown until the slot ends.
+    let error = process(
+        &mut context,
+        reveal(raffle_key, &authority, seed),
+        &[&authority],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::DrawSlotUnavailable);
+
+    context.warp_to_slot(draw_slot + 2).unwrap();
+    process(
+        &mut context,
+        reveal(raffle_key, &authority, seed),
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    let slot_hashes = context
+        .banks_client
+        .get_sysvar::<SlotHashes>()
+        .await
+        .unwrap();
+    let slot_hash = slot_hashes.get(&draw_slot).unwrap().to_bytes();
+    let account = get_account(&mut context, &raffle_key).await.unwrap();
+    assert_eq!(
+        Raffle::try_deserialize(&mut &account.data[..])
+            .unwrap()
+            .randomness,
+        Some(charm::utils::draw_randomness(&seed, &slot_hash))
+    );
+}
+
+#[tokio::test]
+async fn proceeds_of_the_winners_are_split_like_a_sale() {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    let master_mint = Pubkey::new_unique();
+    add_config(&mut program_test, Pubkey::new_unique(), 250, 0);
+    let (platform_treasury, _) =
+        add_platform_treasury(&mut program_test, spl_token::native_mint::id());
+    add_metadata(&mut program_test, master_mint, authority.pubkey(), 0, None);
+    let (listing_key, listing_bump) =
+        Pubkey::find_program_address(&[b"listing", master_mint.as_ref()], &charm::id());
+    add_charm_account(
+        &mut program_test,
+        listing_key,
+        &Listing {
+            raffled: true,
+            bump: listing_bump,
+            ..listing(master_mint, authority.pubkey())
+        },
+        Listing::LEN,
+        10_000_000,
+    );
+    let treasuries = add_native_treasuries(
+        &mut program_test,
+        listing_key,
+        &master_mint,
+        authority.pubkey(),
+    );
+    let revealed = Raffle {
+        randomness: Some([9; 32]),
+        ..raffle(master_mint, authority.pubkey(), &[3; 32], i64::MAX)
+    };
+    let (raffle_key, _) = add_raffle(&mut program_test, &revealed, &Pubkey::new_unique());
+    let mut context = program_test.start_with_context().await;
+
+    let withdraw = charm_instruction(
+        charm::accounts::WithdrawRaffleProceeds {
+            raffle: raffle_key,
+            authority: authority.pubkey(),
+            listing: listing_key,
+            master_metadata: metadata_address(&master_mint),
+            config: Pubkey::find_program_address(&[b"config"], &charm::id()).0,
+            platform_treasury,
+            royalty_treasury: treasuries.royalty_treasury,
+            royalty_vault: treasuries.royalty_vault,
+            listing_treasury: treasuries.listing_treasury,
+            token_program: spl_token::id(),
+        },
+        charm::instruction::WithdrawRaffleProceeds {},
+    );
+    process(&mut context, withdraw, &[&authority])
+        .await
+        .unwrap();
+
+    let fee = charm::utils::basis_points_of(PRICE, 250);
+    let account = get_account(&mut context, &platform_treasury).await.unwrap();
+    assert_eq!(account.lamports, 10_000_000 + fee);
+    let platform = PlatformTreasury::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!(platform.fees, fee);
+    let account = get_account(&mut context, &treasuries.listing_treasury)
+        .await
+        .unwrap();
+    assert_eq!(account.lamports, 10_000_000 + PRICE - fee);
+    let treasury = ListingTreasury::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!(treasury.gross_sales, PRICE);
+    assert_eq!(treasury.fees, fee);
+    assert_eq!(get_lamports(&mut context, &raffle_key).await, 10_000_000);
+}
+
+#[tokio::test]
+async fn draw_slots_gone_from_slot_hashes_are_replaced() {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    let seed = [3; 32];
+    add_wallet(&mut program_test, &authority.pubkey());
+    let unrevealed = raffle(Pubkey::new_unique(), authority.pubkey(), &seed, i64::MAX);
+    let (raffle_key, _) = add_raffle(&mut program_test, &unrevealed, &Pubkey::new_unique());
+    let mut context = program_test.start_with_context().await;
+    let close = || {
+        charm_instruction(
+            charm::accounts::CloseRaffleEntries {
+                raffle: raffle_key,
+                clock: sysvar::clock::id(),
+            },
+            charm::instruction::CloseRaffleEntries {},
+        )
+    };
+    let draw_slot = |account: solana_sdk::account::Account| {
+        Raffle::try_deserialize(&mut &account.data[..])
+            .unwrap()
+            .draw_slot
+            .unwrap()
+    };
+
+    process(&mut context, close(), &[]).await.unwrap();
+    let first_slot = draw_slot(get_account(&mut context, &raffle_key).await.unwrap());
+
+    // SlotHashes only keeps the last 512 slots, the creator revealed too late.
+    context.warp_to_slot(first_slot + 600).unwrap();
+    let error = process(
+        &mut context,
+        reveal(raffle_key, &authority, seed),
+        &[&authority],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::DrawSlotUnavailable);
+
+    process(&mut context, close(), &[]).await.unwrap();
+    let second_slot = draw_slot(get_account(&mut context, &raffle_key).await.unwrap());
+    assert!(second_slot > first_slot + 512);
+
+    context.warp_to_slot(second_slot + 2).unwrap();
+    process(
+        &mut context,
+        reveal(raffle_key, &authority, seed),
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let slot_hashes = context
+        .banks_client
+        .get_sysvar::<SlotHashes>()
+        .await
+        .unwrap();
+    let slot_hash = slot_hashes.get(&second_slot).unwrap().to_bytes();
+    let account = get_account(&mut context, &raffle_key).await.unwrap();
+    assert_eq!(
+        Raffle::try_deserialize(&mut &account.data[..])
+            .unwrap()
+            .randomness,
+        Some(charm::utils::draw_randomness(&seed, &slot_hash))
+    );
+
+    // Revealed raffles keep their draw.
+    context.warp_to_slot(second_slot + 600).unwrap();
+    let error = process(&mut context, close(), &[]).await.unwrap_err();
+    assert_charm_error(error, ErrorCode::RaffleRevealed);
+}
+
+#[tokio::test]
+async fn listings_of_unrevealed_raffles_sell_again_after_the_deadline() {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    let seed = [3; 32];
+    let mut listings = vec![];
+    for deadline in [200, i64::MAX].iter() {
+        let master_mint = Pubkey::new_unique();
+        let (listing_key, listing_bump) =
+            Pubkey::find_program_address(&[b"listing", master_mint.as_ref()], &charm::id());
+        add_charm_account(
+            &mut program_test,
+            listing_key,
+            &Listing {
+                raffled: true,
+                bump: listing_bump,
+                ..listing(master_mint, authority.pubkey())
+            },
+            Listing::LEN,
+            10_000_000,
+        );
+        let unrevealed = raffle(master_mint, authority.pubkey(), &seed, *deadline);
+        let (raffle_key, _) = add_raffle(&mut program_test, &unrevealed, &Pubkey::new_unique());
+        listings.push((raffle_key, listing_key));
+    }
+    let mut context = program_test.start_with_context().await;
+    let expire = |(raffle, listing): (Pubkey, Pubkey)| {
+        charm_instruction(
+            charm::accounts::ExpireRaffle {
+                raffle,
+                listing,
+                clock: sysvar::clock::id(),
+            },
+            charm::instruction::ExpireRaffle {},
+        )
+    };
+
+    let error = process(&mut context, expire(listings[1]), &[])
+        .await
+        .unwrap_err();
+    assert_charm_error(error, ErrorCode::RevealDeadlineNotPassed);
+
+    process(&mut context, expire(listings[0]), &[])
+        .await
+        .unwrap();
+    let account = get_account(&mut context, &listings[0].1).await.unwrap();
+    assert!(
+        !Listing::try_deserialize(&mut &account.data[..])
+            .unwrap()
+            .raffled
+    );
+}
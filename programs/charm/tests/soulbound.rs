@@ -0,0 +1,143 @@
+// The program is registered through its entrypoint, run with `cargo test -p charm`.
+#![cfg(not(feature = "no-entrypoint"))]
+mod utils;
+
+use anchor_lang::prelude::Pubkey;
+use charm::{
+    state::{PassTerms, SoulboundEdition},
+    ErrorCode,
+};
+use solana_program_test::*;
+use solana_sdk::{instruction::AccountMeta, signature::Signer, signer::keypair::Keypair};
+use utils::*;
+
+// Natively loaded programs can not create accounts, this needs the BPF build.
+#[cfg_attr(not(feature = "test-bpf"), ignore)]
+#[tokio::test]
+async fn soulbound_listings_do_not_sell_passes() {
+    let mut program_test = program_test();
+    let authority = Keypair::new();
+    let master_mint = Pubkey::new_unique();
+    add_wallet(&mut program_test, &authority.pubkey());
+    add_metadata(&mut program_test, master_mint, authority.pubkey(), 0, None);
+    add_master_edition(&mut program_test, &master_mint, 0, Some(10));
+    let (listing, bump) =
+        Pubkey::find_program_address(&[b"listing", master_mint.as_ref()], &charm::id());
+    let mut context = program_test.start_with_context().await;
+
+    let error = process(
+        &mut context,
+        charm_instruction(
+            charm::accounts::CreateListing {
+                metadata: metadata_address(&master_mint),
+                master_edition: charm::utils::edition_address(&master_mint),
+                listing,
+                authority: authority.pubkey(),
+                system_program: solana_sdk::system_program::id(),
+            },
+            charm::instruction::CreateListing {
+                bump,
+                soulbound: true,
+                pass: Some(PassTerms {
+                    duration: 86_400,
+                    renewal_price: 1_000,
+                }),
+                window: None,
+            },
+        ),
+        &[&authority],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::SoulboundPass);
+}
+
+#[tokio::test]
+async fn soulbound_editions_are_held_by_their_holder() {
+    let mut program_test = program_test();
+    let holder = Keypair::new();
+    let master_mint = Pubkey::new_unique();
+    let edition_mint = Pubkey::new_unique();
+    add_master_edition(&mut program_test, &master_mint, 1, Some(10));
+    add_mint(&mut program_test, edition_mint, 1);
+    add_metadata(
+        &mut program_test,
+        edition_mint,
+        Pubkey::new_unique(),
+        0,
+        None,
+    );
+    add_edition(
+        &mut program_test,
+        &edition_mint,
+        charm::utils::edition_address(&master_mint),
+    );
+    let (soulbound_edition, bump) =
+        Pubkey::find_program_address(&[b"soulbound", edition_mint.as_ref()], &charm::id());
+    add_charm_account(
+        &mut program_test,
+        soulbound_edition,
+        &SoulboundEdition {
+            mint: edition_mint,
+            master_mint,
+            holder: holder.pubkey(),
+            bump,
+        },
+        SoulboundEdition::LEN,
+        10_000_000,
+    );
+    let custody = Pubkey::new_unique();
+    add_token_account(
+        &mut program_test,
+        custody,
+        edition_mint,
+        soulbound_edition,
+        1,
+    );
+    let mut context = program_test.start_with_context().await;
+    let prove_ownership = |wallet: Pubkey, soulbound: bool| {
+        let mut instruction = charm_instruction(
+            charm::accounts::ProveOwnership {
+                wallet,
+                token_account: custody,
+                metadata: metadata_address(&edition_mint),
+                edition: charm::utils::edition_address(&edition_mint),
+                master_edition: charm::utils::edition_address(&master_mint),
+            },
+            charm::instruction::ProveOwnership {},
+        );
+        if soulbound {
+            instruction
+                .accounts
+                .push(AccountMeta::new_readonly(soulbound_edition, false));
+        }
+        instruction
+    };
+
+    let error = process(
+        &mut context,
+        prove_ownership(holder.pubkey(), false),
+        &[&holder],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::NotEditionHolder);
+
+    let stranger = Keypair::new();
+    let error = process(
+        &mut context,
+        prove_ownership(stranger.pubkey(), true),
+        &[&stranger],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::NotEditionHolder);
+
+    process(
+        &mut context,
+        prove_ownership(holder.pubkey(), true),
+        &[&holder],
+    )
+    .await
+    .unwrap();
+}
@@ -0,0 +1,136 @@
+// The program is registered through its entrypoint, run with `cargo test -p charm`.
+#![cfg(not(feature = "no-entrypoint"))]
+mod utils;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize};
+use charm::{state::PlatformTreasury, ErrorCode};
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::Instruction, signature::Signer, signer::keypair::Keypair, system_program, sysvar,
+};
+use utils::*;
+
+fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &charm::id()).0
+}
+
+fn platform_treasury_address(currency_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"platform_treasury", currency_mint.as_ref()],
+        &charm::id(),
+    )
+}
+
+fn platform_vault_address(treasury: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"platform_vault", treasury.as_ref()], &charm::id())
+}
+
+fn withdraw_fees(
+    fee_wallet: &Pubkey,
+    treasury: Pubkey,
+    destination: Pubkey,
+    amount: u64,
+) -> Instruction {
+    charm_instruction(
+        charm::accounts::WithdrawPlatformFees {
+            fee_wallet: *fee_wallet,
+            config: config_address(),
+            treasury,
+            vault: platform_vault_address(&treasury).0,
+            destination,
+            token_program: spl_token::id(),
+        },
+        charm::instruction::WithdrawPlatformFees { amount },
+    )
+}
+
+#[tokio::test]
+async fn only_the_fee_wallet_withdraws_the_platform_fees() {
+    let mut program_test = program_test();
+    let fee_wallet = Keypair::new();
+    let other = Keypair::new();
+    add_wallet(&mut program_test, &fee_wallet.pubkey());
+    add_wallet(&mut program_test, &other.pubkey());
+    add_config(&mut program_test, fee_wallet.pubkey(), 250, 0);
+    let (treasury, bump) = platform_treasury_address(&spl_token::native_mint::id());
+    add_charm_account(
+        &mut program_test,
+        treasury,
+        &PlatformTreasury {
+            currency_mint: spl_token::native_mint::id(),
+            fees: 3_000,
+            withdrawn: 1_000,
+            bump,
+        },
+        PlatformTreasury::LEN,
+        10_000_000 + 2_000,
+    );
+    let mut context = program_test.start_with_context().await;
+
+    process(
+        &mut context,
+        withdraw_fees(&other.pubkey(), treasury, other.pubkey(), 2_000),
+        &[&other],
+    )
+    .await
+    .unwrap_err();
+
+    let error = process(
+        &mut context,
+        withdraw_fees(&fee_wallet.pubkey(), treasury, fee_wallet.pubkey(), 2_001),
+        &[&fee_wallet],
+    )
+    .await
+    .unwrap_err();
+    assert_charm_error(error, ErrorCode::InsufficientProceeds);
+
+    let before = get_lamports(&mut context, &fee_wallet.pubkey()).await;
+    process(
+        &mut context,
+        withdraw_fees(&fee_wallet.pubkey(), treasury, fee_wallet.pubkey(), 2_000),
+        &[&fee_wallet],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        get_lamports(&mut context, &fee_wallet.pubkey()).await - before,
+        2_000
+    );
+    let account = get_account(&mut context, &treasury).await.unwrap();
+    let treasury = PlatformTreasury::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!(treasury.withdrawn, 3_000);
+    assert_eq!(treasury.available(), 0);
+}
+
+// Natively loaded programs can not create accounts, this needs the BPF build.
+#[cfg_attr(not(feature = "test-bpf"), ignore)]
+#[tokio::test]
+async fn vaults_are_created_at_addresses_funded_beforehand() {
+    let mut program_test = program_test();
+    let currency_mint = Pubkey::new_unique();
+    add_mint(&mut program_test, currency_mint, 1_000_000);
+    let (treasury, bump) = platform_treasury_address(&currency_mint);
+    let (vault, vault_bump) = platform_vault_address(&treasury);
+    // Anyone can send lamports to the vault address before it exists.
+    add_wallet(&mut program_test, &vault);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let create = charm_instruction(
+        charm::accounts::CreatePlatformTreasury {
+            payer,
+            currency_mint,
+            treasury,
+            vault,
+            token_program: spl_token::id(),
+            system_program: system_program::id(),
+            rent: sysvar::rent::id(),
+        },
+        charm::instruction::CreatePlatformTreasury { bump, vault_bump },
+    );
+    process(&mut context, create, &[]).await.unwrap();
+
+    let account = get_account(&mut context, &vault).await.unwrap();
+    assert_eq!(account.owner, spl_token::id());
+    assert_eq!(get_token_balance(&mut context, &vault).await, 0);
+}
@@ -0,0 +1,550 @@
+#![allow(dead_code)]
+
+use anchor_lang::{AccountSerialize, AnchorSerialize, InstructionData, ToAccountMetas};
+use charm::{
+    state::{Config, Listing, ListingTreasury, PlatformTreasury, RoyaltyTreasury},
+    ErrorCode,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    instruction::{Instruction, InstructionError},
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::Signer,
+    signer::keypair::Keypair,
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+    transport::{self, TransportError},
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use spl_token_metadata::state::{
+    Creator, Data, Edition, Key, MasterEditionV2, Metadata, EDITION, MAX_METADATA_LEN, PREFIX,
+};
+
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new("charm", charm::id(), processor!(charm::entry))
+}
+
+/// Adds the programs the charm program calls into, only available as BPF builds.
+pub fn add_metadata_program(program_test: &mut ProgramTest) {
+    program_test.add_program("spl_token_metadata", spl_token_metadata::id(), None);
+}
+
+pub fn charm_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"charmpda"], &charm::id())
+}
+
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    let program_id = spl_token_metadata::id();
+    Pubkey::find_program_address(
+        &[PREFIX.as_bytes(), program_id.as_ref(), mint.as_ref()],
+        &program_id,
+    )
+    .0
+}
+
+pub fn edition_marker_address(master_mint: &Pubkey, edition: u64) -> Pubkey {
+    let program_id = spl_token_metadata::id();
+    let marker = (edition / spl_token_metadata::state::EDITION_MARKER_BIT_SIZE).to_string();
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            program_id.as_ref(),
+            master_mint.as_ref(),
+            EDITION.as_bytes(),
+            marker.as_bytes(),
+        ],
+        &program_id,
+    )
+    .0
+}
+
+/// Adds a system account holding 10 SOL.
+pub fn add_wallet(program_test: &mut ProgramTest, wallet: &Pubkey) {
+    program_test.add_account(
+        *wallet,
+        Account {
+            lamports: 10_000_000_000,
+            data: vec![],
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// Adds an anchor account owned by the charm program, padded to `len`.
+pub fn add_charm_account(
+    program_test: &mut ProgramTest,
+    pubkey: Pubkey,
+    account: &impl AccountSerialize,
+    len: usize,
+    lamports: u64,
+) {
+    let mut data = Vec::with_capacity(len);
+    account.try_serialize(&mut data).unwrap();
+    data.resize(len, 0);
+    program_test.add_account(
+        pubkey,
+        Account {
+            lamports,
+            data,
+            owner: charm::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// Listing of `master_mint` selling free editions.
+pub fn listing(master_mint: Pubkey, authority: Pubkey) -> Listing {
+    Listing {
+        master_mint,
+        authority,
+        soulbound: false,
+        pass: None,
+        window: None,
+        closed: false,
+        raffled: false,
+        referral_bps: None,
+        prices: vec![],
+        bump: 255,
+    }
+}
+
+/// Adds the config, platform fees being paid to `fee_wallet`.
+pub fn add_config(
+    program_test: &mut ProgramTest,
+    fee_wallet: Pubkey,
+    platform_fee_bps: u16,
+    referral_bps: u16,
+) {
+    let (address, bump) = Pubkey::find_program_address(&[b"config"], &charm::id());
+    let config = Config {
+        admins: vec![],
+        admin_threshold: 1,
+        reward_mint: Pubkey::new_unique(),
+        emission_cap: 0,
+        total_emitted: 0,
+        platform_fee_bps,
+        fee_wallet,
+        referral_bps,
+        bump,
+    };
+    add_charm_account(program_test, address, &config, Config::LEN, 10_000_000);
+}
+
+fn add_token_program_account(program_test: &mut ProgramTest, pubkey: Pubkey, data: Vec<u8>) {
+    program_test.add_account(
+        pubkey,
+        Account {
+            lamports: 10_000_000,
+            data,
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+pub fn add_mint(program_test: &mut ProgramTest, mint: Pubkey, supply: u64) {
+    let mut data = vec![0; Mint::LEN];
+    Mint {
+        supply,
+        decimals: 0,
+        is_initialized: true,
+        ..Mint::default()
+    }
+    .pack_into_slice(&mut data);
+    add_token_program_account(program_test, mint, data);
+}
+
+pub fn add_token_account(
+    program_test: &mut ProgramTest,
+    pubkey: Pubkey,
+    mint: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint,
+        owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    }
+    .pack_into_slice(&mut data);
+    add_token_program_account(program_test, pubkey, data);
+}
+
+pub async fn get_token_balance(context: &mut ProgramTestContext, pubkey: &Pubkey) -> u64 {
+    let account = get_account(context, pubkey).await.unwrap();
+    TokenAccount::unpack(&account.data).unwrap().amount
+}
+
+fn add_metadata_program_account(program_test: &mut ProgramTest, pubkey: Pubkey, data: Vec<u8>) {
+    program_test.add_account(
+        pubkey,
+        Account {
+            lamports: 10_000_000,
+            data,
+            owner: spl_token_metadata::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// Adds the metadata of `mint`, as token metadata would have created it.
+pub fn add_metadata(
+    program_test: &mut ProgramTest,
+    mint: Pubkey,
+    update_authority: Pubkey,
+    seller_fee_basis_points: u16,
+    creators: Option<Vec<Creator>>,
+) {
+    let mut data = Metadata {
+        key: Key::MetadataV1,
+        update_authority,
+        mint,
+        data: Data {
+            name: "Charm".to_string(),
+            symbol: "CHRM".to_string(),
+            uri: "https://example.com/charm.json".to_string(),
+            seller_fee_basis_points,
+            creators,
+        },
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+    }
+    .try_to_vec()
+    .unwrap();
+    data.resize(MAX_METADATA_LEN, 0);
+    add_metadata_program_account(program_test, metadata_address(&mint), data);
+}
+
+/// Adds the edition of `mint`, printed from `master_edition`.
+pub fn add_edition(program_test: &mut ProgramTest, mint: &Pubkey, master_edition: Pubkey) {
+    let data = Edition {
+        key: Key::EditionV1,
+        parent: master_edition,
+        edition: 1,
+    }
+    .try_to_vec()
+    .unwrap();
+    add_metadata_program_account(program_test, charm::utils::edition_address(mint), data);
+}
+
+/// Adds the master edition of `mint`, as token metadata would have created it.
+pub fn add_master_edition(
+    program_test: &mut ProgramTest,
+    mint: &Pubkey,
+    supply: u64,
+    max_supply: Option<u64>,
+) {
+    let data = MasterEditionV2 {
+        key: Key::MasterEditionV2,
+        supply,
+        max_supply,
+    }
+    .try_to_vec()
+    .unwrap();
+    add_metadata_program_account(program_test, charm::utils::edition_address(mint), data);
+}
+
+/// Adds the `PlatformTreasury` of `currency_mint`, with its vault unless the currency
+/// is native SOL, and returns both addresses.
+pub fn add_platform_treasury(
+    program_test: &mut ProgramTest,
+    currency_mint: Pubkey,
+) -> (Pubkey, Pubkey) {
+    let (treasury, bump) = Pubkey::find_program_address(
+        &[b"platform_treasury", currency_mint.as_ref()],
+        &charm::id(),
+    );
+    let platform_treasury = PlatformTreasury {
+        currency_mint,
+        fees: 0,
+        withdrawn: 0,
+        bump,
+    };
+    add_charm_account(
+        program_test,
+        treasury,
+        &platform_treasury,
+        PlatformTreasury::LEN,
+        10_000_000,
+    );
+    let vault =
+        Pubkey::find_program_address(&[b"platform_vault", treasury.as_ref()], &charm::id()).0;
+    if currency_mint != spl_token::native_mint::id() {
+        add_token_account(program_test, vault, currency_mint, treasury, 0);
+    }
+    (treasury, vault)
+}
+
+/// Native SOL treasuries a sale of a listing is split into.
+pub struct NativeTreasuries {
+    pub listing_treasury: Pubkey,
+    pub royalty_treasury: Pubkey,
+    pub royalty_vault: Pubkey,
+}
+
+/// Adds the native SOL `ListingTreasury` of `listing`, and the `RoyaltyTreasury`
+/// of the master edition of `master_mint` with its wrapped SOL vault.
+pub fn add_native_treasuries(
+    program_test: &mut ProgramTest,
+    listing: Pubkey,
+    master_mint: &Pubkey,
+    beneficiary: Pubkey,
+) -> NativeTreasuries {
+    let native_mint = spl_token::native_mint::id();
+    let (listing_treasury, bump) = Pubkey::find_program_address(
+        &[b"listing_treasury", listing.as_ref(), native_mint.as_ref()],
+        &charm::id(),
+    );
+    let treasury = ListingTreasury {
+        listing,
+        currency_mint: native_mint,
+        beneficiary,
+        gross_sales: 0,
+        fees: 0,
+        royalties: 0,
+        withdrawn: 0,
+        bump,
+    };
+    add_charm_account(
+        program_test,
+        listing_treasury,
+        &treasury,
+        ListingTreasury::LEN,
+        10_000_000,
+    );
+
+    let master_edition = charm::utils::edition_address(master_mint);
+    let (royalty_treasury, bump) = Pubkey::find_program_address(
+        &[
+            b"royalty_treasury",
+            master_edition.as_ref(),
+            native_mint.as_ref(),
+        ],
+        &charm::id(),
+    );
+    let treasury = RoyaltyTreasury {
+        master_edition,
+        currency_mint: native_mint,
+        total_received: 0,
+        claims: vec![],
+        bump,
+    };
+    add_charm_account(
+        program_test,
+        royalty_treasury,
+        &treasury,
+        RoyaltyTreasury::LEN,
+        10_000_000,
+    );
+    let royalty_vault =
+        Pubkey::find_program_address(&[b"royalty_vault", royalty_treasury.as_ref()], &charm::id())
+            .0;
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint: native_mint,
+        owner: royalty_treasury,
+        is_native: COption::Some(10_000_000),
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    }
+    .pack_into_slice(&mut data);
+    add_token_program_account(program_test, royalty_vault, data);
+
+    NativeTreasuries {
+        listing_treasury,
+        royalty_treasury,
+        royalty_vault,
+    }
+}
+
+pub fn charm_instruction(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: charm::id(),
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+pub async fn process(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+    signers: &[&Keypair],
+) -> transport::Result<()> {
+    let blockhash = context.banks_client.get_recent_blockhash().await.unwrap();
+    let mut signing_keypairs = vec![&context.payer];
+    signing_keypairs.extend_from_slice(signers);
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &signing_keypairs,
+        blockhash,
+    );
+
+    context.banks_client.process_transaction(tx).await
+}
+
+pub async fn get_account(context: &mut ProgramTestContext, pubkey: &Pubkey) -> Option<Account> {
+    context.banks_client.get_account(*pubkey).await.unwrap()
+}
+
+pub fn assert_charm_error(error: TransportError, code: ErrorCode) {
+    let expected = match ProgramError::from(code) {
+        ProgramError::Custom(expected) => expected,
+        _ => unreachable!(),
+    };
+    match error {
+        TransportError::TransactionError(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(actual),
+        )) => assert_eq!(actual, expected, "expected {:?}", code),
+        error => panic!("expected {:?}, got {:?}", code, error),
+    }
+}
+
+pub async fn get_lamports(context: &mut ProgramTestContext, pubkey: &Pubkey) -> u64 {
+    get_account(context, pubkey)
+        .await
+        .map_or(0, |account| account.lamports)
+}
+
+/// Creates a mint with no decimals and `token`, a token account of `owner` holding
+/// one token of it.
+pub async fn create_nft(
+    context: &mut ProgramTestContext,
+    mint: &Keypair,
+    mint_authority: &Keypair,
+    token: &Keypair,
+    owner: &Pubkey,
+) -> transport::Result<()> {
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+    let instructions = [
+        system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &mint_authority.pubkey(),
+            None,
+            0,
+        )
+        .unwrap(),
+        system_instruction::create_account(
+            &payer,
+            &token.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &token.pubkey(),
+            &mint.pubkey(),
+            owner,
+        )
+        .unwrap(),
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &token.pubkey(),
+            &mint_authority.pubkey(),
+            &[],
+            1,
+        )
+        .unwrap(),
+    ];
+    let blockhash = context.banks_client.get_recent_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer),
+        &[&context.payer, mint, token, mint_authority],
+        blockhash,
+    );
+
+    context.banks_client.process_transaction(tx).await
+}
+
+/// A master edition whose token is escrowed with the charm PDA.
+pub struct Master {
+    pub mint: Keypair,
+    pub token: Keypair,
+    pub metadata: Pubkey,
+    pub edition: Pubkey,
+}
+
+impl Master {
+    pub fn new() -> Self {
+        let mint = Keypair::new();
+        Master {
+            metadata: metadata_address(&mint.pubkey()),
+            edition: charm::utils::edition_address(&mint.pubkey()),
+            mint,
+            token: Keypair::new(),
+        }
+    }
+
+    /// Creates the master edition, `authority` being its update and mint authority.
+    pub async fn create(
+        &self,
+        context: &mut ProgramTestContext,
+        authority: &Keypair,
+        creators: Vec<Creator>,
+        seller_fee_basis_points: u16,
+        max_supply: Option<u64>,
+    ) -> transport::Result<()> {
+        create_nft(context, &self.mint, authority, &self.token, &charm_pda().0).await?;
+        let program_id = spl_token_metadata::id();
+        let mint = self.mint.pubkey();
+        let payer = context.payer.pubkey();
+        let instructions = vec![
+            spl_token_metadata::instruction::create_metadata_accounts(
+                program_id,
+                self.metadata,
+                mint,
+                authority.pubkey(),
+                payer,
+                authority.pubkey(),
+                "Charm".to_string(),
+                "CHRM".to_string(),
+                "https://example.com/charm.json".to_string(),
+                Some(creators),
+                seller_fee_basis_points,
+                true,
+                true,
+            ),
+            spl_token_metadata::instruction::create_master_edition(
+                program_id,
+                self.edition,
+                mint,
+                authority.pubkey(),
+                authority.pubkey(),
+                self.metadata,
+                payer,
+                max_supply,
+            ),
+        ];
+        for instruction in instructions {
+            process(context, instruction, &[authority]).await?;
+        }
+        Ok(())
+    }
+}
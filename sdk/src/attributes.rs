@@ -0,0 +1,46 @@
+//! Reads the attributes of a charm, following an edition to its master edition.
+
+use {
+    crate::{
+        pda::{attributes_address, edition_address},
+        resolver::{fetch, AccountSource, ResolveError},
+    },
+    anchor_lang::AccountDeserialize,
+    charm::state::CharmAttributes,
+    solana_program::borsh::try_from_slice_unchecked,
+    solana_sdk::pubkey::Pubkey,
+    spl_token_metadata::state::{Edition, Key},
+};
+
+/// Returns the master edition account that the attributes of `mint` are keyed by.
+pub fn master_edition_of(
+    source: &(impl AccountSource + ?Sized),
+    mint: &Pubkey,
+) -> Result<Pubkey, ResolveError> {
+    let edition_key = edition_address(mint);
+    let account = fetch(source, &edition_key)?;
+    match account.data.first() {
+        Some(key) if *key == Key::MasterEditionV2 as u8 => Ok(edition_key),
+        Some(key) if *key == Key::EditionV1 as u8 => {
+            let edition: Edition = try_from_slice_unchecked(&account.data)
+                .map_err(|_| ResolveError::InvalidAccountData(edition_key))?;
+            Ok(edition.parent)
+        }
+        _ => Err(ResolveError::InvalidAccountData(edition_key)),
+    }
+}
+
+/// Fetches the attributes of `mint`, a master edition or edition mint. Editions
+/// inherit the attributes of their master edition. `None` when none were set.
+pub fn fetch_attributes(
+    source: &(impl AccountSource + ?Sized),
+    mint: &Pubkey,
+) -> Result<Option<CharmAttributes>, ResolveError> {
+    let address = attributes_address(&master_edition_of(source, mint)?).0;
+    match source.get_account(&address)? {
+        Some(account) => CharmAttributes::try_deserialize(&mut &account.data[..])
+            .map(Some)
+            .map_err(|_| ResolveError::InvalidAccountData(address)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,177 @@
+//! Verifies the off-chain content of a charm against the hashes recorded on-chain.
+//!
+//! Content is read through a [`ContentFetcher`], implemented over HTTP by
+//! [`HttpFetcher`] and in memory by [`MemoryFetcher`].
+
+use {
+    crate::{
+        attributes::master_edition_of,
+        pda::{content_hash_address, metadata_address},
+        resolver::{fetch, AccountSource, ResolveError},
+    },
+    anchor_lang::AccountDeserialize,
+    charm::state::ContentHash,
+    solana_program::{borsh::try_from_slice_unchecked, hash::hash},
+    solana_sdk::pubkey::Pubkey,
+    spl_token_metadata::state::Metadata,
+    std::collections::HashMap,
+    thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("no content hash recorded for {0}")]
+    NoContentHash(Pubkey),
+
+    #[error("could not fetch {uri}: {message}")]
+    Fetch { uri: String, message: String },
+
+    #[error("metadata JSON at {0} is invalid")]
+    InvalidMetadataJson(String),
+
+    #[error(transparent)]
+    Resolve(#[from] ResolveError),
+}
+
+/// Read access to off-chain content by uri.
+pub trait ContentFetcher {
+    fn fetch(&self, uri: &str) -> Result<Vec<u8>, VerifyError>;
+}
+
+/// Fetches content with blocking HTTP GET requests.
+#[derive(Default)]
+pub struct HttpFetcher {
+    client: reqwest::blocking::Client,
+}
+
+impl HttpFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ContentFetcher for HttpFetcher {
+    fn fetch(&self, uri: &str) -> Result<Vec<u8>, VerifyError> {
+        let fetch_error = |err: reqwest::Error| VerifyError::Fetch {
+            uri: uri.to_owned(),
+            message: err.to_string(),
+        };
+        let response = self
+            .client
+            .get(uri)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map_err(fetch_error)?;
+        Ok(response.bytes().map_err(fetch_error)?.to_vec())
+    }
+}
+
+/// Content held in memory, keyed by uri.
+#[derive(Default)]
+pub struct MemoryFetcher {
+    pub contents: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, uri: &str, content: Vec<u8>) {
+        self.contents.insert(uri.to_owned(), content);
+    }
+}
+
+impl ContentFetcher for MemoryFetcher {
+    fn fetch(&self, uri: &str) -> Result<Vec<u8>, VerifyError> {
+        self.contents
+            .get(uri)
+            .cloned()
+            .ok_or_else(|| VerifyError::Fetch {
+                uri: uri.to_owned(),
+                message: "not found".to_owned(),
+            })
+    }
+}
+
+/// Outcome of comparing the content of a charm with its recorded hashes.
+#[derive(Debug, PartialEq)]
+pub struct ContentReport {
+    pub metadata_uri: String,
+    pub metadata_matches: bool,
+    /// `image` of the metadata JSON, when a media hash is recorded.
+    pub media_uri: Option<String>,
+    /// `None` when no media hash is recorded.
+    pub media_matches: Option<bool>,
+}
+
+impl ContentReport {
+    pub fn is_valid(&self) -> bool {
+        self.metadata_matches && self.media_matches.unwrap_or(true)
+    }
+}
+
+/// Fetches the metadata JSON at `metadata_uri` and the media of its `image`, if any,
+/// and returns their SHA-256 to record when the charm is created.
+pub fn hash_content(
+    fetcher: &(impl ContentFetcher + ?Sized),
+    metadata_uri: &str,
+) -> Result<([u8; 32], Option<[u8; 32]>), VerifyError> {
+    let json = fetcher.fetch(metadata_uri)?;
+    let document: serde_json::Value = serde_json::from_slice(&json)
+        .map_err(|_| VerifyError::InvalidMetadataJson(metadata_uri.to_owned()))?;
+    let media_hash = match document.get("image").and_then(|image| image.as_str()) {
+        Some(image) => Some(hash(&fetcher.fetch(image)?).to_bytes()),
+        None => None,
+    };
+    Ok((hash(&json).to_bytes(), media_hash))
+}
+
+/// Fetches the metadata JSON of `mint`, and its media when a media hash is recorded,
+/// and compares their SHA-256 with the content hash of the charm. Editions are
+/// checked against the hashes of their master edition.
+pub fn verify_content(
+    source: &(impl AccountSource + ?Sized),
+    fetcher: &(impl ContentFetcher + ?Sized),
+    mint: &Pubkey,
+) -> Result<ContentReport, VerifyError> {
+    let metadata_key = metadata_address(mint);
+    let metadata: Metadata = try_from_slice_unchecked(&fetch(source, &metadata_key)?.data)
+        .map_err(|_| ResolveError::InvalidAccountData(metadata_key))?;
+
+    let content_hash_key = content_hash_address(&master_edition_of(source, mint)?).0;
+    let content_hash = match source.get_account(&content_hash_key)? {
+        Some(account) => ContentHash::try_deserialize(&mut &account.data[..])
+            .map_err(|_| ResolveError::InvalidAccountData(content_hash_key))?,
+        None => return Err(VerifyError::NoContentHash(*mint)),
+    };
+
+    let metadata_uri = metadata.data.uri.trim_end_matches('\0').to_owned();
+    let json = fetcher.fetch(&metadata_uri)?;
+    let metadata_matches = hash(&json).to_bytes() == content_hash.metadata_hash;
+
+    let (media_uri, media_matches) = match content_hash.media_hash {
+        Some(media_hash) => {
+            let document: serde_json::Value = serde_json::from_slice(&json)
+                .map_err(|_| VerifyError::InvalidMetadataJson(metadata_uri.clone()))?;
+            match document.get("image").and_then(|image| image.as_str()) {
+                Some(image) => {
+                    let media = fetcher.fetch(image)?;
+                    (
+                        Some(image.to_owned()),
+                        Some(hash(&media).to_bytes() == media_hash),
+                    )
+                }
+                None => (None, Some(false)),
+            }
+        }
+        None => (None, None),
+    };
+
+    Ok(ContentReport {
+        metadata_uri,
+        metadata_matches,
+        media_uri,
+        media_matches,
+    })
+}
@@ -0,0 +1,25 @@
+//! Reads the engraving written by the holder of an edition.
+
+use {
+    crate::{
+        pda::engraving_address,
+        resolver::{AccountSource, ResolveError},
+    },
+    anchor_lang::AccountDeserialize,
+    charm::state::Engraving,
+    solana_sdk::pubkey::Pubkey,
+};
+
+/// Fetches the engraving of `edition_mint`, `None` when it was never engraved.
+pub fn fetch_engraving(
+    source: &(impl AccountSource + ?Sized),
+    edition_mint: &Pubkey,
+) -> Result<Option<Engraving>, ResolveError> {
+    let address = engraving_address(edition_mint).0;
+    match source.get_account(&address)? {
+        Some(account) => Engraving::try_deserialize(&mut &account.data[..])
+            .map(Some)
+            .map_err(|_| ResolveError::InvalidAccountData(address)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,38 @@
+//! Client side helpers for building Charm transactions.
+
+pub mod attributes;
+pub mod content;
+pub mod engraving;
+pub mod pda;
+pub mod proceeds;
+pub mod raffle;
+pub mod resolver;
+pub mod royalties;
+
+use {
+    anchor_lang::{InstructionData, ToAccountMetas},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+    },
+};
+
+/// Builds a charm instruction from Anchor's generated `accounts` and `instruction` structs.
+pub fn charm_instruction(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: charm::id(),
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Adds `cosigners` to an admin instruction, so that together with its admin signer
+/// they reach the admin threshold of the config. Each of them must sign the transaction.
+pub fn with_cosigners(mut instruction: Instruction, cosigners: &[Pubkey]) -> Instruction {
+    instruction.accounts.extend(
+        cosigners
+            .iter()
+            .map(|cosigner| AccountMeta::new_readonly(*cosigner, true)),
+    );
+    instruction
+}
@@ -0,0 +1,214 @@
+//! Program derived addresses used by charm and the token metadata program.
+
+use {
+    solana_sdk::pubkey::Pubkey,
+    spl_token_metadata::state::{EDITION, EDITION_MARKER_BIT_SIZE, PREFIX},
+};
+
+/// Seed of the PDA that holds escrowed master edition tokens and mints faucet tokens.
+pub const CHARM_PDA_SEED: &[u8] = b"charmpda";
+
+pub fn charm_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CHARM_PDA_SEED], &charm::id())
+}
+
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    let program_key = spl_token_metadata::id();
+    Pubkey::find_program_address(
+        &[PREFIX.as_bytes(), program_key.as_ref(), mint.as_ref()],
+        &program_key,
+    )
+    .0
+}
+
+/// Address of the master edition or edition account of `mint`.
+pub fn edition_address(mint: &Pubkey) -> Pubkey {
+    let program_key = spl_token_metadata::id();
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            program_key.as_ref(),
+            mint.as_ref(),
+            EDITION.as_bytes(),
+        ],
+        &program_key,
+    )
+    .0
+}
+
+/// Address of the edition marker that records whether `edition` of `master_mint` was printed.
+pub fn edition_marker_address(master_mint: &Pubkey, edition: u64) -> Pubkey {
+    let program_key = spl_token_metadata::id();
+    let marker = (edition / EDITION_MARKER_BIT_SIZE).to_string();
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            program_key.as_ref(),
+            master_mint.as_ref(),
+            EDITION.as_bytes(),
+            marker.as_bytes(),
+        ],
+        &program_key,
+    )
+    .0
+}
+
+pub fn config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &charm::id())
+}
+
+pub fn profile_address(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"profile", authority.as_ref()], &charm::id())
+}
+
+/// Address of page `page` of the collection index of the profile of `authority`.
+pub fn profile_page_address(authority: &Pubkey, page: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"profile_page", authority.as_ref(), &page.to_le_bytes()],
+        &charm::id(),
+    )
+}
+
+/// Address of the collection created as number `index` of `profile`.
+pub fn collection_address(profile: &Pubkey, index: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"collection", profile.as_ref(), &index.to_le_bytes()],
+        &charm::id(),
+    )
+}
+
+pub fn membership_address(collection: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"membership", collection.as_ref(), mint.as_ref()],
+        &charm::id(),
+    )
+}
+
+/// Address of the attributes of a charm, shared by its master edition and every edition.
+pub fn attributes_address(master_edition: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"attributes", master_edition.as_ref()], &charm::id())
+}
+
+/// Address of the content hashes of a charm, shared by its master edition and every edition.
+pub fn content_hash_address(master_edition: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"content_hash", master_edition.as_ref()], &charm::id())
+}
+
+pub fn bracelet_address(parent_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"bracelet", parent_mint.as_ref()], &charm::id())
+}
+
+/// Address of the token account holding `charm_mint` while it is attached to `bracelet`.
+pub fn bracelet_escrow_address(bracelet: &Pubkey, charm_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"escrow", bracelet.as_ref(), charm_mint.as_ref()],
+        &charm::id(),
+    )
+}
+
+pub fn engraving_address(edition_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"engraving", edition_mint.as_ref()], &charm::id())
+}
+
+pub fn listing_address(master_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"listing", master_mint.as_ref()], &charm::id())
+}
+
+pub fn stake_address(edition_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stake", edition_mint.as_ref()], &charm::id())
+}
+
+/// Address of the token account holding a staked edition.
+pub fn stake_vault_address(edition_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stake_vault", edition_mint.as_ref()], &charm::id())
+}
+
+pub fn redemption_address(edition_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"redemption", edition_mint.as_ref()], &charm::id())
+}
+
+pub fn expiry_address(edition_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"expiry", edition_mint.as_ref()], &charm::id())
+}
+
+pub fn soulbound_edition_address(edition_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"soulbound", edition_mint.as_ref()], &charm::id())
+}
+
+pub fn raffle_address(master_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"raffle", master_mint.as_ref()], &charm::id())
+}
+
+pub fn raffle_entry_address(raffle: &Pubkey, entrant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"raffle_entry", raffle.as_ref(), entrant.as_ref()],
+        &charm::id(),
+    )
+}
+
+pub fn bundle_address(authority: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"bundle", authority.as_ref(), &id.to_le_bytes()],
+        &charm::id(),
+    )
+}
+
+pub fn offer_address(edition_mint: &Pubkey, bidder: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"offer", edition_mint.as_ref(), bidder.as_ref()],
+        &charm::id(),
+    )
+}
+
+/// Address of the token account holding the tokens offered by `offer`.
+pub fn offer_escrow_address(offer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"offer_escrow", offer.as_ref()], &charm::id())
+}
+
+/// Address of the royalties of a charm paid in `currency_mint`.
+pub fn royalty_treasury_address(master_edition: &Pubkey, currency_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"royalty_treasury",
+            master_edition.as_ref(),
+            currency_mint.as_ref(),
+        ],
+        &charm::id(),
+    )
+}
+
+pub fn royalty_vault_address(treasury: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"royalty_vault", treasury.as_ref()], &charm::id())
+}
+
+/// Address of the proceeds of a listing paid in `currency_mint`.
+pub fn listing_treasury_address(listing: &Pubkey, currency_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"listing_treasury",
+            listing.as_ref(),
+            currency_mint.as_ref(),
+        ],
+        &charm::id(),
+    )
+}
+
+pub fn listing_vault_address(treasury: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"listing_vault", treasury.as_ref()], &charm::id())
+}
+
+/// Address of the platform fees paid in `currency_mint`.
+pub fn platform_treasury_address(currency_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"platform_treasury", currency_mint.as_ref()],
+        &charm::id(),
+    )
+}
+
+pub fn platform_vault_address(treasury: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"platform_vault", treasury.as_ref()], &charm::id())
+}
+
+pub fn referrer_stats_address(referrer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"referrer_stats", referrer.as_ref()], &charm::id())
+}
@@ -0,0 +1,90 @@
+//! Reads listing and platform treasuries and builds withdrawals of their proceeds.
+
+use {
+    crate::{
+        charm_instruction,
+        pda::{
+            config_address, listing_address, listing_treasury_address, listing_vault_address,
+            platform_treasury_address, platform_vault_address,
+        },
+        resolver::{fetch, payment_account, AccountSource, ResolveError},
+    },
+    anchor_lang::AccountDeserialize,
+    charm::state::{ListingTreasury, PlatformTreasury},
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_program, sysvar},
+};
+
+pub fn fetch_listing_treasury(
+    source: &(impl AccountSource + ?Sized),
+    master_mint: &Pubkey,
+    currency_mint: &Pubkey,
+) -> Result<ListingTreasury, ResolveError> {
+    let address = listing_treasury_address(&listing_address(master_mint).0, currency_mint).0;
+    ListingTreasury::try_deserialize(&mut &fetch(source, &address)?.data[..])
+        .map_err(|_| ResolveError::InvalidAccountData(address))
+}
+
+/// Withdraws `amount` of the proceeds of `treasury` to its beneficiary, in its wallet
+/// for native SOL and otherwise its associated token account.
+pub fn withdraw_proceeds(treasury: &ListingTreasury, amount: u64) -> Instruction {
+    let treasury_key = listing_treasury_address(&treasury.listing, &treasury.currency_mint).0;
+    charm_instruction(
+        charm::accounts::WithdrawProceeds {
+            beneficiary: treasury.beneficiary,
+            treasury: treasury_key,
+            vault: listing_vault_address(&treasury_key).0,
+            destination: payment_account(&treasury.beneficiary, &treasury.currency_mint),
+            token_program: spl_token::id(),
+        },
+        charm::instruction::WithdrawProceeds { amount },
+    )
+}
+
+pub fn fetch_platform_treasury(
+    source: &(impl AccountSource + ?Sized),
+    currency_mint: &Pubkey,
+) -> Result<PlatformTreasury, ResolveError> {
+    let address = platform_treasury_address(currency_mint).0;
+    PlatformTreasury::try_deserialize(&mut &fetch(source, &address)?.data[..])
+        .map_err(|_| ResolveError::InvalidAccountData(address))
+}
+
+/// Creates the treasury holding the platform fees paid in `currency_mint`, which
+/// must exist before editions can be sold in that currency.
+pub fn create_platform_treasury(payer: &Pubkey, currency_mint: &Pubkey) -> Instruction {
+    let (treasury, bump) = platform_treasury_address(currency_mint);
+    let (vault, vault_bump) = platform_vault_address(&treasury);
+    charm_instruction(
+        charm::accounts::CreatePlatformTreasury {
+            payer: *payer,
+            currency_mint: *currency_mint,
+            treasury,
+            vault,
+            token_program: spl_token::id(),
+            system_program: system_program::id(),
+            rent: sysvar::rent::id(),
+        },
+        charm::instruction::CreatePlatformTreasury { bump, vault_bump },
+    )
+}
+
+/// Withdraws `amount` of the platform fees of `treasury` to `fee_wallet`, the config
+/// fee wallet, in the wallet for native SOL and otherwise its associated token account.
+pub fn withdraw_platform_fees(
+    treasury: &PlatformTreasury,
+    fee_wallet: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let treasury_key = platform_treasury_address(&treasury.currency_mint).0;
+    charm_instruction(
+        charm::accounts::WithdrawPlatformFees {
+            fee_wallet: *fee_wallet,
+            config: config_address().0,
+            treasury: treasury_key,
+            vault: platform_vault_address(&treasury_key).0,
+            destination: payment_account(fee_wallet, &treasury.currency_mint),
+            token_program: spl_token::id(),
+        },
+        charm::instruction::WithdrawPlatformFees { amount },
+    )
+}
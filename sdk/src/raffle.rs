@@ -0,0 +1,62 @@
+//! Reads raffles and tells entrants whether they won.
+
+use {
+    crate::{
+        pda::{raffle_address, raffle_entry_address},
+        resolver::{fetch, AccountSource, ResolveError},
+    },
+    anchor_lang::AccountDeserialize,
+    charm::state::{Raffle, RaffleEntry},
+    solana_sdk::pubkey::Pubkey,
+};
+
+/// Where an entry stands in its raffle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntryOutcome {
+    /// The raffle has not been revealed yet.
+    Pending,
+    /// The entrant can buy an edition.
+    Won,
+    /// The entrant already bought its edition.
+    Claimed,
+    /// The entrant can claim a refund.
+    Lost,
+    /// The raffle was not revealed before its deadline, the entrant can claim a refund.
+    Expired,
+}
+
+pub fn fetch_raffle(
+    source: &(impl AccountSource + ?Sized),
+    master_mint: &Pubkey,
+) -> Result<Raffle, ResolveError> {
+    let address = raffle_address(master_mint).0;
+    Raffle::try_deserialize(&mut &fetch(source, &address)?.data[..])
+        .map_err(|_| ResolveError::InvalidAccountData(address))
+}
+
+/// Fetches the entry of `entrant` in the raffle of `master_mint`, `None` when it never
+/// entered or was refunded.
+pub fn fetch_entry(
+    source: &(impl AccountSource + ?Sized),
+    master_mint: &Pubkey,
+    entrant: &Pubkey,
+) -> Result<Option<RaffleEntry>, ResolveError> {
+    let address = raffle_entry_address(&raffle_address(master_mint).0, entrant).0;
+    match source.get_account(&address)? {
+        Some(account) => RaffleEntry::try_deserialize(&mut &account.data[..])
+            .map(Some)
+            .map_err(|_| ResolveError::InvalidAccountData(address)),
+        None => Ok(None),
+    }
+}
+
+/// Outcome of `entry` at the unix timestamp `now`.
+pub fn entry_outcome(raffle: &Raffle, entry: &RaffleEntry, now: i64) -> EntryOutcome {
+    match raffle.is_winner(entry.index) {
+        None if now >= raffle.reveal_deadline => EntryOutcome::Expired,
+        None => EntryOutcome::Pending,
+        Some(true) if entry.claimed => EntryOutcome::Claimed,
+        Some(true) => EntryOutcome::Won,
+        Some(false) => EntryOutcome::Lost,
+    }
+}
@@ -0,0 +1,424 @@
+//! Derives every account of the charm `buy` instruction from the master mint.
+//!
+//! Master edition state is read through an [`AccountSource`], which is implemented
+//! for [`RpcClient`] and for the in-memory [`MemoryAccountSource`] used in tests.
+
+use {
+    crate::{
+        charm_instruction,
+        pda::{
+            charm_pda, config_address, edition_address, edition_marker_address, expiry_address,
+            listing_address, listing_treasury_address, listing_vault_address, metadata_address,
+            platform_treasury_address, platform_vault_address, raffle_address,
+            raffle_entry_address, referrer_stats_address, royalty_treasury_address,
+            royalty_vault_address, soulbound_edition_address,
+        },
+    },
+    anchor_lang::AccountDeserialize,
+    charm::state::{Listing, ListingPrice},
+    solana_account_decoder::UiAccountEncoding,
+    solana_client::{
+        client_error::ClientError,
+        rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+    },
+    solana_program::{borsh::try_from_slice_unchecked, program_pack::Pack},
+    solana_sdk::{
+        account::Account,
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        rent::Rent,
+        signature::{Keypair, Signer},
+        system_instruction::create_account,
+        system_program, sysvar,
+    },
+    spl_associated_token_account::{create_associated_token_account, get_associated_token_address},
+    spl_token::{
+        instruction::{initialize_mint, mint_to},
+        state::{Account as TokenAccount, Mint},
+    },
+    spl_token_metadata::state::{Key, MasterEditionV2, Metadata},
+    std::collections::HashMap,
+    thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum ResolveError {
+    #[error("account {0} not found")]
+    AccountNotFound(Pubkey),
+
+    #[error("account {0} could not be deserialized")]
+    InvalidAccountData(Pubkey),
+
+    #[error("account {0} is not a master edition")]
+    NotMasterEdition(Pubkey),
+
+    #[error("edition {edition} is outside the supply of the master edition (max {max_supply:?})")]
+    EditionOutOfRange {
+        edition: u64,
+        max_supply: Option<u64>,
+    },
+
+    #[error("listing {0} is closed")]
+    ListingClosed(Pubkey),
+
+    #[error("listing {listing} does not accept {currency:?}, it accepts {accepted:?}")]
+    CurrencyNotAccepted {
+        listing: Pubkey,
+        currency: Option<Pubkey>,
+        accepted: Vec<Pubkey>,
+    },
+
+    #[error("no token account holds the token of {0}")]
+    MasterTokenNotFound(Pubkey),
+
+    #[error("master edition token is held by {0} instead of the charm PDA")]
+    MasterTokenNotEscrowed(Pubkey),
+
+    #[error(transparent)]
+    Client(Box<ClientError>),
+
+    #[error(transparent)]
+    Program(#[from] solana_sdk::program_error::ProgramError),
+}
+
+impl From<ClientError> for ResolveError {
+    fn from(err: ClientError) -> Self {
+        ResolveError::Client(Box::new(err))
+    }
+}
+
+/// Read access to on-chain accounts.
+pub trait AccountSource {
+    fn get_account(&self, pubkey: &Pubkey) -> Result<Option<Account>, ResolveError>;
+
+    /// Every token account of `mint`, with its address.
+    fn get_token_accounts_by_mint(
+        &self,
+        mint: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Account)>, ResolveError>;
+
+    fn minimum_balance_for_rent_exemption(&self, data_len: usize) -> Result<u64, ResolveError> {
+        Ok(Rent::default().minimum_balance(data_len))
+    }
+}
+
+impl AccountSource for RpcClient {
+    fn get_account(&self, pubkey: &Pubkey) -> Result<Option<Account>, ResolveError> {
+        Ok(self
+            .get_account_with_commitment(pubkey, self.commitment())?
+            .value)
+    }
+
+    fn get_token_accounts_by_mint(
+        &self,
+        mint: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Account)>, ResolveError> {
+        Ok(self.get_program_accounts_with_config(
+            &spl_token::id(),
+            RpcProgramAccountsConfig {
+                filters: Some(vec![
+                    RpcFilterType::DataSize(TokenAccount::LEN as u64),
+                    RpcFilterType::Memcmp(Memcmp {
+                        offset: 0,
+                        bytes: MemcmpEncodedBytes::Binary(mint.to_string()),
+                        encoding: None,
+                    }),
+                ]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )?)
+    }
+
+    fn minimum_balance_for_rent_exemption(&self, data_len: usize) -> Result<u64, ResolveError> {
+        Ok(self.get_minimum_balance_for_rent_exemption(data_len)?)
+    }
+}
+
+/// Accounts held in memory, keyed by address.
+#[derive(Default)]
+pub struct MemoryAccountSource {
+    pub accounts: HashMap<Pubkey, Account>,
+}
+
+impl MemoryAccountSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, pubkey: Pubkey, account: Account) {
+        self.accounts.insert(pubkey, account);
+    }
+}
+
+impl AccountSource for MemoryAccountSource {
+    fn get_account(&self, pubkey: &Pubkey) -> Result<Option<Account>, ResolveError> {
+        Ok(self.accounts.get(pubkey).cloned())
+    }
+
+    fn get_token_accounts_by_mint(
+        &self,
+        mint: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Account)>, ResolveError> {
+        Ok(self
+            .accounts
+            .iter()
+            .filter(|(_, account)| {
+                account.owner == spl_token::id()
+                    && account.data.len() == TokenAccount::LEN
+                    && account.data[..32] == mint.as_ref()[..]
+            })
+            .map(|(key, account)| (*key, account.clone()))
+            .collect())
+    }
+}
+
+/// A `buy` instruction with every account derived, plus what is needed to send it.
+pub struct ResolvedBuy {
+    /// Mint of the new edition. It must sign the transaction.
+    pub new_mint: Keypair,
+    pub edition: u64,
+    pub new_metadata: Pubkey,
+    pub new_edition: Pubkey,
+    /// The edition is held in `new_token_account` by its soulbound record until revoked.
+    pub soulbound: bool,
+    /// The edition is a pass, valid until the timestamp in its `Expiry` account.
+    pub pass: bool,
+    /// Currency and price the edition is paid with, `None` when the listing is free.
+    pub payment: Option<ListingPrice>,
+    /// Associated token account of the buyer, or of the soulbound record, receiving the
+    /// edition token.
+    pub new_token_account: Pubkey,
+    /// Creates `new_mint` and mints its single token to `new_token_account`.
+    pub setup_instructions: Vec<Instruction>,
+    pub instruction: Instruction,
+}
+
+impl ResolvedBuy {
+    /// Credits the purchase to `referrer` in its referral stats. On paid listings the
+    /// referral is paid to the referrer, in its associated token account unless the
+    /// edition is paid in SOL.
+    pub fn with_referrer(mut self, referrer: &Pubkey) -> Self {
+        let referrer_account = match self.payment {
+            Some(price) => AccountMeta::new(payment_account(referrer, &price.mint), false),
+            None => AccountMeta::new_readonly(*referrer, false),
+        };
+        self.instruction.accounts.extend(vec![
+            referrer_account,
+            AccountMeta::new(referrer_stats_address(referrer).0, false),
+        ]);
+        self
+    }
+
+    /// Setup instructions followed by the `buy` instruction, in transaction order.
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let mut instructions = self.setup_instructions.clone();
+        instructions.push(self.instruction.clone());
+        instructions
+    }
+}
+
+/// Where `owner` pays or is paid in `currency_mint`: the wallet itself for native SOL,
+/// otherwise its associated token account.
+pub fn payment_account(owner: &Pubkey, currency_mint: &Pubkey) -> Pubkey {
+    if *currency_mint == spl_token::native_mint::id() {
+        *owner
+    } else {
+        get_associated_token_address(owner, currency_mint)
+    }
+}
+
+pub(crate) fn fetch(
+    source: &(impl AccountSource + ?Sized),
+    pubkey: &Pubkey,
+) -> Result<Account, ResolveError> {
+    source
+        .get_account(pubkey)?
+        .ok_or(ResolveError::AccountNotFound(*pubkey))
+}
+
+/// Finds the token account holding the single token of `mint`.
+pub fn find_holding_account(
+    source: &(impl AccountSource + ?Sized),
+    mint: &Pubkey,
+) -> Result<(Pubkey, TokenAccount), ResolveError> {
+    source
+        .get_token_accounts_by_mint(mint)?
+        .into_iter()
+        .filter_map(|(key, account)| {
+            TokenAccount::unpack(&account.data)
+                .ok()
+                .map(|token| (key, token))
+        })
+        .find(|(_, token)| token.amount == 1)
+        .ok_or(ResolveError::MasterTokenNotFound(*mint))
+}
+
+/// Resolves the `buy` of an edition of `master_mint` by `buyer`, who pays for and
+/// receives the edition. Without an explicit `edition`, the next one after the
+/// current supply is printed. Listings with prices are paid in `currency_mint`,
+/// which must be one of the currencies they accept.
+pub fn resolve_buy(
+    source: &(impl AccountSource + ?Sized),
+    master_mint: &Pubkey,
+    buyer: &Pubkey,
+    edition: Option<u64>,
+    currency_mint: Option<&Pubkey>,
+) -> Result<ResolvedBuy, ResolveError> {
+    let master_metadata_key = metadata_address(master_mint);
+    let master_edition_key = edition_address(master_mint);
+
+    let master_metadata: Metadata =
+        try_from_slice_unchecked(&fetch(source, &master_metadata_key)?.data)
+            .map_err(|_| ResolveError::InvalidAccountData(master_metadata_key))?;
+    let master_edition_account = fetch(source, &master_edition_key)?;
+    if master_edition_account.data.first() != Some(&(Key::MasterEditionV2 as u8)) {
+        return Err(ResolveError::NotMasterEdition(master_edition_key));
+    }
+    let master_edition: MasterEditionV2 = try_from_slice_unchecked(&master_edition_account.data)
+        .map_err(|_| ResolveError::InvalidAccountData(master_edition_key))?;
+
+    let edition = edition.unwrap_or(master_edition.supply + 1);
+    let exceeds_supply =
+        matches!(master_edition.max_supply, Some(max_supply) if edition > max_supply);
+    if edition == 0 || exceeds_supply {
+        return Err(ResolveError::EditionOutOfRange {
+            edition,
+            max_supply: master_edition.max_supply,
+        });
+    }
+
+    let listing_key = listing_address(master_mint).0;
+    let listing = Listing::try_deserialize(&mut &fetch(source, &listing_key)?.data[..])
+        .map_err(|_| ResolveError::InvalidAccountData(listing_key))?;
+    if listing.closed {
+        return Err(ResolveError::ListingClosed(listing_key));
+    }
+
+    // Raffle winners already paid the raffle price when entering.
+    let payment = if listing.prices.is_empty() || listing.raffled {
+        None
+    } else {
+        let price = currency_mint.and_then(|mint| listing.price_in(mint));
+        Some(price.ok_or_else(|| ResolveError::CurrencyNotAccepted {
+            listing: listing_key,
+            currency: currency_mint.copied(),
+            accepted: listing.prices.iter().map(|price| price.mint).collect(),
+        })?)
+    };
+
+    // Free editions never read the payment accounts, the buyer stands in for them.
+    let payment_source = match payment {
+        Some(price) if price.amount > 0 => payment_account(buyer, &price.mint),
+        _ => *buyer,
+    };
+    let currency = payment.map_or_else(spl_token::native_mint::id, |price| price.mint);
+    let listing_treasury = listing_treasury_address(&listing_key, &currency).0;
+    let platform_treasury = platform_treasury_address(&currency).0;
+    let royalty_treasury = royalty_treasury_address(&master_edition_key, &currency).0;
+
+    let (token_account, master_token) = find_holding_account(source, master_mint)?;
+    let (pda, pda_bump) = charm_pda();
+    if master_token.owner != pda {
+        return Err(ResolveError::MasterTokenNotEscrowed(master_token.owner));
+    }
+
+    let new_mint = Keypair::new();
+    let new_mint_key = new_mint.pubkey();
+    let new_metadata = metadata_address(&new_mint_key);
+    let new_edition = edition_address(&new_mint_key);
+    // Soulbound editions are held by their soulbound record instead of the buyer.
+    let soulbound_edition = soulbound_edition_address(&new_mint_key).0;
+    let edition_owner = if listing.soulbound {
+        soulbound_edition
+    } else {
+        *buyer
+    };
+    let new_token_account = get_associated_token_address(&edition_owner, &new_mint_key);
+
+    let setup_instructions = vec![
+        create_account(
+            buyer,
+            &new_mint_key,
+            source.minimum_balance_for_rent_exemption(Mint::LEN)?,
+            Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        initialize_mint(&spl_token::id(), &new_mint_key, buyer, Some(buyer), 0)?,
+        create_associated_token_account(buyer, &edition_owner, &new_mint_key),
+        mint_to(
+            &spl_token::id(),
+            &new_mint_key,
+            &new_token_account,
+            buyer,
+            &[buyer],
+            1,
+        )?,
+    ];
+
+    let mut instruction = charm_instruction(
+        charm::accounts::Buy {
+            payer: *buyer,
+            metadata_program: spl_token_metadata::id(),
+            new_metadata_account: new_metadata,
+            new_edition_account: new_edition,
+            master_edition_account: master_edition_key,
+            new_mint_account: new_mint_key,
+            new_mint_authority: *buyer,
+            token_account_owner: pda,
+            token_account,
+            new_metadata_update_authority: master_metadata.update_authority,
+            metadata: master_metadata_key,
+            metadata_mint: *master_mint,
+            system_program: system_program::id(),
+            rent_program: sysvar::rent::id(),
+            edition_pda: edition_marker_address(master_mint, edition),
+            token_program: spl_token::id(),
+            listing: listing_key,
+            new_token_account,
+            pda,
+            expiry: expiry_address(&new_mint_key).0,
+            soulbound_edition,
+            config: config_address().0,
+            payment_source,
+            listing_treasury,
+            listing_vault: listing_vault_address(&listing_treasury).0,
+            platform_treasury,
+            platform_vault: platform_vault_address(&platform_treasury).0,
+            royalty_treasury,
+            royalty_vault: royalty_vault_address(&royalty_treasury).0,
+        },
+        charm::instruction::Buy {
+            edition,
+            pda_nonce: pda_bump,
+            currency_mint: payment.map(|price| price.mint),
+        },
+    );
+
+    if listing.raffled {
+        // Only a winning entry of the buyer can buy.
+        let raffle = raffle_address(master_mint).0;
+        instruction.accounts.extend(vec![
+            AccountMeta::new_readonly(raffle, false),
+            AccountMeta::new(raffle_entry_address(&raffle, buyer).0, false),
+        ]);
+    }
+
+    Ok(ResolvedBuy {
+        new_mint,
+        edition,
+        new_metadata,
+        new_edition,
+        soulbound: listing.soulbound,
+        pass: listing.pass.is_some(),
+        payment,
+        new_token_account,
+        setup_instructions,
+        instruction,
+    })
+}
@@ -0,0 +1,38 @@
+//! Reads royalty treasuries and what each creator can still claim from them.
+
+use {
+    crate::{
+        pda::{edition_address, metadata_address, royalty_treasury_address},
+        resolver::{fetch, AccountSource, ResolveError},
+    },
+    anchor_lang::AccountDeserialize,
+    charm::state::RoyaltyTreasury,
+    solana_program::borsh::try_from_slice_unchecked,
+    solana_sdk::pubkey::Pubkey,
+    spl_token_metadata::state::Metadata,
+};
+
+pub fn fetch_treasury(
+    source: &(impl AccountSource + ?Sized),
+    master_mint: &Pubkey,
+    currency_mint: &Pubkey,
+) -> Result<RoyaltyTreasury, ResolveError> {
+    let address = royalty_treasury_address(&edition_address(master_mint), currency_mint).0;
+    RoyaltyTreasury::try_deserialize(&mut &fetch(source, &address)?.data[..])
+        .map_err(|_| ResolveError::InvalidAccountData(address))
+}
+
+/// Royalties `creator` can claim from the treasury of `master_mint` in `currency_mint`,
+/// `None` when it is not a verified creator of the master metadata.
+pub fn claimable_royalties(
+    source: &(impl AccountSource + ?Sized),
+    master_mint: &Pubkey,
+    currency_mint: &Pubkey,
+    creator: &Pubkey,
+) -> Result<Option<u64>, ResolveError> {
+    let treasury = fetch_treasury(source, master_mint, currency_mint)?;
+    let metadata_key = metadata_address(master_mint);
+    let metadata: Metadata = try_from_slice_unchecked(&fetch(source, &metadata_key)?.data)
+        .map_err(|_| ResolveError::InvalidAccountData(metadata_key))?;
+    Ok(charm::utils::claimable_royalties(&treasury, &metadata.data, creator).ok())
+}
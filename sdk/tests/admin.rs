@@ -0,0 +1,91 @@
+use {
+    charm::{state::Config, utils::assert_upgrade_authority},
+    charm_sdk::{charm_instruction, pda::config_address, with_cosigners},
+    solana_sdk::{
+        account_info::AccountInfo,
+        bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+        pubkey::Pubkey,
+    },
+};
+
+fn config(admins: Vec<Pubkey>, admin_threshold: u8) -> Config {
+    Config {
+        admins,
+        admin_threshold,
+        reward_mint: Pubkey::default(),
+        emission_cap: 0,
+        total_emitted: 0,
+        platform_fee_bps: 0,
+        fee_wallet: Pubkey::default(),
+        referral_bps: 0,
+        bump: 255,
+    }
+}
+
+#[test]
+fn counts_each_admin_once() {
+    let (alice, bob, carol) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let config = config(vec![alice, bob, carol], 2);
+
+    assert_eq!(config.admin_approvals(&[alice, alice]), 1);
+    assert_eq!(config.admin_approvals(&[alice, Pubkey::new_unique()]), 1);
+    assert_eq!(config.admin_approvals(&[carol, alice, bob]), 3);
+}
+
+#[test]
+fn cosigners_sign_after_the_instruction_accounts() {
+    let admin = Pubkey::new_unique();
+    let cosigners = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let instruction = with_cosigners(
+        charm_instruction(
+            charm::accounts::SetPlatformFee {
+                config: config_address().0,
+                admin,
+            },
+            charm::instruction::SetPlatformFee {
+                platform_fee_bps: 250,
+                fee_wallet: Pubkey::new_unique(),
+            },
+        ),
+        &cosigners,
+    );
+
+    let accounts = &instruction.accounts[2..];
+    assert_eq!(accounts.len(), 2);
+    assert!(accounts
+        .iter()
+        .zip(&cosigners)
+        .all(|(meta, cosigner)| meta.pubkey == *cosigner && meta.is_signer && !meta.is_writable));
+}
+
+#[test]
+fn only_the_upgrade_authority_initializes() {
+    let authority = Pubkey::new_unique();
+    let (address, _) =
+        Pubkey::find_program_address(&[charm::ID.as_ref()], &bpf_loader_upgradeable::id());
+    let mut data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 1,
+        upgrade_authority_address: Some(authority),
+    })
+    .unwrap();
+    let mut lamports = 0;
+    let loader = bpf_loader_upgradeable::id();
+    let program_data = AccountInfo::new(
+        &address,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &loader,
+        false,
+        0,
+    );
+
+    assert!(assert_upgrade_authority(&program_data, &authority, &charm::ID).is_ok());
+    assert!(assert_upgrade_authority(&program_data, &Pubkey::new_unique(), &charm::ID).is_err());
+    assert!(assert_upgrade_authority(&program_data, &authority, &Pubkey::new_unique()).is_err());
+}
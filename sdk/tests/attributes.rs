@@ -0,0 +1,117 @@
+mod common;
+
+use {
+    anchor_lang::AccountSerialize,
+    borsh::BorshSerialize,
+    charm::state::{Attribute, AttributeValue, CharmAttributes},
+    charm_sdk::{
+        attributes::fetch_attributes,
+        pda::{attributes_address, edition_address},
+        resolver::{MemoryAccountSource, ResolveError},
+    },
+    common::account,
+    solana_sdk::pubkey::Pubkey,
+    spl_token_metadata::state::{Edition, Key, MasterEditionV2},
+};
+
+fn attributes(master_edition: Pubkey) -> CharmAttributes {
+    CharmAttributes {
+        master_edition,
+        attributes: vec![
+            Attribute {
+                key: "metal".to_owned(),
+                value: AttributeValue::String("silver".to_owned()),
+            },
+            Attribute {
+                key: "weight".to_owned(),
+                value: AttributeValue::Integer(12),
+            },
+            Attribute {
+                key: "engraved".to_owned(),
+                value: AttributeValue::Boolean(false),
+            },
+        ],
+        bump: 255,
+    }
+}
+
+/// A master edition with attributes and one edition printed from it.
+fn source() -> (MemoryAccountSource, Pubkey, Pubkey) {
+    let master_mint = Pubkey::new_unique();
+    let edition_mint = Pubkey::new_unique();
+    let master_edition = edition_address(&master_mint);
+    let mut source = MemoryAccountSource::new();
+
+    let master = MasterEditionV2 {
+        key: Key::MasterEditionV2,
+        supply: 1,
+        max_supply: None,
+    };
+    source.insert(
+        master_edition,
+        account(spl_token_metadata::id(), master.try_to_vec().unwrap()),
+    );
+    let edition = Edition {
+        key: Key::EditionV1,
+        parent: master_edition,
+        edition: 1,
+    };
+    source.insert(
+        edition_address(&edition_mint),
+        account(spl_token_metadata::id(), edition.try_to_vec().unwrap()),
+    );
+
+    let mut data = Vec::new();
+    attributes(master_edition).try_serialize(&mut data).unwrap();
+    source.insert(
+        attributes_address(&master_edition).0,
+        account(charm::id(), data),
+    );
+
+    (source, master_mint, edition_mint)
+}
+
+#[test]
+fn reads_master_attributes() {
+    let (source, master_mint, _) = source();
+
+    let record = fetch_attributes(&source, &master_mint).unwrap().unwrap();
+
+    assert_eq!(record.attributes.len(), 3);
+    assert_eq!(record.get("weight"), Some(&AttributeValue::Integer(12)));
+    assert_eq!(record.get("size"), None);
+}
+
+#[test]
+fn editions_inherit_master_attributes() {
+    let (source, master_mint, edition_mint) = source();
+
+    let record = fetch_attributes(&source, &edition_mint).unwrap().unwrap();
+
+    assert_eq!(record.master_edition, edition_address(&master_mint));
+    assert_eq!(
+        record.get("metal"),
+        Some(&AttributeValue::String("silver".to_owned()))
+    );
+}
+
+#[test]
+fn missing_attributes_are_none() {
+    let (mut source, master_mint, _) = source();
+    source
+        .accounts
+        .remove(&attributes_address(&edition_address(&master_mint)).0);
+
+    assert!(fetch_attributes(&source, &master_mint).unwrap().is_none());
+}
+
+#[test]
+fn rejects_mint_without_edition() {
+    let (source, _, _) = source();
+    let mint = Pubkey::new_unique();
+
+    match fetch_attributes(&source, &mint) {
+        Err(ResolveError::AccountNotFound(key)) => assert_eq!(key, edition_address(&mint)),
+        other => panic!("unexpected result {:?}", other.map(|r| r.is_some())),
+    }
+}
@@ -0,0 +1,40 @@
+use charm::utils::bundle_share;
+
+#[test]
+fn bundle_shares_add_up_to_the_price() {
+    for (price, weights) in [
+        (1_000_000_001, vec![1, 1, 1]),
+        (7, vec![3, 1, 2, 5]),
+        (3, vec![0, 0, 0, 0]),
+        (10, vec![4]),
+        (u64::MAX, vec![u64::MAX, u64::MAX]),
+    ]
+    .iter()
+    {
+        let shares: Vec<u64> = (0..weights.len())
+            .map(|index| bundle_share(*price, weights, index))
+            .collect();
+
+        assert_eq!(shares.iter().sum::<u64>(), *price);
+    }
+}
+
+#[test]
+fn shares_follow_the_listing_prices() {
+    let weights = [1_000_000_000, 3_000_000_000];
+    assert_eq!(bundle_share(2_000_000_000, &weights, 0), 500_000_000);
+    assert_eq!(bundle_share(2_000_000_000, &weights, 1), 1_500_000_000);
+}
+
+#[test]
+fn last_master_takes_the_rounding_remainder() {
+    assert_eq!(bundle_share(1_000_000_001, &[1, 1, 1], 0), 333_333_333);
+    assert_eq!(bundle_share(1_000_000_001, &[1, 1, 1], 1), 333_333_333);
+    assert_eq!(bundle_share(1_000_000_001, &[1, 1, 1], 2), 333_333_335);
+}
+
+#[test]
+fn unpriced_masters_split_evenly() {
+    assert_eq!(bundle_share(7, &[0, 0], 0), 3);
+    assert_eq!(bundle_share(7, &[0, 0], 1), 4);
+}
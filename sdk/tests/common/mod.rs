@@ -0,0 +1,12 @@
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+/// Rent paying account of `owner` holding `data`.
+pub fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: 1_000_000,
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use {
+    anchor_lang::AccountSerialize,
+    borsh::BorshSerialize,
+    charm::state::ContentHash,
+    charm_sdk::{
+        content::{hash_content, verify_content, MemoryFetcher, VerifyError},
+        pda::{content_hash_address, edition_address, metadata_address},
+        resolver::MemoryAccountSource,
+    },
+    common::account,
+    solana_program::hash::hash,
+    solana_sdk::pubkey::Pubkey,
+    spl_token_metadata::state::{Data, Key, MasterEditionV2, Metadata},
+};
+
+const METADATA_URI: &str = "https://arweave.net/heart";
+const IMAGE_URI: &str = "https://arweave.net/heart.png";
+const JSON: &[u8] = br#"{"name":"Silver Heart","image":"https://arweave.net/heart.png"}"#;
+const IMAGE: &[u8] = b"\x89PNG heart";
+
+/// A master edition whose metadata uri is padded like the CLI does.
+fn source(media_hash: Option<[u8; 32]>) -> (MemoryAccountSource, Pubkey) {
+    let mint = Pubkey::new_unique();
+    let mut source = MemoryAccountSource::new();
+
+    let metadata = Metadata {
+        key: Key::MetadataV1,
+        update_authority: Pubkey::new_unique(),
+        mint,
+        data: Data {
+            name: "Silver Heart".to_owned(),
+            symbol: "CHRM".to_owned(),
+            uri: format!("{}\0\0\0", METADATA_URI),
+            seller_fee_basis_points: 20,
+            creators: None,
+        },
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+    };
+    source.insert(
+        metadata_address(&mint),
+        account(spl_token_metadata::id(), metadata.try_to_vec().unwrap()),
+    );
+    let master_edition = MasterEditionV2 {
+        key: Key::MasterEditionV2,
+        supply: 0,
+        max_supply: Some(10),
+    };
+    source.insert(
+        edition_address(&mint),
+        account(
+            spl_token_metadata::id(),
+            master_edition.try_to_vec().unwrap(),
+        ),
+    );
+
+    let content_hash = ContentHash {
+        master_edition: edition_address(&mint),
+        metadata_hash: hash(JSON).to_bytes(),
+        media_hash,
+        bump: 255,
+    };
+    let mut data = Vec::new();
+    content_hash.try_serialize(&mut data).unwrap();
+    source.insert(
+        content_hash_address(&edition_address(&mint)).0,
+        account(charm::id(), data),
+    );
+
+    (source, mint)
+}
+
+fn fetcher(json: &[u8], image: &[u8]) -> MemoryFetcher {
+    let mut fetcher = MemoryFetcher::new();
+    fetcher.insert(METADATA_URI, json.to_vec());
+    fetcher.insert(IMAGE_URI, image.to_vec());
+    fetcher
+}
+
+#[test]
+fn verifies_matching_content() {
+    let (source, mint) = source(Some(hash(IMAGE).to_bytes()));
+
+    let report = verify_content(&source, &fetcher(JSON, IMAGE), &mint).unwrap();
+
+    assert_eq!(report.metadata_uri, METADATA_URI);
+    assert_eq!(report.media_uri.as_deref(), Some(IMAGE_URI));
+    assert!(report.is_valid());
+}
+
+#[test]
+fn detects_swapped_metadata() {
+    let (source, mint) = source(None);
+    let swapped = br#"{"name":"Silver Heart","image":"https://example.com/other.png"}"#;
+
+    let report = verify_content(&source, &fetcher(swapped, IMAGE), &mint).unwrap();
+
+    assert!(!report.metadata_matches);
+    assert_eq!(report.media_matches, None);
+    assert!(!report.is_valid());
+}
+
+#[test]
+fn detects_swapped_media() {
+    let (source, mint) = source(Some(hash(IMAGE).to_bytes()));
+
+    let report = verify_content(&source, &fetcher(JSON, b"other"), &mint).unwrap();
+
+    assert!(report.metadata_matches);
+    assert_eq!(report.media_matches, Some(false));
+    assert!(!report.is_valid());
+}
+
+#[test]
+fn reports_missing_content_hash() {
+    let (mut source, mint) = source(None);
+    source
+        .accounts
+        .remove(&content_hash_address(&edition_address(&mint)).0);
+
+    match verify_content(&source, &fetcher(JSON, IMAGE), &mint) {
+        Err(VerifyError::NoContentHash(key)) => assert_eq!(key, mint),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn hashes_content_recorded_at_creation() {
+    let fetcher = fetcher(JSON, IMAGE);
+
+    let (metadata_hash, media_hash) = hash_content(&fetcher, METADATA_URI).unwrap();
+    assert_eq!(metadata_hash, hash(JSON).to_bytes());
+    assert_eq!(media_hash, Some(hash(IMAGE).to_bytes()));
+
+    let mut fetcher = MemoryFetcher::new();
+    fetcher.insert(METADATA_URI, br#"{"name":"Silver Heart"}"#.to_vec());
+    assert_eq!(hash_content(&fetcher, METADATA_URI).unwrap().1, None);
+}
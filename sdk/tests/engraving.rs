@@ -0,0 +1,63 @@
+mod common;
+
+use {
+    anchor_lang::AccountSerialize,
+    charm::state::Engraving,
+    charm_sdk::{
+        engraving::fetch_engraving,
+        pda::engraving_address,
+        resolver::{MemoryAccountSource, ResolveError},
+    },
+    common::account,
+    solana_sdk::pubkey::Pubkey,
+};
+
+#[test]
+fn reads_engraving_of_edition() {
+    let mint = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let mut data = Vec::new();
+    Engraving {
+        mint,
+        message: "For Ada, 2021".to_owned(),
+        engraved_by: holder,
+        locked: true,
+        bump: 254,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    let mut source = MemoryAccountSource::new();
+    source.insert(engraving_address(&mint).0, account(charm::id(), data));
+
+    let engraving = fetch_engraving(&source, &mint).unwrap().unwrap();
+
+    assert_eq!(engraving.message, "For Ada, 2021");
+    assert_eq!(engraving.engraved_by, holder);
+    assert!(engraving.locked);
+}
+
+#[test]
+fn unengraved_edition_is_none() {
+    let source = MemoryAccountSource::new();
+
+    assert!(fetch_engraving(&source, &Pubkey::new_unique())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn rejects_foreign_account_data() {
+    let mint = Pubkey::new_unique();
+    let mut source = MemoryAccountSource::new();
+    source.insert(
+        engraving_address(&mint).0,
+        account(charm::id(), vec![0; 16]),
+    );
+
+    match fetch_engraving(&source, &mint) {
+        Err(ResolveError::InvalidAccountData(key)) => {
+            assert_eq!(key, engraving_address(&mint).0)
+        }
+        other => panic!("unexpected result {:?}", other.map(|e| e.is_some())),
+    }
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use {
+    anchor_lang::AccountSerialize,
+    charm::state::{ListingTreasury, PlatformTreasury},
+    charm_sdk::{
+        pda::{
+            config_address, listing_address, listing_treasury_address, listing_vault_address,
+            platform_treasury_address, platform_vault_address,
+        },
+        proceeds::{
+            fetch_listing_treasury, fetch_platform_treasury, withdraw_platform_fees,
+            withdraw_proceeds,
+        },
+        resolver::MemoryAccountSource,
+    },
+    common::account,
+    solana_sdk::pubkey::Pubkey,
+    spl_associated_token_account::get_associated_token_address,
+};
+
+fn treasury_source(currency_mint: Pubkey) -> (MemoryAccountSource, Pubkey, Pubkey) {
+    let master_mint = Pubkey::new_unique();
+    let beneficiary = Pubkey::new_unique();
+    let listing = listing_address(&master_mint).0;
+    let mut data = Vec::new();
+    ListingTreasury {
+        listing,
+        currency_mint,
+        beneficiary,
+        gross_sales: 10_000,
+        fees: 250,
+        royalties: 500,
+        withdrawn: 4_000,
+        bump: 255,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    let mut source = MemoryAccountSource::new();
+    source.insert(
+        listing_treasury_address(&listing, &currency_mint).0,
+        account(charm::id(), data),
+    );
+
+    (source, master_mint, beneficiary)
+}
+
+#[test]
+fn proceeds_are_sales_minus_fees_royalties_and_withdrawals() {
+    let currency = Pubkey::new_unique();
+    let (source, master_mint, beneficiary) = treasury_source(currency);
+
+    let treasury = fetch_listing_treasury(&source, &master_mint, &currency).unwrap();
+    assert_eq!(treasury.beneficiary, beneficiary);
+    assert_eq!(treasury.available(), 5_250);
+    assert!(fetch_listing_treasury(&source, &master_mint, &Pubkey::new_unique()).is_err());
+}
+
+#[test]
+fn withdraws_to_the_beneficiary() {
+    let currency = Pubkey::new_unique();
+    let (source, master_mint, beneficiary) = treasury_source(currency);
+    let treasury = fetch_listing_treasury(&source, &master_mint, &currency).unwrap();
+
+    let instruction = withdraw_proceeds(&treasury, 5_250);
+
+    let treasury_key = listing_treasury_address(&treasury.listing, &currency).0;
+    let keys: Vec<Pubkey> = instruction
+        .accounts
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            beneficiary,
+            treasury_key,
+            listing_vault_address(&treasury_key).0,
+            get_associated_token_address(&beneficiary, &currency),
+            spl_token::id(),
+        ]
+    );
+    assert!(instruction.accounts[0].is_signer);
+
+    let native_mint = spl_token::native_mint::id();
+    let (source, master_mint, beneficiary) = treasury_source(native_mint);
+    let treasury = fetch_listing_treasury(&source, &master_mint, &native_mint).unwrap();
+    assert_eq!(
+        withdraw_proceeds(&treasury, 1).accounts[3].pubkey,
+        beneficiary
+    );
+}
+
+#[test]
+fn withdraws_platform_fees_to_the_fee_wallet() {
+    let currency = Pubkey::new_unique();
+    let fee_wallet = Pubkey::new_unique();
+    let mut data = Vec::new();
+    PlatformTreasury {
+        currency_mint: currency,
+        fees: 750,
+        withdrawn: 500,
+        bump: 255,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    let treasury_key = platform_treasury_address(&currency).0;
+    let mut source = MemoryAccountSource::new();
+    source.insert(treasury_key, account(charm::id(), data));
+
+    let treasury = fetch_platform_treasury(&source, &currency).unwrap();
+    assert_eq!(treasury.available(), 250);
+    assert!(fetch_platform_treasury(&source, &Pubkey::new_unique()).is_err());
+
+    let instruction = withdraw_platform_fees(&treasury, &fee_wallet, 250);
+    let keys: Vec<Pubkey> = instruction
+        .accounts
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            fee_wallet,
+            config_address().0,
+            treasury_key,
+            platform_vault_address(&treasury_key).0,
+            get_associated_token_address(&fee_wallet, &currency),
+            spl_token::id(),
+        ]
+    );
+    assert!(instruction.accounts[0].is_signer);
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use {
+    anchor_lang::AccountSerialize,
+    charm::state::{MintWindow, Raffle, RaffleEntry},
+    charm_sdk::{
+        pda::{raffle_address, raffle_entry_address},
+        raffle::{entry_outcome, fetch_entry, fetch_raffle, EntryOutcome},
+        resolver::MemoryAccountSource,
+    },
+    common::account,
+    solana_sdk::pubkey::Pubkey,
+};
+
+fn raffle(entry_count: u32, winner_count: u32, randomness: Option<[u8; 32]>) -> Raffle {
+    Raffle {
+        master_mint: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
+        price: 500_000_000,
+        entry_window: MintWindow { start: 0, end: 100 },
+        winner_count,
+        entry_count,
+        seed_commitment: [7; 32],
+        reveal_deadline: 200,
+        draw_slot: Some(50),
+        randomness,
+        proceeds_withdrawn: false,
+        bump: 255,
+    }
+}
+
+fn entry(index: u32, claimed: bool) -> RaffleEntry {
+    RaffleEntry {
+        raffle: Pubkey::new_unique(),
+        entrant: Pubkey::new_unique(),
+        index,
+        claimed,
+        bump: 255,
+    }
+}
+
+#[test]
+fn draws_exactly_winner_count_winners() {
+    let seed = [3; 32];
+    for slot_hash in [[0; 32], [1; 32], [0xab; 32]].iter() {
+        let randomness = charm::utils::draw_randomness(&seed, slot_hash);
+        let drawn = raffle(10, 3, Some(randomness));
+
+        let winners: Vec<u32> = (0..10)
+            .filter(|index| drawn.is_winner(*index).unwrap())
+            .collect();
+
+        assert_eq!(winners.len(), 3);
+        // The same seed and slot hash always draw the same winners.
+        let redraw = raffle(10, 3, Some(charm::utils::draw_randomness(&seed, slot_hash)));
+        assert!((0..10).all(|index| redraw.is_winner(index) == drawn.is_winner(index)));
+    }
+}
+
+#[test]
+fn winners_depend_on_slot_hash() {
+    let seed = [3; 32];
+    let draws: Vec<Vec<bool>> = (0u8..8)
+        .map(|byte| {
+            let raffle = raffle(
+                16,
+                1,
+                Some(charm::utils::draw_randomness(&seed, &[byte; 32])),
+            );
+            (0..16)
+                .map(|index| raffle.is_winner(index).unwrap())
+                .collect()
+        })
+        .collect();
+
+    assert!(draws.iter().any(|draw| draw != &draws[0]));
+}
+
+#[test]
+fn undersubscribed_raffle_makes_everyone_win() {
+    let raffle = raffle(2, 5, Some([9; 32]));
+
+    assert_eq!(raffle.winners(), 2);
+    assert_eq!(raffle.is_winner(0), Some(true));
+    assert_eq!(raffle.is_winner(1), Some(true));
+}
+
+#[test]
+fn reports_entry_outcomes() {
+    let pending = raffle(4, 1, None);
+    assert_eq!(
+        entry_outcome(&pending, &entry(0, false), 150),
+        EntryOutcome::Pending
+    );
+
+    // Little endian offset 2 of 4 entries, so only the third entry wins.
+    let mut randomness = [0; 32];
+    randomness[0] = 2;
+    let revealed = raffle(4, 1, Some(randomness));
+    assert_eq!(
+        entry_outcome(&revealed, &entry(2, false), 150),
+        EntryOutcome::Won
+    );
+    assert_eq!(
+        entry_outcome(&revealed, &entry(2, true), 150),
+        EntryOutcome::Claimed
+    );
+    assert_eq!(
+        entry_outcome(&revealed, &entry(0, false), 150),
+        EntryOutcome::Lost
+    );
+    // Revealing too late does not change who won.
+    assert_eq!(
+        entry_outcome(&revealed, &entry(0, false), 250),
+        EntryOutcome::Lost
+    );
+}
+
+#[test]
+fn unrevealed_raffle_refunds_everyone_after_its_deadline() {
+    let raffle = raffle(4, 1, None);
+
+    assert!(!raffle.is_refundable(0, 199));
+    assert!((0..4).all(|index| raffle.is_refundable(index, 200)));
+    assert_eq!(
+        entry_outcome(&raffle, &entry(0, false), 200),
+        EntryOutcome::Expired
+    );
+}
+
+fn slot_hashes(entries: &[(u64, [u8; 32])]) -> Vec<u8> {
+    let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+    for (slot, hash) in entries {
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.extend_from_slice(hash);
+    }
+    data
+}
+
+#[test]
+fn finds_the_hash_of_the_draw_slot() {
+    let key = solana_sdk::sysvar::slot_hashes::id();
+    let owner = solana_sdk::sysvar::id();
+    let mut lamports = 0;
+    let mut data = slot_hashes(&[(52, [2; 32]), (51, [1; 32]), (50, [0; 32])]);
+    let info = anchor_lang::prelude::AccountInfo::new(
+        &key,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+
+    assert_eq!(
+        charm::utils::slot_hash_at(&info, 51).unwrap(),
+        Some([1; 32])
+    );
+    // Slots not produced yet, or already dropped, have no hash to draw from.
+    assert_eq!(charm::utils::slot_hash_at(&info, 53).unwrap(), None);
+    assert_eq!(charm::utils::slot_hash_at(&info, 49).unwrap(), None);
+}
+
+#[test]
+fn fetches_raffle_and_entry() {
+    let master_mint = Pubkey::new_unique();
+    let entrant = Pubkey::new_unique();
+    let raffle_key = raffle_address(&master_mint).0;
+    let mut source = MemoryAccountSource::new();
+
+    let mut data = Vec::new();
+    Raffle {
+        master_mint,
+        ..raffle(1, 1, None)
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    source.insert(raffle_key, account(charm::id(), data));
+    let mut data = Vec::new();
+    RaffleEntry {
+        raffle: raffle_key,
+        entrant,
+        ..entry(0, false)
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    source.insert(
+        raffle_entry_address(&raffle_key, &entrant).0,
+        account(charm::id(), data),
+    );
+
+    assert_eq!(
+        fetch_raffle(&source, &master_mint).unwrap().master_mint,
+        master_mint
+    );
+    assert_eq!(
+        fetch_entry(&source, &master_mint, &entrant)
+            .unwrap()
+            .unwrap()
+            .entrant,
+        entrant
+    );
+    assert!(fetch_entry(&source, &master_mint, &Pubkey::new_unique())
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,516 @@
+mod common;
+
+use {
+    anchor_lang::{AccountDeserialize, AccountSerialize},
+    borsh::BorshSerialize,
+    charm::state::{Listing, ListingPrice, MintWindow, PassTerms},
+    charm_sdk::{
+        pda::{
+            charm_pda, config_address, edition_address, edition_marker_address, expiry_address,
+            listing_address, listing_treasury_address, listing_vault_address, metadata_address,
+            platform_treasury_address, platform_vault_address, raffle_address,
+            raffle_entry_address, referrer_stats_address, royalty_treasury_address,
+            royalty_vault_address, soulbound_edition_address,
+        },
+        resolver::{resolve_buy, MemoryAccountSource, ResolveError},
+    },
+    common::account,
+    solana_program::{program_option::COption, program_pack::Pack},
+    solana_sdk::{pubkey::Pubkey, signature::Signer},
+    spl_associated_token_account::get_associated_token_address,
+    spl_token::instruction::TokenInstruction,
+    spl_token::state::{Account as TokenAccount, AccountState},
+    spl_token_metadata::state::{Data, Key, MasterEditionV2, Metadata},
+};
+
+struct Master {
+    mint: Pubkey,
+    update_authority: Pubkey,
+    token_account: Pubkey,
+}
+
+fn master_source(
+    supply: u64,
+    max_supply: Option<u64>,
+    token_owner: Pubkey,
+) -> (MemoryAccountSource, Master) {
+    let master = Master {
+        mint: Pubkey::new_unique(),
+        update_authority: Pubkey::new_unique(),
+        token_account: Pubkey::new_unique(),
+    };
+    let mut source = MemoryAccountSource::new();
+
+    let metadata = Metadata {
+        key: Key::MetadataV1,
+        update_authority: master.update_authority,
+        mint: master.mint,
+        data: Data {
+            name: "Silver Heart".to_owned(),
+            symbol: "CHRM".to_owned(),
+            uri: "https://arweave.net/heart".to_owned(),
+            seller_fee_basis_points: 20,
+            creators: None,
+        },
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+    };
+    source.insert(
+        metadata_address(&master.mint),
+        account(spl_token_metadata::id(), metadata.try_to_vec().unwrap()),
+    );
+
+    let master_edition = MasterEditionV2 {
+        key: Key::MasterEditionV2,
+        supply,
+        max_supply,
+    };
+    source.insert(
+        edition_address(&master.mint),
+        account(
+            spl_token_metadata::id(),
+            master_edition.try_to_vec().unwrap(),
+        ),
+    );
+
+    let mut token_data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint: master.mint,
+        owner: token_owner,
+        amount: 1,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut token_data);
+    source.insert(master.token_account, account(spl_token::id(), token_data));
+
+    insert_listing(&mut source, &master, false, None);
+
+    (source, master)
+}
+
+fn insert_listing(
+    source: &mut MemoryAccountSource,
+    master: &Master,
+    soulbound: bool,
+    pass: Option<PassTerms>,
+) {
+    let mut data = Vec::new();
+    Listing {
+        master_mint: master.mint,
+        authority: master.update_authority,
+        soulbound,
+        pass,
+        window: None,
+        closed: false,
+        raffled: false,
+        referral_bps: None,
+        prices: Vec::new(),
+        bump: 255,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    source.insert(listing_address(&master.mint).0, account(charm::id(), data));
+}
+
+/// Prices the listing of `master`.
+fn insert_prices(source: &mut MemoryAccountSource, master: &Master, prices: &[(Pubkey, u64)]) {
+    let listing_key = listing_address(&master.mint).0;
+    let mut data = Vec::new();
+    Listing {
+        prices: prices
+            .iter()
+            .map(|&(mint, amount)| ListingPrice { mint, amount })
+            .collect(),
+        ..Listing::try_deserialize(&mut &source.accounts[&listing_key].data[..]).unwrap()
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    source.insert(listing_key, account(charm::id(), data));
+}
+
+/// Freeze authority set by the `initialize_mint` setup instruction.
+fn freeze_authority(resolved: &charm_sdk::resolver::ResolvedBuy) -> COption<Pubkey> {
+    match TokenInstruction::unpack(&resolved.setup_instructions[1].data).unwrap() {
+        TokenInstruction::InitializeMint {
+            freeze_authority, ..
+        } => freeze_authority,
+        _ => panic!("second setup instruction is not initialize_mint"),
+    }
+}
+
+#[test]
+fn resolves_next_edition_from_master_mint() {
+    let buyer = Pubkey::new_unique();
+    let (pda, pda_bump) = charm_pda();
+    let (source, master) = master_source(3, Some(10), pda);
+
+    let resolved = resolve_buy(&source, &master.mint, &buyer, None, None).unwrap();
+    let new_mint = resolved.new_mint.pubkey();
+
+    assert_eq!(resolved.edition, 4);
+    assert_eq!(resolved.new_metadata, metadata_address(&new_mint));
+    assert_eq!(resolved.new_edition, edition_address(&new_mint));
+    assert_eq!(
+        resolved.new_token_account,
+        get_associated_token_address(&buyer, &new_mint)
+    );
+    assert_eq!(resolved.setup_instructions.len(), 4);
+    assert_eq!(resolved.instructions().len(), 5);
+
+    let native_mint = spl_token::native_mint::id();
+    let treasury = royalty_treasury_address(&edition_address(&master.mint), &native_mint).0;
+    let listing_treasury =
+        listing_treasury_address(&listing_address(&master.mint).0, &native_mint).0;
+    let platform_treasury = platform_treasury_address(&native_mint).0;
+    let instruction = &resolved.instruction;
+    assert_eq!(instruction.program_id, charm::id());
+    let keys: Vec<Pubkey> = instruction
+        .accounts
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            buyer,
+            spl_token_metadata::id(),
+            metadata_address(&new_mint),
+            edition_address(&new_mint),
+            edition_address(&master.mint),
+            new_mint,
+            buyer,
+            pda,
+            master.token_account,
+            master.update_authority,
+            metadata_address(&master.mint),
+            master.mint,
+            solana_sdk::system_program::id(),
+            solana_sdk::sysvar::rent::id(),
+            edition_marker_address(&master.mint, 4),
+            spl_token::id(),
+            listing_address(&master.mint).0,
+            resolved.new_token_account,
+            pda,
+            expiry_address(&new_mint).0,
+            soulbound_edition_address(&new_mint).0,
+            // Free listing, the buyer stands in for the payment accounts.
+            config_address().0,
+            buyer,
+            listing_treasury,
+            listing_vault_address(&listing_treasury).0,
+            platform_treasury,
+            platform_vault_address(&platform_treasury).0,
+            treasury,
+            royalty_vault_address(&treasury).0,
+        ]
+    );
+    assert!(!resolved.soulbound);
+    assert!(!resolved.pass);
+    assert_eq!(freeze_authority(&resolved), COption::Some(buyer));
+    // Anchor sighash, then the borsh encoded edition and pda nonce.
+    assert_eq!(instruction.data[8..16], 4u64.to_le_bytes());
+    assert_eq!(instruction.data[16], pda_bump);
+}
+
+#[test]
+fn soulbound_listing_mints_into_custody_of_the_soulbound_record() {
+    let buyer = Pubkey::new_unique();
+    let (mut source, master) = master_source(0, Some(10), charm_pda().0);
+    insert_listing(&mut source, &master, true, None);
+
+    let resolved = resolve_buy(&source, &master.mint, &buyer, None, None).unwrap();
+    let new_mint = resolved.new_mint.pubkey();
+
+    assert!(resolved.soulbound);
+    assert_eq!(
+        resolved.new_token_account,
+        get_associated_token_address(&soulbound_edition_address(&new_mint).0, &new_mint)
+    );
+    assert_eq!(freeze_authority(&resolved), COption::Some(buyer));
+}
+
+#[test]
+fn pass_listing_sells_passes() {
+    let buyer = Pubkey::new_unique();
+    let (mut source, master) = master_source(0, None, charm_pda().0);
+    let terms = PassTerms {
+        duration: 30 * 24 * 60 * 60,
+        renewal_price: 1_000_000,
+    };
+    insert_listing(&mut source, &master, false, Some(terms));
+
+    let resolved = resolve_buy(&source, &master.mint, &buyer, None, None).unwrap();
+
+    assert!(resolved.pass);
+    assert_eq!(
+        resolved.instruction.accounts[19].pubkey,
+        expiry_address(&resolved.new_mint.pubkey()).0
+    );
+    assert!(resolved.instruction.accounts[19].is_writable);
+}
+
+#[test]
+fn raffled_listing_passes_entry_of_buyer() {
+    let buyer = Pubkey::new_unique();
+    let (mut source, master) = master_source(0, Some(10), charm_pda().0);
+    let mut data = Vec::new();
+    Listing {
+        master_mint: master.mint,
+        authority: master.update_authority,
+        soulbound: false,
+        pass: None,
+        window: None,
+        closed: false,
+        raffled: true,
+        referral_bps: None,
+        prices: vec![ListingPrice {
+            mint: spl_token::native_mint::id(),
+            amount: 1_000,
+        }],
+        bump: 255,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    source.insert(listing_address(&master.mint).0, account(charm::id(), data));
+
+    let resolved = resolve_buy(&source, &master.mint, &buyer, None, None).unwrap();
+
+    // Winners paid the raffle price when entering.
+    assert_eq!(resolved.payment, None);
+    let raffle = raffle_address(&master.mint).0;
+    let accounts = &resolved.instruction.accounts[29..];
+    assert_eq!(accounts.len(), 2);
+    assert_eq!(accounts[0].pubkey, raffle);
+    assert_eq!(accounts[1].pubkey, raffle_entry_address(&raffle, &buyer).0);
+    assert!(accounts[1].is_writable);
+}
+
+#[test]
+fn referrer_follows_listing_accounts() {
+    let buyer = Pubkey::new_unique();
+    let referrer = Pubkey::new_unique();
+    let (source, master) = master_source(0, Some(10), charm_pda().0);
+
+    let resolved = resolve_buy(&source, &master.mint, &buyer, None, None)
+        .unwrap()
+        .with_referrer(&referrer);
+
+    let accounts = &resolved.instruction.accounts[29..];
+    assert_eq!(accounts.len(), 2);
+    assert_eq!(accounts[0].pubkey, referrer);
+    assert_eq!(accounts[1].pubkey, referrer_stats_address(&referrer).0);
+    assert!(accounts[1].is_writable);
+}
+
+#[test]
+fn sol_listing_pays_wallets_and_royalty_treasury() {
+    let buyer = Pubkey::new_unique();
+    let referrer = Pubkey::new_unique();
+    let native_mint = spl_token::native_mint::id();
+    let (mut source, master) = master_source(0, Some(10), charm_pda().0);
+    insert_prices(
+        &mut source,
+        &master,
+        &[(Pubkey::new_unique(), 5_000), (native_mint, 1_000_000_000)],
+    );
+
+    let resolved = resolve_buy(&source, &master.mint, &buyer, None, Some(&native_mint))
+        .unwrap()
+        .with_referrer(&referrer);
+
+    let treasury = royalty_treasury_address(&edition_address(&master.mint), &native_mint).0;
+    let accounts = &resolved.instruction.accounts;
+    assert_eq!(
+        resolved.payment,
+        Some(ListingPrice {
+            mint: native_mint,
+            amount: 1_000_000_000
+        })
+    );
+    let listing_treasury =
+        listing_treasury_address(&listing_address(&master.mint).0, &native_mint).0;
+    assert_eq!(accounts[22].pubkey, buyer);
+    assert_eq!(accounts[23].pubkey, listing_treasury);
+    assert_eq!(
+        accounts[25].pubkey,
+        platform_treasury_address(&native_mint).0
+    );
+    assert_eq!(accounts[27].pubkey, treasury);
+    assert_eq!(accounts[28].pubkey, royalty_vault_address(&treasury).0);
+    assert!(accounts[22..29].iter().all(|meta| meta.is_writable));
+    assert_eq!(accounts[29].pubkey, referrer);
+    assert!(accounts[29].is_writable);
+    // Anchor sighash, edition, pda nonce, then the chosen currency.
+    assert_eq!(resolved.instruction.data[17], 1);
+    assert_eq!(resolved.instruction.data[18..50], native_mint.to_bytes());
+}
+
+#[test]
+fn token_listing_pays_associated_token_accounts() {
+    let buyer = Pubkey::new_unique();
+    let referrer = Pubkey::new_unique();
+    let currency = Pubkey::new_unique();
+    let (mut source, master) = master_source(0, Some(10), charm_pda().0);
+    insert_prices(
+        &mut source,
+        &master,
+        &[
+            (spl_token::native_mint::id(), 1_000_000_000),
+            (currency, 5_000),
+        ],
+    );
+
+    let resolved = resolve_buy(&source, &master.mint, &buyer, None, Some(&currency))
+        .unwrap()
+        .with_referrer(&referrer);
+
+    let treasury = royalty_treasury_address(&edition_address(&master.mint), &currency).0;
+    let accounts = &resolved.instruction.accounts;
+    assert_eq!(resolved.payment.unwrap().amount, 5_000);
+    assert_eq!(
+        accounts[22].pubkey,
+        get_associated_token_address(&buyer, &currency)
+    );
+    let listing_treasury = listing_treasury_address(&listing_address(&master.mint).0, &currency).0;
+    assert_eq!(accounts[23].pubkey, listing_treasury);
+    assert_eq!(
+        accounts[24].pubkey,
+        listing_vault_address(&listing_treasury).0
+    );
+    let platform_treasury = platform_treasury_address(&currency).0;
+    assert_eq!(accounts[25].pubkey, platform_treasury);
+    assert_eq!(
+        accounts[26].pubkey,
+        platform_vault_address(&platform_treasury).0
+    );
+    assert_eq!(accounts[27].pubkey, treasury);
+    assert_eq!(
+        accounts[29].pubkey,
+        get_associated_token_address(&referrer, &currency)
+    );
+    assert_eq!(accounts[30].pubkey, referrer_stats_address(&referrer).0);
+}
+
+#[test]
+fn paid_listing_requires_accepted_currency() {
+    let buyer = Pubkey::new_unique();
+    let currency = Pubkey::new_unique();
+    let (mut source, master) = master_source(0, Some(10), charm_pda().0);
+    insert_prices(&mut source, &master, &[(currency, 5_000)]);
+
+    for requested in [None, Some(spl_token::native_mint::id())] {
+        match resolve_buy(&source, &master.mint, &buyer, None, requested.as_ref()) {
+            Err(ResolveError::CurrencyNotAccepted {
+                currency: rejected,
+                accepted,
+                ..
+            }) => {
+                assert_eq!(rejected, requested);
+                assert_eq!(accepted, vec![currency]);
+            }
+            other => panic!("expected CurrencyNotAccepted, got {:?}", other.err()),
+        }
+    }
+}
+
+#[test]
+fn requires_a_listing() {
+    let buyer = Pubkey::new_unique();
+    let (mut source, master) = master_source(0, Some(10), charm_pda().0);
+    source.accounts.remove(&listing_address(&master.mint).0);
+
+    match resolve_buy(&source, &master.mint, &buyer, None, None) {
+        Err(ResolveError::AccountNotFound(key)) => {
+            assert_eq!(key, listing_address(&master.mint).0)
+        }
+        other => panic!("unexpected result {:?}", other.map(|r| r.edition)),
+    }
+}
+
+#[test]
+fn rejects_closed_listing() {
+    let buyer = Pubkey::new_unique();
+    let (mut source, master) = master_source(7, None, charm_pda().0);
+    let listing_key = listing_address(&master.mint).0;
+    let mut data = Vec::new();
+    Listing {
+        master_mint: master.mint,
+        authority: master.update_authority,
+        soulbound: false,
+        pass: None,
+        window: Some(MintWindow { start: 0, end: 1 }),
+        closed: true,
+        raffled: false,
+        referral_bps: None,
+        prices: Vec::new(),
+        bump: 255,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    source.insert(listing_key, account(charm::id(), data));
+
+    match resolve_buy(&source, &master.mint, &buyer, None, None) {
+        Err(ResolveError::ListingClosed(key)) => assert_eq!(key, listing_key),
+        other => panic!("unexpected result {:?}", other.map(|r| r.edition)),
+    }
+}
+
+#[test]
+fn uses_requested_edition_marker() {
+    let buyer = Pubkey::new_unique();
+    let (source, master) = master_source(0, None, charm_pda().0);
+
+    let resolved = resolve_buy(&source, &master.mint, &buyer, Some(300), None).unwrap();
+
+    assert_eq!(resolved.edition, 300);
+    assert_eq!(
+        resolved.instruction.accounts[14].pubkey,
+        edition_marker_address(&master.mint, 300)
+    );
+    assert_ne!(
+        edition_marker_address(&master.mint, 300),
+        edition_marker_address(&master.mint, 1)
+    );
+}
+
+#[test]
+fn rejects_edition_beyond_max_supply() {
+    let buyer = Pubkey::new_unique();
+    let (source, master) = master_source(1, Some(1), charm_pda().0);
+
+    match resolve_buy(&source, &master.mint, &buyer, None, None) {
+        Err(ResolveError::EditionOutOfRange {
+            edition: 2,
+            max_supply: Some(1),
+        }) => {}
+        other => panic!("unexpected result {:?}", other.map(|r| r.edition)),
+    }
+}
+
+#[test]
+fn rejects_master_token_held_by_third_party() {
+    let buyer = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let (source, master) = master_source(0, Some(5), holder);
+
+    match resolve_buy(&source, &master.mint, &buyer, None, None) {
+        Err(ResolveError::MasterTokenNotEscrowed(owner)) => assert_eq!(owner, holder),
+        other => panic!("unexpected result {:?}", other.map(|r| r.edition)),
+    }
+}
+
+#[test]
+fn reports_missing_master() {
+    let source = MemoryAccountSource::new();
+    let mint = Pubkey::new_unique();
+
+    match resolve_buy(&source, &mint, &Pubkey::new_unique(), None, None) {
+        Err(ResolveError::AccountNotFound(key)) => assert_eq!(key, metadata_address(&mint)),
+        other => panic!("unexpected result {:?}", other.map(|r| r.edition)),
+    }
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use {
+    anchor_lang::AccountSerialize,
+    borsh::BorshSerialize,
+    charm::state::{CreatorClaim, RoyaltyTreasury},
+    charm_sdk::{
+        pda::{edition_address, metadata_address, royalty_treasury_address},
+        resolver::MemoryAccountSource,
+        royalties::claimable_royalties,
+    },
+    common::account,
+    solana_sdk::pubkey::Pubkey,
+    spl_token_metadata::state::{Creator, Data, Key, Metadata},
+};
+
+fn treasury_source(
+    creators: Vec<Creator>,
+    total_received: u64,
+    claims: Vec<CreatorClaim>,
+) -> (MemoryAccountSource, Pubkey, Pubkey) {
+    let master_mint = Pubkey::new_unique();
+    let currency_mint = Pubkey::new_unique();
+    let mut source = MemoryAccountSource::new();
+
+    let metadata = Metadata {
+        key: Key::MetadataV1,
+        update_authority: Pubkey::new_unique(),
+        mint: master_mint,
+        data: Data {
+            name: "Silver Heart".to_owned(),
+            symbol: "CHRM".to_owned(),
+            uri: "https://arweave.net/heart".to_owned(),
+            seller_fee_basis_points: 500,
+            creators: Some(creators),
+        },
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+    };
+    source.insert(
+        metadata_address(&master_mint),
+        account(spl_token_metadata::id(), metadata.try_to_vec().unwrap()),
+    );
+
+    let master_edition = edition_address(&master_mint);
+    let mut data = Vec::new();
+    RoyaltyTreasury {
+        master_edition,
+        currency_mint,
+        total_received,
+        claims,
+        bump: 255,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    source.insert(
+        royalty_treasury_address(&master_edition, &currency_mint).0,
+        account(charm::id(), data),
+    );
+
+    (source, master_mint, currency_mint)
+}
+
+#[test]
+fn splits_treasury_by_creator_share_minus_claims() {
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let creators = vec![
+        Creator {
+            address: alice,
+            verified: true,
+            share: 70,
+        },
+        Creator {
+            address: bob,
+            verified: true,
+            share: 30,
+        },
+    ];
+    let claims = vec![
+        CreatorClaim {
+            creator: alice,
+            share: 70,
+            claimed: 400,
+        },
+        CreatorClaim {
+            creator: bob,
+            share: 30,
+            claimed: 0,
+        },
+    ];
+    let (source, master_mint, currency_mint) = treasury_source(creators, 1_001, claims);
+
+    let claimable =
+        |creator| claimable_royalties(&source, &master_mint, &currency_mint, creator).unwrap();
+    assert_eq!(claimable(&alice), Some(300));
+    assert_eq!(claimable(&bob), Some(300));
+    assert_eq!(claimable(&Pubkey::new_unique()), None);
+}
+
+#[test]
+fn unverified_creator_can_not_claim() {
+    let carol = Pubkey::new_unique();
+    let creators = vec![Creator {
+        address: carol,
+        verified: false,
+        share: 100,
+    }];
+    let claims = vec![CreatorClaim {
+        creator: carol,
+        share: 100,
+        claimed: 0,
+    }];
+    let (source, master_mint, currency_mint) = treasury_source(creators, 5_000, claims);
+
+    assert_eq!(
+        claimable_royalties(&source, &master_mint, &currency_mint, &carol).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn shares_are_the_ones_at_treasury_creation() {
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    // The update authority moved every share to alice after the treasury was created.
+    let creators = vec![
+        Creator {
+            address: alice,
+            verified: true,
+            share: 100,
+        },
+        Creator {
+            address: bob,
+            verified: true,
+            share: 0,
+        },
+    ];
+    let claims = vec![
+        CreatorClaim {
+            creator: alice,
+            share: 50,
+            claimed: 0,
+        },
+        CreatorClaim {
+            creator: bob,
+            share: 50,
+            claimed: 500,
+        },
+    ];
+    let (source, master_mint, currency_mint) = treasury_source(creators, 1_000, claims);
+
+    let claimable =
+        |creator| claimable_royalties(&source, &master_mint, &currency_mint, creator).unwrap();
+    assert_eq!(claimable(&alice), Some(500));
+    assert_eq!(claimable(&bob), Some(0));
+}
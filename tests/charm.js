@@ -6,9 +6,23 @@ describe('charm', () => {
   anchor.setProvider(anchor.Provider.env());
 
   it('Is initialized!', async () => {
-    // Add your test here.
     const program = anchor.workspace.Charm;
-    const tx = await program.rpc.initialize();
+    const [config, bump] = await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from("config")],
+      program.programId
+    );
+    const [programData] = await anchor.web3.PublicKey.findProgramAddress(
+      [program.programId.toBuffer()],
+      new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    const tx = await program.rpc.initialize(bump, {
+      accounts: {
+        config,
+        admin: program.provider.wallet.publicKey,
+        programData,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      },
+    });
     console.log("Your transaction signature", tx);
   });
 });